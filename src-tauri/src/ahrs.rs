@@ -0,0 +1,502 @@
+//! Host-side attitude filter (Madgwick / Mahony) over the raw HI91 channels
+//!
+//! The filters are the reference MadgwickAHRS / MahonyAHRS updates, which
+//! assume an Earth frame with x pointing to magnetic north (NWU). Output is
+//! rotated into ENU and converted to Euler angles with the same ZXY convention
+//! the HI12 uses for `roll`/`pitch`/`yaw`, so the host and device attitude can
+//! be compared field by field.
+//!
+//! The time step comes from the device `system_time`, not from host arrival
+//! time, so USB batching does not distort the integration.
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::Hi91Data;
+
+const DEG_TO_RAD: f64 = std::f64::consts::PI / 180.0;
+const RAD_TO_DEG: f64 = 180.0 / std::f64::consts::PI;
+
+/// Steps longer than this (device reset, long gap) re-initialise the filter
+const MAX_DT_S: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AhrsAlgorithm {
+    Madgwick,
+    Mahony,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AhrsConfig {
+    pub algorithm: AhrsAlgorithm,
+    /// Madgwick gradient-descent gain
+    pub beta: f64,
+    /// Mahony proportional gain
+    pub kp: f64,
+    /// Mahony integral gain (gyro bias estimation; 0 disables)
+    pub ki: f64,
+    /// Use the magnetometer for heading; false runs the 6-axis variant
+    pub use_mag: bool,
+}
+
+impl Default for AhrsConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: AhrsAlgorithm::Madgwick,
+            beta: 0.1,
+            kp: 1.0,
+            ki: 0.0,
+            use_mag: true,
+        }
+    }
+}
+
+/// Payload of the "imu-ahrs" event: host estimate next to the device's own
+#[derive(Debug, Clone, Serialize)]
+pub struct AhrsSample {
+    pub system_time: u32,
+    pub algorithm: AhrsAlgorithm,
+    /// Host estimate, ENU, (w, x, y, z)
+    pub quat: [f64; 4],
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+    pub device_quat: [f64; 4],
+    pub device_roll: f64,
+    pub device_pitch: f64,
+    pub device_yaw: f64,
+    /// Full rotation between host and device attitude (deg)
+    pub attitude_error_deg: f64,
+    /// Angle between host and device gravity direction, ignoring heading (deg)
+    pub tilt_error_deg: f64,
+}
+
+pub struct Ahrs {
+    config: AhrsConfig,
+    /// Body -> NWU
+    q: [f64; 4],
+    /// Mahony integral feedback
+    integral: [f64; 3],
+    last_system_time: Option<u32>,
+}
+
+impl Ahrs {
+    pub fn new(config: AhrsConfig) -> Self {
+        Self {
+            config,
+            q: [1.0, 0.0, 0.0, 0.0],
+            integral: [0.0; 3],
+            last_system_time: None,
+        }
+    }
+
+    /// Change gains or algorithm without losing the current attitude
+    pub fn set_config(&mut self, config: AhrsConfig) {
+        if config.algorithm != self.config.algorithm {
+            self.integral = [0.0; 3];
+        }
+        self.config = config;
+    }
+
+    /// Feed one sample; the first sample (and any after a long gap) only
+    /// initialises the attitude from acc/mag. A sample repeating the previous
+    /// `system_time` (the same reading sent again as HI92) returns None.
+    pub fn update(&mut self, s: &Hi91Data) -> Option<AhrsSample> {
+        if self.last_system_time == Some(s.system_time) {
+            return None;
+        }
+        let dt = self
+            .last_system_time
+            .filter(|&last| s.system_time > last)
+            .map(|last| (s.system_time - last) as f64 / 1000.0)
+            .filter(|&dt| dt <= MAX_DT_S);
+        self.last_system_time = Some(s.system_time);
+
+        let mag = if self.config.use_mag { s.mag } else { [0.0; 3] };
+        match dt {
+            None => {
+                self.q = initial_attitude(s.acc, mag);
+                self.integral = [0.0; 3];
+            }
+            Some(dt) => {
+                let gyr = [
+                    s.gyr[0] * DEG_TO_RAD,
+                    s.gyr[1] * DEG_TO_RAD,
+                    s.gyr[2] * DEG_TO_RAD,
+                ];
+                match self.config.algorithm {
+                    AhrsAlgorithm::Madgwick => self.madgwick(gyr, s.acc, mag, dt),
+                    AhrsAlgorithm::Mahony => self.mahony(gyr, s.acc, mag, dt),
+                }
+            }
+        }
+
+        let quat = self.quat_enu();
+        let (roll, pitch, yaw) = quat_to_euler(quat);
+        let device_quat = normalize4(s.quat);
+        Some(AhrsSample {
+            system_time: s.system_time,
+            algorithm: self.config.algorithm,
+            quat,
+            roll,
+            pitch,
+            yaw,
+            device_quat,
+            device_roll: s.roll,
+            device_pitch: s.pitch,
+            device_yaw: s.yaw,
+            attitude_error_deg: quat_angle_between(quat, device_quat),
+            tilt_error_deg: tilt_angle_between(quat, device_quat),
+        })
+    }
+
+    /// Current attitude, body -> ENU
+    pub fn quat_enu(&self) -> [f64; 4] {
+        // ENU = NWU rotated +90 deg about z
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        quat_mul([h, 0.0, 0.0, h], self.q)
+    }
+
+    /// MadgwickAHRSupdate / MadgwickAHRSupdateIMU
+    fn madgwick(&mut self, g: [f64; 3], a: [f64; 3], m: [f64; 3], dt: f64) {
+        let [mut q0, mut q1, mut q2, mut q3] = self.q;
+        let (gx, gy, gz) = (g[0], g[1], g[2]);
+
+        // Rate of change of quaternion from gyroscope
+        let mut q_dot1 = 0.5 * (-q1 * gx - q2 * gy - q3 * gz);
+        let mut q_dot2 = 0.5 * (q0 * gx + q2 * gz - q3 * gy);
+        let mut q_dot3 = 0.5 * (q0 * gy - q1 * gz + q3 * gx);
+        let mut q_dot4 = 0.5 * (q0 * gz + q1 * gy - q2 * gx);
+
+        if let Some([ax, ay, az]) = normalize3(a) {
+            let (s0, s1, s2, s3) = match normalize3(m) {
+                Some([mx, my, mz]) => {
+                    // Reference direction of Earth's magnetic field
+                    let hx = 2.0 * (mx * (0.5 - q2 * q2 - q3 * q3) + my * (q1 * q2 - q0 * q3) + mz * (q1 * q3 + q0 * q2));
+                    let hy = 2.0 * (mx * (q1 * q2 + q0 * q3) + my * (0.5 - q1 * q1 - q3 * q3) + mz * (q2 * q3 - q0 * q1));
+                    // 2·bx and 2·bz, b being the measured field rotated into the earth frame
+                    let _2bx = (hx * hx + hy * hy).sqrt() * 2.0;
+                    let _2bz = 4.0 * (mx * (q1 * q3 - q0 * q2) + my * (q2 * q3 + q0 * q1) + mz * (0.5 - q1 * q1 - q2 * q2));
+                    let _4bx = 2.0 * _2bx;
+                    let _4bz = 2.0 * _2bz;
+
+                    // Gradient descent corrective step
+                    let f1 = 2.0 * (q1 * q3 - q0 * q2) - ax;
+                    let f2 = 2.0 * (q0 * q1 + q2 * q3) - ay;
+                    let f3 = 1.0 - 2.0 * (q1 * q1 + q2 * q2) - az;
+                    let f4 = _2bx * (0.5 - q2 * q2 - q3 * q3) + _2bz * (q1 * q3 - q0 * q2) - mx;
+                    let f5 = _2bx * (q1 * q2 - q0 * q3) + _2bz * (q0 * q1 + q2 * q3) - my;
+                    let f6 = _2bx * (q0 * q2 + q1 * q3) + _2bz * (0.5 - q1 * q1 - q2 * q2) - mz;
+
+                    let s0 = -2.0 * q2 * f1 + 2.0 * q1 * f2 - _2bz * q2 * f4
+                        + (-_2bx * q3 + _2bz * q1) * f5
+                        + _2bx * q2 * f6;
+                    let s1 = 2.0 * q3 * f1 + 2.0 * q0 * f2 - 4.0 * q1 * f3
+                        + _2bz * q3 * f4
+                        + (_2bx * q2 + _2bz * q0) * f5
+                        + (_2bx * q3 - _4bz * q1) * f6;
+                    let s2 = -2.0 * q0 * f1 + 2.0 * q3 * f2 - 4.0 * q2 * f3
+                        + (-_4bx * q2 - _2bz * q0) * f4
+                        + (_2bx * q1 + _2bz * q3) * f5
+                        + (_2bx * q0 - _4bz * q2) * f6;
+                    let s3 = 2.0 * q1 * f1 + 2.0 * q2 * f2
+                        + (-_4bx * q3 + _2bz * q1) * f4
+                        + (-_2bx * q0 + _2bz * q2) * f5
+                        + _2bx * q1 * f6;
+                    (s0, s1, s2, s3)
+                }
+                None => {
+                    let f1 = 2.0 * (q1 * q3 - q0 * q2) - ax;
+                    let f2 = 2.0 * (q0 * q1 + q2 * q3) - ay;
+                    let f3 = 1.0 - 2.0 * (q1 * q1 + q2 * q2) - az;
+                    (
+                        -2.0 * q2 * f1 + 2.0 * q1 * f2,
+                        2.0 * q3 * f1 + 2.0 * q0 * f2 - 4.0 * q1 * f3,
+                        -2.0 * q0 * f1 + 2.0 * q3 * f2 - 4.0 * q2 * f3,
+                        2.0 * q1 * f1 + 2.0 * q2 * f2,
+                    )
+                }
+            };
+
+            if let Some([s0, s1, s2, s3]) = normalize4_opt([s0, s1, s2, s3]) {
+                let beta = self.config.beta;
+                q_dot1 -= beta * s0;
+                q_dot2 -= beta * s1;
+                q_dot3 -= beta * s2;
+                q_dot4 -= beta * s3;
+            }
+        }
+
+        q0 += q_dot1 * dt;
+        q1 += q_dot2 * dt;
+        q2 += q_dot3 * dt;
+        q3 += q_dot4 * dt;
+        self.q = normalize4([q0, q1, q2, q3]);
+    }
+
+    /// MahonyAHRSupdate / MahonyAHRSupdateIMU
+    fn mahony(&mut self, g: [f64; 3], a: [f64; 3], m: [f64; 3], dt: f64) {
+        let [q0, q1, q2, q3] = self.q;
+        let [mut gx, mut gy, mut gz] = g;
+
+        if let Some([ax, ay, az]) = normalize3(a) {
+            // Estimated direction of gravity
+            let vx = q1 * q3 - q0 * q2;
+            let vy = q0 * q1 + q2 * q3;
+            let vz = q0 * q0 - 0.5 + q3 * q3;
+            let mut ex = ay * vz - az * vy;
+            let mut ey = az * vx - ax * vz;
+            let mut ez = ax * vy - ay * vx;
+
+            if let Some([mx, my, mz]) = normalize3(m) {
+                // Reference direction of Earth's magnetic field
+                let hx = 2.0 * (mx * (0.5 - q2 * q2 - q3 * q3) + my * (q1 * q2 - q0 * q3) + mz * (q1 * q3 + q0 * q2));
+                let hy = 2.0 * (mx * (q1 * q2 + q0 * q3) + my * (0.5 - q1 * q1 - q3 * q3) + mz * (q2 * q3 - q0 * q1));
+                let bx = (hx * hx + hy * hy).sqrt();
+                let bz = 2.0 * (mx * (q1 * q3 - q0 * q2) + my * (q2 * q3 + q0 * q1) + mz * (0.5 - q1 * q1 - q2 * q2));
+
+                // Estimated direction of magnetic field
+                let wx = bx * (0.5 - q2 * q2 - q3 * q3) + bz * (q1 * q3 - q0 * q2);
+                let wy = bx * (q1 * q2 - q0 * q3) + bz * (q0 * q1 + q2 * q3);
+                let wz = bx * (q0 * q2 + q1 * q3) + bz * (0.5 - q1 * q1 - q2 * q2);
+                ex += my * wz - mz * wy;
+                ey += mz * wx - mx * wz;
+                ez += mx * wy - my * wx;
+            }
+
+            // The reference code works with half-vectors; the factor 2 restores the full error
+            let (kp, ki) = (self.config.kp, self.config.ki);
+            if ki > 0.0 {
+                self.integral[0] += 2.0 * ki * ex * dt;
+                self.integral[1] += 2.0 * ki * ey * dt;
+                self.integral[2] += 2.0 * ki * ez * dt;
+                gx += self.integral[0];
+                gy += self.integral[1];
+                gz += self.integral[2];
+            } else {
+                self.integral = [0.0; 3];
+            }
+            gx += 2.0 * kp * ex;
+            gy += 2.0 * kp * ey;
+            gz += 2.0 * kp * ez;
+        }
+
+        let (gx, gy, gz) = (gx * 0.5 * dt, gy * 0.5 * dt, gz * 0.5 * dt);
+        self.q = normalize4([
+            q0 - q1 * gx - q2 * gy - q3 * gz,
+            q1 + q0 * gx + q2 * gz - q3 * gy,
+            q2 + q0 * gy - q1 * gz + q3 * gx,
+            q3 + q0 * gz + q1 * gy - q2 * gx,
+        ]);
+    }
+}
+
+/// Attitude (body -> NWU) from gravity and, if available, the magnetic field
+fn initial_attitude(acc: [f64; 3], mag: [f64; 3]) -> [f64; 4] {
+    let Some(up) = normalize3(acc) else {
+        return [1.0, 0.0, 0.0, 0.0];
+    };
+    // East = m x up; without a usable field the forward (body y) axis is north
+    let east = normalize3(cross(mag, up)).unwrap_or_else(|| {
+        let north = [-up[1] * up[0], 1.0 - up[1] * up[1], -up[1] * up[2]];
+        normalize3(cross(north, up)).unwrap_or([1.0, 0.0, 0.0])
+    });
+    let north = cross(up, east);
+    let west = [-east[0], -east[1], -east[2]];
+    // Rows are the NWU axes expressed in the body frame
+    rotation_to_quat([north, west, up])
+}
+
+fn rotation_to_quat(r: [[f64; 3]; 3]) -> [f64; 4] {
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [0.25 * s, (r[2][1] - r[1][2]) / s, (r[0][2] - r[2][0]) / s, (r[1][0] - r[0][1]) / s]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+        [(r[2][1] - r[1][2]) / s, 0.25 * s, (r[0][1] + r[1][0]) / s, (r[0][2] + r[2][0]) / s]
+    } else if r[1][1] > r[2][2] {
+        let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+        [(r[0][2] - r[2][0]) / s, (r[0][1] + r[1][0]) / s, 0.25 * s, (r[1][2] + r[2][1]) / s]
+    } else {
+        let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+        [(r[1][0] - r[0][1]) / s, (r[0][2] + r[2][0]) / s, (r[1][2] + r[2][1]) / s, 0.25 * s]
+    };
+    normalize4(q)
+}
+
+/// (roll, pitch, yaw) in degrees, HiPNUC ZXY convention: pitch about x,
+/// roll about y, yaw about z
+pub fn quat_to_euler(q: [f64; 4]) -> (f64, f64, f64) {
+    let [w, x, y, z] = q;
+    let pitch = (2.0 * (w * x + y * z)).clamp(-1.0, 1.0).asin();
+    let roll = (-2.0 * (x * z - w * y)).atan2(1.0 - 2.0 * (x * x + y * y));
+    let yaw = (-2.0 * (x * y - w * z)).atan2(1.0 - 2.0 * (x * x + z * z));
+    (roll * RAD_TO_DEG, pitch * RAD_TO_DEG, yaw * RAD_TO_DEG)
+}
+
+/// Inverse of `quat_to_euler`: q = qz(yaw) * qx(pitch) * qy(roll)
+pub fn euler_to_quat(roll: f64, pitch: f64, yaw: f64) -> [f64; 4] {
+    let half = |deg: f64| (deg * DEG_TO_RAD / 2.0).sin_cos();
+    let (sr, cr) = half(roll);
+    let (sp, cp) = half(pitch);
+    let (sy, cy) = half(yaw);
+    quat_mul(quat_mul([cy, 0.0, 0.0, sy], [cp, sp, 0.0, 0.0]), [cr, 0.0, sr, 0.0])
+}
+
+pub fn quat_conj(q: [f64; 4]) -> [f64; 4] {
+    [q[0], -q[1], -q[2], -q[3]]
+}
+
+pub fn quat_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+/// Rotate body vector `v` into the earth frame by `q`
+pub fn quat_rotate(q: [f64; 4], v: [f64; 3]) -> [f64; 3] {
+    let p = quat_mul(quat_mul(q, [0.0, v[0], v[1], v[2]]), quat_conj(q));
+    [p[1], p[2], p[3]]
+}
+
+fn quat_angle_between(a: [f64; 4], b: [f64; 4]) -> f64 {
+    let dot = (a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]).abs().min(1.0);
+    2.0 * dot.acos() * RAD_TO_DEG
+}
+
+fn tilt_angle_between(a: [f64; 4], b: [f64; 4]) -> f64 {
+    // Earth "up" seen from each body frame
+    let up_a = quat_rotate(quat_conj(a), [0.0, 0.0, 1.0]);
+    let up_b = quat_rotate(quat_conj(b), [0.0, 0.0, 1.0]);
+    let dot = up_a[0] * up_b[0] + up_a[1] * up_b[1] + up_a[2] * up_b[2];
+    dot.clamp(-1.0, 1.0).acos() * RAD_TO_DEG
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize3(v: [f64; 3]) -> Option<[f64; 3]> {
+    let n = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    (n > 1e-9).then(|| [v[0] / n, v[1] / n, v[2] / n])
+}
+
+fn normalize4_opt(q: [f64; 4]) -> Option<[f64; 4]> {
+    let n = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    (n > 1e-12).then(|| [q[0] / n, q[1] / n, q[2] / n, q[3] / n])
+}
+
+fn normalize4(q: [f64; 4]) -> [f64; 4] {
+    normalize4_opt(q).unwrap_or([1.0, 0.0, 0.0, 0.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Static sample decoded from the reference HI91 frame in protocol.rs
+    fn reference_sample(system_time: u32) -> Hi91Data {
+        Hi91Data {
+            acc: [-2.1634902857750653, 2.0514418234363196, 9.305423064115644],
+            gyr: [0.0; 3],
+            mag: [7.891666889190674, 14.625000953674316, -60.04166793823242],
+            roll: 13.051900863647461,
+            pitch: 12.188458442687988,
+            yaw: -122.47705841064453,
+            quat: [-0.4859222173690796, -0.14982013404369354, 0.03808683156967163, 0.8602226376533508],
+            system_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_euler_matches_device_convention() {
+        let s = reference_sample(0);
+        let (roll, pitch, yaw) = quat_to_euler(s.quat);
+        assert!((roll - s.roll).abs() < 1e-3, "roll {}", roll);
+        assert!((pitch - s.pitch).abs() < 1e-3, "pitch {}", pitch);
+        assert!((yaw - s.yaw).abs() < 1e-3, "yaw {}", yaw);
+
+        // Same rotation back (up to sign)
+        let q = euler_to_quat(roll, pitch, yaw);
+        let dot: f64 = q.iter().zip(normalize4(s.quat)).map(|(a, b)| a * b).sum();
+        assert!(dot.abs() > 1.0 - 1e-9, "dot {}", dot);
+    }
+
+    #[test]
+    fn test_static_convergence_to_device_tilt() {
+        for algorithm in [AhrsAlgorithm::Madgwick, AhrsAlgorithm::Mahony] {
+            let mut ahrs = Ahrs::new(AhrsConfig {
+                algorithm,
+                ..Default::default()
+            });
+            let out = ahrs.update(&reference_sample(0)).unwrap();
+            assert!(out.tilt_error_deg < 0.5, "{:?} initial tilt error {}", algorithm, out.tilt_error_deg);
+            assert!(ahrs.update(&reference_sample(0)).is_none(), "same sample as HI92");
+
+            // Knock the estimate over with a bogus rate, then hold still
+            let mut t = 0;
+            let mut out = out;
+            for _ in 0..50 {
+                t += 5;
+                let mut s = reference_sample(t);
+                s.gyr = [100.0, 0.0, 0.0];
+                out = ahrs.update(&s).unwrap();
+            }
+            assert!(out.tilt_error_deg > 5.0);
+            for _ in 0..2_000 {
+                t += 5;
+                out = ahrs.update(&reference_sample(t)).unwrap();
+            }
+            assert!(out.tilt_error_deg < 0.5, "{:?} tilt error {}", algorithm, out.tilt_error_deg);
+        }
+    }
+
+    #[test]
+    fn test_gyro_integration_yaw() {
+        for algorithm in [AhrsAlgorithm::Madgwick, AhrsAlgorithm::Mahony] {
+            let mut ahrs = Ahrs::new(AhrsConfig {
+                algorithm,
+                use_mag: false,
+                ..Default::default()
+            });
+            let mut s = Hi91Data {
+                acc: [0.0, 0.0, 9.80665],
+                ..Default::default()
+            };
+            let start = ahrs.update(&s).unwrap();
+            assert!(start.yaw.abs() < 1e-6);
+
+            // 90 deg/s about z for 1 s at 100 Hz
+            s.gyr = [0.0, 0.0, 90.0];
+            let mut out = start;
+            for i in 1..=100 {
+                s.system_time = i * 10;
+                out = ahrs.update(&s).unwrap();
+            }
+            assert!((out.yaw - 90.0).abs() < 0.5, "{:?} yaw {}", algorithm, out.yaw);
+            assert!(out.roll.abs() < 0.1 && out.pitch.abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn test_long_gap_reinitialises() {
+        let mut ahrs = Ahrs::new(AhrsConfig::default());
+        ahrs.update(&reference_sample(0));
+        let mut spinning = reference_sample(10);
+        spinning.gyr = [500.0, 0.0, 0.0];
+        ahrs.update(&spinning);
+        // 2 s later: the spin is not integrated over the gap
+        let out = ahrs.update(&reference_sample(2_010)).unwrap();
+        assert!(out.tilt_error_deg < 0.5, "tilt error {}", out.tilt_error_deg);
+    }
+}
@@ -0,0 +1,315 @@
+//! IMU noise characterisation
+//!
+//! Collects a long static run and computes, per gyro and accelerometer axis,
+//! mean / standard deviation and the overlapping Allan deviation curve. Two
+//! figures are read off the curve:
+//!   - random walk (ARW for gyro, VRW for acc): the -1/2 slope region
+//!     extrapolated to tau = 1 s, in unit·√s (= unit/√Hz)
+//!   - bias instability: the curve minimum / 0.664 (flicker floor)
+//!
+//! Samples are taken in m/s^2 and deg/s after calibration and mounting.
+
+use serde::Serialize;
+
+use crate::protocol::Hi91Data;
+
+/// Samples kept per run (~7 h at 400 Hz); collection stops growing after that
+const MAX_SAMPLES: usize = 10_000_000;
+/// Cluster sizes per decade on the Allan curve
+const POINTS_PER_DECADE: f64 = 10.0;
+/// Timestamp steps needed to estimate the sample period
+const MIN_INTERVALS: usize = 10;
+/// Flicker-floor scale factor: adev_min = sqrt(2 ln 2 / pi) * B
+const BIAS_INSTABILITY_FACTOR: f64 = 0.664;
+
+/// Allan deviation curve and derived figures for one axis
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisNoise {
+    pub mean: f64,
+    pub std: f64,
+    /// Averaging times (s)
+    pub taus: Vec<f64>,
+    /// Allan deviation at each tau (sensor unit)
+    pub adev: Vec<f64>,
+    /// unit·√s (multiply by 60 for unit/√h)
+    pub random_walk: Option<f64>,
+    pub bias_instability: Option<f64>,
+    /// Tau at the curve minimum (s)
+    pub bias_instability_tau: Option<f64>,
+}
+
+/// Result of `imu_noise_stop`
+#[derive(Debug, Clone, Serialize)]
+pub struct NoiseAnalysis {
+    pub samples: usize,
+    pub duration_s: f64,
+    /// Median `system_time` step (s), used as the Allan base period
+    pub sample_period_s: f64,
+    /// Samples lost in `system_time` gaps; the analysis assumes none
+    pub gaps: u64,
+    /// Times `system_time` jumped backwards (device reset or reconnect); the
+    /// segments are joined end to end
+    pub segment_breaks: u64,
+    /// deg/s
+    pub gyr: [AxisNoise; 3],
+    /// m/s^2
+    pub acc: [AxisNoise; 3],
+}
+
+/// Progress of a running collection
+#[derive(Debug, Clone, Serialize)]
+pub struct NoiseProgress {
+    pub samples: usize,
+    pub duration_s: f64,
+    pub segment_breaks: u64,
+}
+
+/// Gathers gyro + acc samples while a run is active
+#[derive(Default)]
+pub struct NoiseCollector {
+    /// gyr xyz, acc xyz; f32 keeps multi-hour runs in memory
+    samples: Vec<[f32; 6]>,
+    intervals: Vec<u32>,
+    last_system_time: Option<u32>,
+    segment_breaks: u64,
+}
+
+impl NoiseCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one sample; the same reading sent again as HI92 is skipped.
+    /// `system_time` may wrap; a jump backwards starts a new segment.
+    pub fn push(&mut self, s: &Hi91Data) {
+        if self.samples.len() >= MAX_SAMPLES {
+            return;
+        }
+        if let Some(last) = self.last_system_time {
+            match s.system_time.wrapping_sub(last) {
+                0 => return,
+                dt if dt < 1 << 31 => self.intervals.push(dt),
+                _ => self.segment_breaks += 1,
+            }
+        }
+        self.last_system_time = Some(s.system_time);
+        self.samples.push([
+            s.gyr[0] as f32,
+            s.gyr[1] as f32,
+            s.gyr[2] as f32,
+            s.acc[0] as f32,
+            s.acc[1] as f32,
+            s.acc[2] as f32,
+        ]);
+    }
+
+    pub fn progress(&self) -> NoiseProgress {
+        NoiseProgress {
+            samples: self.samples.len(),
+            duration_s: self.duration_s(),
+            segment_breaks: self.segment_breaks,
+        }
+    }
+
+    /// Time covered by the samples, excluding segment breaks
+    fn duration_s(&self) -> f64 {
+        self.intervals.iter().map(|&dt| dt as u64).sum::<u64>() as f64 / 1000.0
+    }
+
+    pub fn analyse(&self) -> Result<NoiseAnalysis, String> {
+        if self.samples.len() < 100 {
+            return Err(format!(
+                "Only {} samples collected; record at least a few seconds",
+                self.samples.len()
+            ));
+        }
+        if self.intervals.len() < MIN_INTERVALS {
+            return Err(format!(
+                "system_time only advanced {} time(s) over {} samples; cannot estimate the sample period",
+                self.intervals.len(),
+                self.samples.len()
+            ));
+        }
+        let mut sorted = self.intervals.clone();
+        sorted.sort_unstable();
+        let median_ms = sorted[sorted.len() / 2].max(1);
+        let tau0 = median_ms as f64 / 1000.0;
+        let gaps = self
+            .intervals
+            .iter()
+            .filter(|&&dt| dt as f64 > median_ms as f64 * 1.5 && dt > median_ms + 1)
+            .map(|&dt| (dt as f64 / median_ms as f64).round() as u64 - 1)
+            .sum();
+
+        let axis = |i: usize| {
+            let values: Vec<f64> = self.samples.iter().map(|s| s[i] as f64).collect();
+            axis_noise(&values, tau0)
+        };
+        Ok(NoiseAnalysis {
+            samples: self.samples.len(),
+            duration_s: self.duration_s(),
+            sample_period_s: tau0,
+            gaps,
+            segment_breaks: self.segment_breaks,
+            gyr: [axis(0), axis(1), axis(2)],
+            acc: [axis(3), axis(4), axis(5)],
+        })
+    }
+}
+
+fn axis_noise(values: &[f64], tau0: f64) -> AxisNoise {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+
+    let (taus, adev) = allan_deviation(values, tau0);
+    let min = (0..adev.len()).min_by(|&a, &b| adev[a].total_cmp(&adev[b]));
+
+    // Random walk: the point before the minimum whose local slope is closest to -1/2
+    let random_walk = min.and_then(|min| {
+        let slope = |i: usize| (adev[i + 1] / adev[i]).ln() / (taus[i + 1] / taus[i]).ln();
+        (0..min)
+            .min_by(|&a, &b| (slope(a) + 0.5).abs().total_cmp(&(slope(b) + 0.5).abs()))
+            .map(|i| adev[i] * taus[i].sqrt())
+    });
+
+    AxisNoise {
+        mean,
+        std,
+        random_walk,
+        bias_instability: min.map(|i| adev[i] / BIAS_INSTABILITY_FACTOR),
+        bias_instability_tau: min.map(|i| taus[i]),
+        taus,
+        adev,
+    }
+}
+
+/// Overlapping Allan deviation at log-spaced cluster sizes
+fn allan_deviation(values: &[f64], tau0: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = values.len();
+    // Integrated signal θ_k = τ0 Σ y_i (i < k)
+    let mut theta = Vec::with_capacity(n + 1);
+    theta.push(0.0);
+    let mut acc = 0.0;
+    for v in values {
+        acc += v * tau0;
+        theta.push(acc);
+    }
+
+    let max_m = (n - 1) / 2;
+    let mut cluster_sizes: Vec<usize> = Vec::new();
+    let mut k = 0.0;
+    loop {
+        let m = 10f64.powf(k / POINTS_PER_DECADE).round() as usize;
+        if m > max_m {
+            break;
+        }
+        if cluster_sizes.last() != Some(&m) {
+            cluster_sizes.push(m);
+        }
+        k += 1.0;
+    }
+
+    let mut taus = Vec::with_capacity(cluster_sizes.len());
+    let mut adev = Vec::with_capacity(cluster_sizes.len());
+    for m in cluster_sizes {
+        let tau = m as f64 * tau0;
+        let terms = n + 1 - 2 * m;
+        let sum: f64 = (0..terms)
+            .map(|k| (theta[k + 2 * m] - 2.0 * theta[k + m] + theta[k]).powi(2))
+            .sum();
+        taus.push(tau);
+        adev.push((sum / (2.0 * tau * tau * terms as f64)).sqrt());
+    }
+    (taus, adev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic unit-variance Gaussian noise (xorshift + Box-Muller)
+    fn gaussian(n: usize, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+        let mut uniform = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+        };
+        (0..n)
+            .map(|_| {
+                let (u1, u2) = (uniform(), uniform());
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_white_noise_allan_slope() {
+        // 100 Hz, sigma 0.05: adev(tau) = sigma * sqrt(tau0 / tau), ARW = sigma * sqrt(tau0)
+        let sigma = 0.05;
+        let values: Vec<f64> = gaussian(100_000, 0x9E37_79B9_7F4A_7C15)
+            .into_iter()
+            .map(|v| 0.3 + sigma * v)
+            .collect();
+        let noise = axis_noise(&values, 0.01);
+
+        assert!((noise.mean - 0.3).abs() < 1e-3);
+        assert!((noise.std - sigma).abs() / sigma < 0.02);
+        assert!((noise.adev[0] - sigma).abs() / sigma < 0.02, "adev(tau0) {}", noise.adev[0]);
+        assert!(noise.taus.windows(2).all(|w| w[1] > w[0]));
+        let arw = noise.random_walk.unwrap();
+        assert!((arw - sigma * 0.1).abs() / (sigma * 0.1) < 0.1, "ARW {}", arw);
+        assert!(noise.bias_instability.is_some());
+    }
+
+    #[test]
+    fn test_collector_skips_duplicates_and_counts_gaps() {
+        let mut c = NoiseCollector::new();
+        assert!(c.analyse().is_err());
+        let mut t = 0;
+        for i in 0..1_000 {
+            let s = Hi91Data {
+                gyr: [0.1, 0.0, 0.0],
+                acc: [0.0, 0.0, 9.8],
+                system_time: t,
+                ..Default::default()
+            };
+            c.push(&s);
+            c.push(&s); // HI92 copy
+            t += if i == 500 { 15 } else { 5 };
+        }
+        let analysis = c.analyse().unwrap();
+        assert_eq!(analysis.samples, 1_000);
+        assert_eq!(analysis.sample_period_s, 0.005);
+        assert_eq!(analysis.gaps, 2);
+        assert!((analysis.acc[2].mean - 9.8).abs() < 1e-5);
+        assert!(analysis.gyr[0].std < 1e-6);
+        assert_eq!(analysis.segment_breaks, 0);
+    }
+
+    #[test]
+    fn test_collector_keeps_samples_after_reset_and_wrap() {
+        let mut c = NoiseCollector::new();
+        let times = (0..300u32)
+            .map(|i| (u32::MAX - 999).wrapping_add(i * 5)) // wraps after 200 samples
+            .chain((0..300).map(|i| 100 + i * 5)); // device reset
+        for t in times {
+            c.push(&Hi91Data { system_time: t, ..Default::default() });
+        }
+        let analysis = c.analyse().unwrap();
+        assert_eq!(analysis.samples, 600);
+        assert_eq!(analysis.segment_breaks, 1);
+        assert_eq!(analysis.gaps, 0);
+        assert!((analysis.duration_s - 598.0 * 0.005).abs() < 1e-9);
+
+        // Every step backwards: samples but no period
+        let mut c = NoiseCollector::new();
+        for i in 0..200u32 {
+            c.push(&Hi91Data { system_time: 10_000 - i * 5, ..Default::default() });
+        }
+        assert_eq!(c.progress().segment_breaks, 199);
+        assert!(c.analyse().is_err());
+    }
+}
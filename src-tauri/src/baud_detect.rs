@@ -0,0 +1,154 @@
+//! IMU baud rate auto-detection
+//!
+//! Each candidate rate is opened briefly and the bytes read are run through a
+//! fresh `HipnucDecoder`. At the wrong rate the UART delivers garbage that
+//! almost never forms a frame with a valid CRC, so a handful of good frames is
+//! a reliable signal. Text replies (device in ASCII mode) are reported too.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::protocol::HipnucDecoder;
+
+/// Candidate rates, most common first
+pub const DEFAULT_CANDIDATES: &[u32] = &[115200, 921600, 460800, 230400, 9600];
+
+/// Valid frames needed before a rate is accepted
+pub const MIN_VALID_FRAMES: u64 = 3;
+
+/// What one candidate rate produced
+#[derive(Debug, Clone, Serialize)]
+pub struct BaudProbe {
+    pub baud_rate: u32,
+    pub bytes: u64,
+    pub frames_ok: u64,
+    pub crc_failures: u64,
+    /// Sub-packet types seen in valid frames (HI91, HI92, ...)
+    pub packet_types: Vec<String>,
+    /// Printable text lines outside frames (AT replies, ASCII output mode)
+    pub text_lines: u64,
+    /// Error opening or reading the port at this rate
+    pub error: Option<String>,
+}
+
+/// Result of `imu_detect_baud`
+#[derive(Debug, Clone, Serialize)]
+pub struct BaudDetection {
+    pub port_name: String,
+    /// Rate that produced valid frames (None if no candidate did)
+    pub baud_rate: Option<u32>,
+    pub packet_types: Vec<String>,
+    pub probes: Vec<BaudProbe>,
+}
+
+/// Accumulates one candidate's bytes
+pub struct ProbeDecoder {
+    baud_rate: u32,
+    decoder: HipnucDecoder,
+    bytes: u64,
+    packet_types: BTreeSet<&'static str>,
+    text_lines: u64,
+    line_len: usize,
+}
+
+impl ProbeDecoder {
+    pub fn new(baud_rate: u32) -> Self {
+        let mut decoder = HipnucDecoder::new();
+        decoder.set_capture_unframed(true);
+        Self {
+            baud_rate,
+            decoder,
+            bytes: 0,
+            packet_types: BTreeSet::new(),
+            text_lines: 0,
+            line_len: 0,
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.bytes += data.len() as u64;
+        for packet in self.decoder.input_bytes(data) {
+            self.packet_types.insert(packet.name());
+        }
+
+        // Count runs of printable characters ending in a newline
+        for b in self.decoder.take_unframed() {
+            match b {
+                b'\n' => {
+                    if self.line_len >= 2 {
+                        self.text_lines += 1;
+                    }
+                    self.line_len = 0;
+                }
+                b'\r' => {}
+                0x20..=0x7E => self.line_len += 1,
+                _ => self.line_len = 0,
+            }
+        }
+    }
+
+    /// Enough valid frames seen to stop sampling early
+    pub fn is_conclusive(&self) -> bool {
+        self.decoder.stats().frames_ok >= MIN_VALID_FRAMES
+    }
+
+    pub fn finish(self, error: Option<String>) -> BaudProbe {
+        let stats = self.decoder.stats();
+        BaudProbe {
+            baud_rate: self.baud_rate,
+            bytes: self.bytes,
+            frames_ok: stats.frames_ok,
+            crc_failures: stats.crc_failures,
+            packet_types: self.packet_types.iter().map(|s| s.to_string()).collect(),
+            text_lines: self.text_lines,
+            error,
+        }
+    }
+}
+
+/// Pick the rate with the most valid frames, requiring `MIN_VALID_FRAMES`
+pub fn pick_baud(probes: &[BaudProbe]) -> Option<&BaudProbe> {
+    probes
+        .iter()
+        .filter(|p| p.frames_ok >= MIN_VALID_FRAMES)
+        .max_by_key(|p| (p.frames_ok, std::cmp::Reverse(p.crc_failures)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::hi91_test_frame;
+
+    #[test]
+    fn test_valid_frames_win() {
+        // Right rate: a clean stream, split across reads
+        let mut right = ProbeDecoder::new(460800);
+        let stream: Vec<u8> = (0..4).flat_map(|_| hi91_test_frame()).collect();
+        for chunk in stream.chunks(37) {
+            right.feed(chunk);
+        }
+        assert!(right.is_conclusive());
+
+        // Wrong rate: the same bytes mangled, plus a stray sync pair
+        let mut wrong = ProbeDecoder::new(115200);
+        let mangled: Vec<u8> = stream.iter().map(|b| b.rotate_left(3) ^ 0x55).collect();
+        wrong.feed(&mangled);
+        assert!(!wrong.is_conclusive());
+
+        let probes = vec![wrong.finish(None), right.finish(None)];
+        let best = pick_baud(&probes).unwrap();
+        assert_eq!(best.baud_rate, 460800);
+        assert_eq!(best.frames_ok, 4);
+        assert_eq!(best.packet_types, vec!["HI91"]);
+    }
+
+    #[test]
+    fn test_text_only_is_not_a_match() {
+        let mut probe = ProbeDecoder::new(115200);
+        probe.feed(b"$GPGGA,123519,4807.038,N\r\nOK\r\n");
+        let probe = probe.finish(None);
+        assert_eq!(probe.text_lines, 2);
+        assert!(pick_baud(&[probe]).is_none());
+    }
+}
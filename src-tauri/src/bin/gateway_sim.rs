@@ -0,0 +1,59 @@
+//! Standalone Waveshare UDP gateway emulator
+//!
+//! Usage: gateway_sim [--bind ADDR] [--mit ID]... [ID]...
+//!
+//! Plain IDs are motors in private protocol, `--mit` IDs are motors in MIT
+//! mode. Defaults to one private motor with ID 1 on 127.0.0.1:20002; point the
+//! app at that address (gateway IP 127.0.0.1, port 20002).
+
+use hecate26_robot_devkit_lib::gateway_sim::{GatewaySim, SimMotor, SimMotorBus, SimProtocol};
+
+fn parse_id(arg: Option<String>) -> Result<u8, String> {
+    let arg = arg.ok_or("Missing motor ID")?;
+    arg.parse().map_err(|_| format!("Invalid motor ID: {}", arg))
+}
+
+fn parse_args() -> Result<(String, Vec<SimMotor>), String> {
+    let mut bind = "127.0.0.1:20002".to_string();
+    let mut motors = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => bind = args.next().ok_or("--bind needs an address")?,
+            "--mit" => motors.push(SimMotor::new(parse_id(args.next())?, SimProtocol::Mit)),
+            _ => motors.push(SimMotor::new(parse_id(Some(arg))?, SimProtocol::Private)),
+        }
+    }
+    if motors.is_empty() {
+        motors.push(SimMotor::new(1, SimProtocol::Private));
+    }
+    Ok((bind, motors))
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let (bind, motors) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\nUsage: gateway_sim [--bind ADDR] [--mit ID]... [ID]...", e);
+            std::process::exit(2);
+        }
+    };
+    let ids: Vec<String> = motors
+        .iter()
+        .map(|m| format!("{}{}", m.id, if m.protocol == SimProtocol::Mit { " (MIT)" } else { "" }))
+        .collect();
+
+    let sim = match GatewaySim::start(&bind, Box::new(SimMotorBus::new(motors))) {
+        Ok(sim) => sim,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Gateway emulator on {} with motors {}", sim.local_addr(), ids.join(", "));
+    loop {
+        std::thread::park();
+    }
+}
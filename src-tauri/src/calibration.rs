@@ -0,0 +1,578 @@
+//! IMU sensor calibration
+//!
+//! Three guided procedures, each run on raw (uncalibrated) samples collected
+//! from the live stream:
+//!   - gyro bias: hold still, bias = mean rate
+//!   - accelerometer six-face: hold each axis up and down, per-axis offset and
+//!     scale from the +/- readings
+//!   - magnetometer: rotate through all orientations, ellipsoid fit for
+//!     hard-iron offset and soft-iron matrix
+//!
+//! The resulting `ImuCalibration` is applied to every packet before it is
+//! published, recorded or fed to the AHRS.
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{Hi91Data, SensorsMut, GRAVITY};
+
+/// Upper bound on samples held by one collection (~4 min at 400 Hz)
+const MAX_SAMPLES: usize = 100_000;
+/// Static procedures fail if the device moved more than this
+const STATIC_GYR_STD_MAX: f64 = 1.0; // deg/s
+const STATIC_ACC_STD_MAX: f64 = 0.3; // m/s^2
+/// A face must be within this angle of an axis to be classified
+const FACE_MAX_TILT_DEG: f64 = 20.0;
+const MIN_STATIC_SAMPLES: usize = 20;
+const MIN_MAG_SAMPLES: usize = 100;
+
+/// Correction applied to every packet:
+///   gyr' = gyr - gyro_bias
+///   acc' = (acc - acc_offset) * acc_scale
+///   mag' = mag_matrix * (mag - mag_offset)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImuCalibration {
+    /// deg/s
+    pub gyro_bias: [f64; 3],
+    /// m/s^2
+    pub acc_offset: [f64; 3],
+    pub acc_scale: [f64; 3],
+    /// Hard-iron offset, uT
+    pub mag_offset: [f64; 3],
+    /// Soft-iron correction (row-major)
+    pub mag_matrix: [[f64; 3]; 3],
+}
+
+impl Default for ImuCalibration {
+    fn default() -> Self {
+        Self {
+            gyro_bias: [0.0; 3],
+            acc_offset: [0.0; 3],
+            acc_scale: [1.0; 3],
+            mag_offset: [0.0; 3],
+            mag_matrix: IDENTITY,
+        }
+    }
+}
+
+const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+impl ImuCalibration {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, sensors: SensorsMut<'_>) {
+        if let Some(gyr) = sensors.gyr {
+            *gyr = sub(*gyr, self.gyro_bias);
+        }
+        if let Some(acc) = sensors.acc {
+            *acc = std::array::from_fn(|i| (acc[i] - self.acc_offset[i]) * self.acc_scale[i]);
+        }
+        if let Some(mag) = sensors.mag {
+            let centered = sub(*mag, self.mag_offset);
+            *mag = mat_vec(&self.mag_matrix, centered);
+        }
+    }
+}
+
+/// Raw channels of one sample
+#[derive(Debug, Clone, Copy)]
+pub struct CalibSample {
+    pub acc: [f64; 3],
+    pub gyr: [f64; 3],
+    pub mag: [f64; 3],
+}
+
+/// Collects raw samples while a procedure runs
+#[derive(Default)]
+pub struct SampleCollector {
+    samples: Vec<CalibSample>,
+    last_system_time: Option<u32>,
+}
+
+impl SampleCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one sample; the same reading sent again as HI92 is skipped
+    pub fn push(&mut self, s: &Hi91Data) {
+        if self.last_system_time == Some(s.system_time) || self.samples.len() >= MAX_SAMPLES {
+            return;
+        }
+        self.last_system_time = Some(s.system_time);
+        self.samples.push(CalibSample {
+            acc: s.acc,
+            gyr: s.gyr,
+            mag: s.mag,
+        });
+    }
+
+    pub fn into_samples(self) -> Vec<CalibSample> {
+        self.samples
+    }
+}
+
+/// Per-session calibration state held in `ImuSession`
+#[derive(Default)]
+pub struct CalibrationState {
+    /// Applied to every published packet
+    pub active: ImuCalibration,
+    /// Set while a procedure is collecting samples
+    pub collector: Option<SampleCollector>,
+    /// Six-face accelerometer readings collected so far (+X, -X, +Y, -Y, +Z, -Z)
+    pub accel_faces: [Option<[f64; 3]>; 6],
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GyroCalibResult {
+    /// deg/s
+    pub bias: [f64; 3],
+    /// Sample standard deviation per axis, deg/s
+    pub std: [f64; 3],
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccelFaceResult {
+    /// "+X", "-X", ... : the axis pointing up
+    pub face: &'static str,
+    pub mean: [f64; 3],
+    pub std: [f64; 3],
+    pub samples: usize,
+    /// Faces collected so far
+    pub faces_done: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccelCalibResult {
+    pub offset: [f64; 3],
+    pub scale: [f64; 3],
+    /// RMS of |acc'| - g over the six faces after calibration, m/s^2
+    pub residual_rms: f64,
+    /// Same before calibration, for comparison
+    pub raw_residual_rms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MagCalibResult {
+    pub offset: [f64; 3],
+    pub matrix: [[f64; 3]; 3],
+    /// Radius of the corrected sphere (local field strength), uT
+    pub field_strength: f64,
+    /// RMS of (|mag'| - field_strength) / field_strength after calibration
+    pub residual_rms: f64,
+    /// Same for the raw samples around their mean norm
+    pub raw_residual_rms: f64,
+    /// Fraction of the 8 octants around the fitted centre that were visited
+    pub coverage: f64,
+    pub samples: usize,
+}
+
+const FACE_NAMES: [&str; 6] = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"];
+
+fn mean_std(values: impl Iterator<Item = [f64; 3]> + Clone) -> ([f64; 3], [f64; 3]) {
+    let n = values.clone().count().max(1) as f64;
+    let mut mean = [0.0; 3];
+    for v in values.clone() {
+        for i in 0..3 {
+            mean[i] += v[i] / n;
+        }
+    }
+    let mut var = [0.0; 3];
+    for v in values {
+        for i in 0..3 {
+            var[i] += (v[i] - mean[i]).powi(2) / (n - 1.0).max(1.0);
+        }
+    }
+    (mean, var.map(f64::sqrt))
+}
+
+fn check_static(samples: &[CalibSample]) -> Result<(), String> {
+    if samples.len() < MIN_STATIC_SAMPLES {
+        return Err(format!(
+            "Only {} samples collected (need {}); is the IMU streaming?",
+            samples.len(),
+            MIN_STATIC_SAMPLES
+        ));
+    }
+    let (_, gyr_std) = mean_std(samples.iter().map(|s| s.gyr));
+    let (_, acc_std) = mean_std(samples.iter().map(|s| s.acc));
+    let gyr_max = gyr_std.iter().cloned().fold(0.0, f64::max);
+    let acc_max = acc_std.iter().cloned().fold(0.0, f64::max);
+    if gyr_max > STATIC_GYR_STD_MAX || acc_max > STATIC_ACC_STD_MAX {
+        return Err(format!(
+            "Device moved during collection (gyr std {:.2} deg/s, acc std {:.3} m/s^2)",
+            gyr_max, acc_max
+        ));
+    }
+    Ok(())
+}
+
+/// Gyro bias from a static collection
+pub fn solve_gyro_bias(samples: &[CalibSample]) -> Result<GyroCalibResult, String> {
+    check_static(samples)?;
+    let (bias, std) = mean_std(samples.iter().map(|s| s.gyr));
+    Ok(GyroCalibResult {
+        bias,
+        std,
+        samples: samples.len(),
+    })
+}
+
+/// Classify a static collection as one of the six faces and store its mean
+pub fn add_accel_face(
+    faces: &mut [Option<[f64; 3]>; 6],
+    samples: &[CalibSample],
+) -> Result<AccelFaceResult, String> {
+    check_static(samples)?;
+    let (mean, std) = mean_std(samples.iter().map(|s| s.acc));
+
+    let norm = norm3(mean);
+    let axis = (0..3)
+        .max_by(|&a, &b| mean[a].abs().total_cmp(&mean[b].abs()))
+        .unwrap_or(2);
+    let tilt = (mean[axis].abs() / norm).clamp(-1.0, 1.0).acos().to_degrees();
+    if norm < GRAVITY * 0.5 || tilt > FACE_MAX_TILT_DEG {
+        return Err(format!(
+            "No axis is vertical (closest {} at {:.1} deg); place the IMU on a face",
+            ["X", "Y", "Z"][axis],
+            tilt
+        ));
+    }
+    let face = axis * 2 + usize::from(mean[axis] < 0.0);
+    faces[face] = Some(mean);
+
+    Ok(AccelFaceResult {
+        face: FACE_NAMES[face],
+        mean,
+        std,
+        samples: samples.len(),
+        faces_done: (0..6)
+            .filter(|&i| faces[i].is_some())
+            .map(|i| FACE_NAMES[i])
+            .collect(),
+    })
+}
+
+/// Per-axis offset and scale from the six face means
+pub fn solve_accel(faces: &[Option<[f64; 3]>; 6]) -> Result<AccelCalibResult, String> {
+    let missing: Vec<&str> = (0..6)
+        .filter(|&i| faces[i].is_none())
+        .map(|i| FACE_NAMES[i])
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Faces not collected yet: {}", missing.join(", ")));
+    }
+    let faces: Vec<[f64; 3]> = faces.iter().flatten().copied().collect();
+
+    let mut offset = [0.0; 3];
+    let mut scale = [1.0; 3];
+    for axis in 0..3 {
+        let up = faces[axis * 2][axis];
+        let down = faces[axis * 2 + 1][axis];
+        offset[axis] = (up + down) / 2.0;
+        scale[axis] = 2.0 * GRAVITY / (up - down);
+    }
+
+    let rms = |f: &dyn Fn([f64; 3]) -> [f64; 3]| {
+        let sum: f64 = faces.iter().map(|&m| (norm3(f(m)) - GRAVITY).powi(2)).sum();
+        (sum / faces.len() as f64).sqrt()
+    };
+    let corrected = |m: [f64; 3]| std::array::from_fn(|i| (m[i] - offset[i]) * scale[i]);
+    Ok(AccelCalibResult {
+        offset,
+        scale,
+        residual_rms: rms(&corrected),
+        raw_residual_rms: rms(&|m| m),
+    })
+}
+
+/// Hard/soft-iron calibration from samples taken while rotating the device.
+///
+/// Fits the general ellipsoid x'Ax + 2b'x = 1 by least squares, then maps it
+/// onto a sphere whose radius is the geometric mean of the semi-axes.
+pub fn solve_mag(samples: &[CalibSample]) -> Result<MagCalibResult, String> {
+    if samples.len() < MIN_MAG_SAMPLES {
+        return Err(format!(
+            "Only {} samples collected (need {}); rotate the IMU through all orientations",
+            samples.len(),
+            MIN_MAG_SAMPLES
+        ));
+    }
+    let points: Vec<[f64; 3]> = samples.iter().map(|s| s.mag).collect();
+
+    // Normalise for conditioning
+    let (mean, _) = mean_std(points.iter().copied());
+    let spread = points.iter().map(|&p| norm3(sub(p, mean))).fold(0.0, f64::max);
+    if spread < 1e-6 {
+        return Err("Magnetometer readings do not change; rotate the IMU".to_string());
+    }
+    let scaled: Vec<[f64; 3]> = points
+        .iter()
+        .map(|&p| sub(p, mean).map(|v| v / spread))
+        .collect();
+
+    // Normal equations for [a b c d e f g h i]:
+    //   a x² + b y² + c z² + 2d xy + 2e xz + 2f yz + 2g x + 2h y + 2i z = 1
+    let mut ata = [[0.0; 9]; 9];
+    let mut atb = [0.0; 9];
+    for &[x, y, z] in &scaled {
+        let row = [x * x, y * y, z * z, 2.0 * x * y, 2.0 * x * z, 2.0 * y * z, 2.0 * x, 2.0 * y, 2.0 * z];
+        for r in 0..9 {
+            atb[r] += row[r];
+            for c in 0..9 {
+                ata[r][c] += row[r] * row[c];
+            }
+        }
+    }
+    let p = solve_linear(ata, atb).ok_or("Ellipsoid fit is singular; rotate the IMU through more orientations")?;
+
+    let a = [[p[0], p[3], p[4]], [p[3], p[1], p[5]], [p[4], p[5], p[2]]];
+    let b = [p[6], p[7], p[8]];
+    let a_inv = invert3(&a).ok_or("Ellipsoid fit is degenerate")?;
+    let center = mat_vec(&a_inv, b).map(|v| -v);
+    // (x - c)' A (x - c) = 1 + c' A c
+    let k = 1.0 + dot(center, mat_vec(&a, center));
+    if k <= 0.0 {
+        return Err("Samples do not describe an ellipsoid".to_string());
+    }
+    let a_norm = a.map(|row| row.map(|v| v / k));
+    let (eigenvalues, eigenvectors) = symmetric_eigen(a_norm);
+    if eigenvalues.iter().any(|&l| l <= 0.0) {
+        return Err("Samples do not describe an ellipsoid; rotate the IMU through more orientations".to_string());
+    }
+
+    // Back to uT: semi-axes scale with `spread`, the centre also shifts by `mean`
+    let radius = spread * eigenvalues.iter().map(|l| 1.0 / l.sqrt()).product::<f64>().cbrt();
+    let sqrt_l = eigenvalues.map(|l| l.sqrt() * radius / spread);
+    let matrix: [[f64; 3]; 3] = std::array::from_fn(|r| {
+        std::array::from_fn(|c| (0..3).map(|k| eigenvectors[r][k] * sqrt_l[k] * eigenvectors[c][k]).sum())
+    });
+    let offset: [f64; 3] = std::array::from_fn(|i| mean[i] + center[i] * spread);
+
+    let relative_rms = |norms: &[f64], reference: f64| {
+        let sum: f64 = norms.iter().map(|n| ((n - reference) / reference).powi(2)).sum();
+        (sum / norms.len() as f64).sqrt()
+    };
+    let corrected: Vec<f64> = points.iter().map(|&p| norm3(mat_vec(&matrix, sub(p, offset)))).collect();
+    let raw: Vec<f64> = points.iter().map(|&p| norm3(p)).collect();
+    let raw_mean = raw.iter().sum::<f64>() / raw.len() as f64;
+
+    let mut octants = [false; 8];
+    for &p in &points {
+        let d = sub(p, offset);
+        let idx = usize::from(d[0] > 0.0) | usize::from(d[1] > 0.0) << 1 | usize::from(d[2] > 0.0) << 2;
+        octants[idx] = true;
+    }
+
+    Ok(MagCalibResult {
+        offset,
+        matrix,
+        field_strength: radius,
+        residual_rms: relative_rms(&corrected, radius),
+        raw_residual_rms: relative_rms(&raw, raw_mean),
+        coverage: octants.iter().filter(|&&o| o).count() as f64 / 8.0,
+        samples: points.len(),
+    })
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm3(v: [f64; 3]) -> f64 {
+    dot(v, v).sqrt()
+}
+
+fn mat_vec(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    [dot(m[0], v), dot(m[1], v), dot(m[2], v)]
+}
+
+fn invert3(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cof = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let det = m[0][0] * cof(1, 2, 1, 2) - m[0][1] * cof(1, 2, 0, 2) + m[0][2] * cof(1, 2, 0, 1);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some([
+        [cof(1, 2, 1, 2) / det, -cof(0, 2, 1, 2) / det, cof(0, 1, 1, 2) / det],
+        [-cof(1, 2, 0, 2) / det, cof(0, 2, 0, 2) / det, -cof(0, 1, 0, 2) / det],
+        [cof(1, 2, 0, 1) / det, -cof(0, 2, 0, 1) / det, cof(0, 1, 0, 1) / det],
+    ])
+}
+
+/// Gaussian elimination with partial pivoting
+fn solve_linear<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..N {
+            let f = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            for (x, p) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= f * p;
+            }
+            b[row] -= f * b[col];
+        }
+    }
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let tail: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Eigen-decomposition of a symmetric 3x3 matrix (cyclic Jacobi).
+/// Returns eigenvalues and the eigenvectors as columns.
+fn symmetric_eigen(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut v = IDENTITY;
+    for _ in 0..50 {
+        let off = a[0][1].powi(2) + a[0][2].powi(2) + a[1][2].powi(2);
+        if off < 1e-30 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < 1e-300 {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            // A <- J' A J, V <- V J
+            for row in a.iter_mut() {
+                let (rp, rq) = (row[p], row[q]);
+                row[p] = c * rp - s * rq;
+                row[q] = s * rp + c * rq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+            a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+            for row in v.iter_mut() {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = c * vp - s * vq;
+                row[q] = s * vp + c * vq;
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::HipnucPacket;
+
+    fn sample(acc: [f64; 3], gyr: [f64; 3], mag: [f64; 3]) -> CalibSample {
+        CalibSample { acc, gyr, mag }
+    }
+
+    /// Deterministic small noise so std checks see something realistic
+    fn jitter(i: usize, amp: f64) -> f64 {
+        ((i * 7919 % 1000) as f64 / 1000.0 - 0.5) * amp
+    }
+
+    #[test]
+    fn test_gyro_bias_and_motion_rejection() {
+        let still: Vec<CalibSample> = (0..200)
+            .map(|i| sample([0.0, 0.0, GRAVITY], [0.5 + jitter(i, 0.1), -0.2, 0.1], [0.0; 3]))
+            .collect();
+        let result = solve_gyro_bias(&still).unwrap();
+        assert!((result.bias[0] - 0.5).abs() < 0.01);
+        assert!((result.bias[1] + 0.2).abs() < 1e-9);
+
+        let moving: Vec<CalibSample> = (0..200)
+            .map(|i| sample([0.0, 0.0, GRAVITY], [0.0, 0.0, i as f64], [0.0; 3]))
+            .collect();
+        assert!(solve_gyro_bias(&moving).is_err());
+        assert!(solve_gyro_bias(&still[..5]).is_err());
+    }
+
+    #[test]
+    fn test_accel_six_face() {
+        let offset = [0.12, -0.08, 0.2];
+        let gain = [1.02, 0.98, 1.01];
+        let raw = |true_acc: [f64; 3]| -> [f64; 3] { std::array::from_fn(|i| true_acc[i] / gain[i] + offset[i]) };
+
+        let mut faces = [None; 6];
+        let mut last = None;
+        for (axis, sign) in [(2, 1.0), (2, -1.0), (0, 1.0), (0, -1.0), (1, 1.0), (1, -1.0)] {
+            let mut up = [0.0; 3];
+            up[axis] = sign * GRAVITY;
+            let samples: Vec<CalibSample> = (0..50)
+                .map(|i| sample(raw(up).map(|v| v + jitter(i, 0.02)), [0.0; 3], [0.0; 3]))
+                .collect();
+            assert!(solve_accel(&faces).is_err(), "incomplete");
+            last = Some(add_accel_face(&mut faces, &samples).unwrap());
+        }
+        assert_eq!(last.unwrap().faces_done.len(), 6);
+
+        let result = solve_accel(&faces).unwrap();
+        for i in 0..3 {
+            assert!((result.offset[i] - offset[i]).abs() < 1e-3, "offset {:?}", result.offset);
+            assert!((result.scale[i] - gain[i]).abs() < 1e-3, "scale {:?}", result.scale);
+        }
+        assert!(result.residual_rms < 0.01 && result.raw_residual_rms > 0.05);
+
+        let tilted: Vec<CalibSample> = (0..50)
+            .map(|_| sample([6.9, 0.0, 6.9], [0.0; 3], [0.0; 3]))
+            .collect();
+        assert!(add_accel_face(&mut faces, &tilted).is_err());
+    }
+
+    #[test]
+    fn test_mag_ellipsoid_fit() {
+        let offset = [12.0, -30.0, 5.0];
+        // Symmetric distortion: 50 uT field seen as an ellipsoid
+        let distortion = [[1.2, 0.1, 0.0], [0.1, 0.9, 0.05], [0.0, 0.05, 1.05]];
+        let n = 400;
+        let samples: Vec<CalibSample> = (0..n)
+            .map(|i| {
+                // Fibonacci sphere
+                let z = 1.0 - 2.0 * (i as f64 + 0.5) / n as f64;
+                let r = (1.0 - z * z).sqrt();
+                let phi = i as f64 * 2.399_963_229_728_653;
+                let field = [r * phi.cos() * 50.0, r * phi.sin() * 50.0, z * 50.0];
+                let m = mat_vec(&distortion, field);
+                sample([0.0; 3], [0.0; 3], [m[0] + offset[0], m[1] + offset[1], m[2] + offset[2]])
+            })
+            .collect();
+
+        let result = solve_mag(&samples).unwrap();
+        for (fit, truth) in result.offset.iter().zip(offset) {
+            assert!((fit - truth).abs() < 1e-6, "offset {:?}", result.offset);
+        }
+        assert!(result.residual_rms < 1e-9, "residual {}", result.residual_rms);
+        assert!(result.raw_residual_rms > 0.05);
+        assert_eq!(result.coverage, 1.0);
+
+        // Applying the calibration puts every sample on the sphere
+        let cal = ImuCalibration {
+            mag_offset: result.offset,
+            mag_matrix: result.matrix,
+            ..Default::default()
+        };
+        let mut packet = HipnucPacket::Hi91(Hi91Data {
+            mag: samples[17].mag,
+            ..Default::default()
+        });
+        cal.apply(packet.sensors_mut().unwrap());
+        let HipnucPacket::Hi91(data) = packet else {
+            unreachable!()
+        };
+        assert!((norm3(data.mag) - result.field_strength).abs() < 1e-6);
+
+        assert!(solve_mag(&samples[..10]).is_err());
+    }
+}
@@ -0,0 +1,876 @@
+//! CAN adapter backends
+//!
+//! Motor commands, scans, control loops and the recv thread work on
+//! `CanFrame`s through a `CanTransport`, independent of how the frames reach
+//! the bus:
+//! - `WaveshareUdp`: Waveshare CAN-ETH gateway, 13-byte frames over UDP
+//!   (several frames may share one datagram)
+//! - `WaveshareTcp`: the same gateway in TCP client or server mode; the byte
+//!   stream is re-framed into 13-byte records and the link re-established
+//!   when it drops
+//! - `Slcan`: USB-CAN dongles speaking the Lawicel SLCAN ASCII protocol on a
+//!   serial port
+//! - `SocketCan` (Linux): a raw SocketCAN socket, e.g. a USB-CAN adapter on
+//!   `can0` or a `vcan` interface for testing
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::motor_protocol::{self, CAN_FRAME_SIZE};
+
+/// How long `recv` blocks before returning a timeout error
+pub const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A CAN 2.0 data frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanFrame {
+    /// 11-bit or 29-bit identifier
+    pub id: u32,
+    pub extended: bool,
+    /// DLC (0~8)
+    pub len: u8,
+    pub data: [u8; 8],
+}
+
+impl CanFrame {
+    pub fn standard(id: u16, data: &[u8; 8]) -> Self {
+        Self { id: id as u32, extended: false, len: 8, data: *data }
+    }
+
+    pub fn extended(id: u32, data: &[u8; 8]) -> Self {
+        Self { id, extended: true, len: 8, data: *data }
+    }
+
+    /// Standard frame with DLC = data.len() (CANopen NMT, SYNC, PDO)
+    pub fn standard_dlc(id: u16, data: &[u8]) -> Result<Self, String> {
+        if data.len() > 8 {
+            return Err(format!("CAN frame data too long ({} bytes, max 8)", data.len()));
+        }
+        let mut frame = Self { id: id as u32, extended: false, len: data.len() as u8, data: [0; 8] };
+        frame.data[..data.len()].copy_from_slice(data);
+        Ok(frame)
+    }
+
+    /// The first `len` data bytes
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..(self.len as usize).min(8)]
+    }
+
+    /// Waveshare 13-byte frame: frame_info (bit7 = extended, DLC), ID big-endian, 8 data bytes
+    pub fn to_waveshare(self) -> [u8; CAN_FRAME_SIZE] {
+        let mut frame = [0u8; CAN_FRAME_SIZE];
+        frame[0] = if self.extended { 0x80 } else { 0x00 } | self.len.min(8);
+        frame[1..5].copy_from_slice(&self.id.to_be_bytes());
+        frame[5..5 + self.payload().len()].copy_from_slice(self.payload());
+        frame
+    }
+
+    /// Parse a Waveshare 13-byte frame; remote frames give None
+    pub fn from_waveshare(bytes: &[u8; CAN_FRAME_SIZE]) -> Option<Self> {
+        let (frame_info, id, data) = motor_protocol::parse_can_frame(bytes);
+        Self::from_parts(frame_info, id, data)
+    }
+
+    /// From a `(frame_info, can_id, data)` tuple as `parse_can_frame` returns;
+    /// remote frames give None
+    pub fn from_parts(frame_info: u8, id: u32, data: [u8; 8]) -> Option<Self> {
+        let extended = if motor_protocol::is_standard_data_frame(frame_info) {
+            false
+        } else if motor_protocol::is_extended_data_frame(frame_info) {
+            true
+        } else {
+            return None;
+        };
+        Some(Self { id, extended, len: (frame_info & 0x0F).min(8), data })
+    }
+}
+
+/// A connection to a CAN bus. Methods take `&self` so one transport can be
+/// shared by the recv thread, commands and control loops.
+pub trait CanTransport: Send + Sync {
+    /// Send frames in order; backends that can put them in one write do so
+    fn send(&self, frames: &[CanFrame]) -> io::Result<()>;
+
+    /// Frames received within `RECV_TIMEOUT`. A timeout is an error of kind
+    /// `TimedOut` or `WouldBlock`, as for a socket read. A transport that
+    /// reconnects by itself reports a lost link once as `ConnectionReset`
+    /// and keeps going.
+    fn recv(&self) -> io::Result<Vec<CanFrame>>;
+
+    /// Human-readable endpoint, for diagnostics and logs
+    fn describe(&self) -> String;
+}
+
+// ── Waveshare CAN-ETH gateway (UDP) ─────────────────────────────────
+
+pub struct WaveshareUdp {
+    socket: UdpSocket,
+}
+
+impl WaveshareUdp {
+    /// Bind `0.0.0.0:local_port` and connect to the gateway at `remote_addr`
+    pub fn connect(local_port: u16, remote_addr: &str) -> Result<Self, String> {
+        let bind_addr = format!("0.0.0.0:{}", local_port);
+        let socket = UdpSocket::bind(&bind_addr)
+            .map_err(|e| format!("Failed to bind UDP socket on {}: {}", bind_addr, e))?;
+        socket
+            .set_read_timeout(Some(RECV_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        socket
+            .connect(remote_addr)
+            .map_err(|e| format!("Failed to connect to {}: {}", remote_addr, e))?;
+        Ok(Self { socket })
+    }
+}
+
+impl CanTransport for WaveshareUdp {
+    fn send(&self, frames: &[CanFrame]) -> io::Result<()> {
+        let packet: Vec<u8> = frames.iter().flat_map(|f| f.to_waveshare()).collect();
+        self.socket.send(&packet).map(|_| ())
+    }
+
+    fn recv(&self) -> io::Result<Vec<CanFrame>> {
+        let mut buf = [0u8; 1024];
+        let n = self.socket.recv(&mut buf)?;
+        Ok(buf[..n]
+            .chunks_exact(CAN_FRAME_SIZE)
+            .filter_map(|chunk| CanFrame::from_waveshare(chunk.try_into().ok()?))
+            .collect())
+    }
+
+    fn describe(&self) -> String {
+        let addr = |a: io::Result<std::net::SocketAddr>| a.map(|a| a.to_string()).unwrap_or_else(|_| "unknown".to_string());
+        format!(
+            "Waveshare UDP, local {} → remote {}",
+            addr(self.socket.local_addr()),
+            addr(self.socket.peer_addr())
+        )
+    }
+}
+
+// ── Waveshare CAN-ETH gateway (TCP) ─────────────────────────────────
+
+/// Minimum time between reconnect attempts
+pub const TCP_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Splits a TCP byte stream into 13-byte records, across partial reads and
+/// coalesced writes. The format has no sync marker, so the buffer must be
+/// reset whenever the connection is re-established.
+#[derive(Default)]
+pub struct FrameReassembler {
+    buf: Vec<u8>,
+}
+
+impl FrameReassembler {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<CanFrame> {
+        self.buf.extend_from_slice(bytes);
+        let complete = self.buf.len() / CAN_FRAME_SIZE * CAN_FRAME_SIZE;
+        let frames = self.buf[..complete]
+            .chunks_exact(CAN_FRAME_SIZE)
+            .filter_map(|chunk| CanFrame::from_waveshare(chunk.try_into().ok()?))
+            .collect();
+        self.buf.drain(..complete);
+        frames
+    }
+
+    pub fn reset(&mut self) {
+        self.buf.clear();
+    }
+}
+
+/// Which side opens the TCP connection
+pub enum TcpRole {
+    /// Gateway in TCP server mode: connect to it
+    Connect(SocketAddr),
+    /// Gateway in TCP client mode: accept its connection
+    Listen(TcpListener),
+}
+
+struct TcpReader {
+    stream: Option<TcpStream>,
+    frames: FrameReassembler,
+    last_attempt: Option<Instant>,
+}
+
+/// Waveshare gateway over TCP. The recv side owns reconnection; sends fail
+/// with `NotConnected` while the link is down.
+pub struct WaveshareTcp {
+    role: TcpRole,
+    writer: Mutex<Option<TcpStream>>,
+    reader: Mutex<TcpReader>,
+}
+
+impl WaveshareTcp {
+    /// Connect to a gateway in TCP server mode
+    pub fn connect(remote_addr: &str) -> Result<Self, String> {
+        let addr = remote_addr
+            .to_socket_addrs()
+            .map_err(|e| format!("Invalid gateway address {}: {}", remote_addr, e))?
+            .next()
+            .ok_or_else(|| format!("Gateway address {} did not resolve", remote_addr))?;
+        let transport = Self::new(TcpRole::Connect(addr));
+        let stream = transport
+            .open_stream()
+            .map_err(|e| format!("Failed to connect to {}: {}", remote_addr, e))?
+            .ok_or_else(|| format!("Failed to connect to {}", remote_addr))?;
+        transport.install(stream).map_err(|e| e.to_string())?;
+        Ok(transport)
+    }
+
+    /// Listen on `0.0.0.0:local_port` for a gateway in TCP client mode; the
+    /// connection is accepted by `recv`
+    pub fn listen(local_port: u16) -> Result<Self, String> {
+        let bind_addr = format!("0.0.0.0:{}", local_port);
+        let listener = TcpListener::bind(&bind_addr)
+            .map_err(|e| format!("Failed to listen on {}: {}", bind_addr, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(Self::new(TcpRole::Listen(listener)))
+    }
+
+    fn new(role: TcpRole) -> Self {
+        Self {
+            role,
+            writer: Mutex::new(None),
+            reader: Mutex::new(TcpReader { stream: None, frames: FrameReassembler::default(), last_attempt: None }),
+        }
+    }
+
+    /// One connect / accept attempt; Ok(None) when no gateway is there yet
+    fn open_stream(&self) -> io::Result<Option<TcpStream>> {
+        match &self.role {
+            TcpRole::Connect(addr) => TcpStream::connect_timeout(addr, TCP_CONNECT_TIMEOUT).map(Some),
+            TcpRole::Listen(listener) => match listener.accept() {
+                Ok((stream, peer)) => {
+                    log::info!("Gateway connected from {}", peer);
+                    stream.set_nonblocking(false)?;
+                    Ok(Some(stream))
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    fn install(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(RECV_TIMEOUT))?;
+        let writer = stream.try_clone()?;
+        let mut reader = self.reader.lock().map_err(|_| io::Error::other("TCP reader lock poisoned"))?;
+        reader.stream = Some(stream);
+        reader.frames.reset();
+        *self.writer.lock().map_err(|_| io::Error::other("TCP writer lock poisoned"))? = Some(writer);
+        Ok(())
+    }
+
+    fn drop_link(&self, reader: &mut TcpReader) {
+        reader.stream = None;
+        reader.frames.reset();
+        if let Ok(mut writer) = self.writer.lock() {
+            *writer = None;
+        }
+    }
+}
+
+impl CanTransport for WaveshareTcp {
+    fn send(&self, frames: &[CanFrame]) -> io::Result<()> {
+        let packet: Vec<u8> = frames.iter().flat_map(|f| f.to_waveshare()).collect();
+        let mut writer = self.writer.lock().map_err(|_| io::Error::other("TCP writer lock poisoned"))?;
+        let stream = writer
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "TCP gateway not connected"))?;
+        if let Err(e) = stream.write_all(&packet) {
+            // Wake the reader so it notices and reconnects
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+            *writer = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn recv(&self) -> io::Result<Vec<CanFrame>> {
+        let mut reader = self.reader.lock().map_err(|_| io::Error::other("TCP reader lock poisoned"))?;
+
+        let Some(stream) = reader.stream.as_mut() else {
+            let due = reader.last_attempt.is_none_or(|t| t.elapsed() >= TCP_RECONNECT_INTERVAL);
+            if due {
+                reader.last_attempt = Some(Instant::now());
+                match self.open_stream() {
+                    Ok(Some(stream)) => {
+                        drop(reader);
+                        self.install(stream)?;
+                        log::info!("TCP gateway link up ({})", self.describe());
+                        return Ok(Vec::new());
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::debug!("TCP gateway reconnect failed: {}", e),
+                }
+            }
+            drop(reader);
+            std::thread::sleep(RECV_TIMEOUT);
+            return Err(io::ErrorKind::TimedOut.into());
+        };
+
+        let mut buf = [0u8; 1024];
+        let lost = match stream.read(&mut buf) {
+            Ok(0) => io::Error::new(io::ErrorKind::ConnectionReset, "TCP gateway closed the connection"),
+            Ok(n) => return Ok(reader.frames.push(&buf[..n])),
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => return Err(e),
+            Err(e) => io::Error::new(io::ErrorKind::ConnectionReset, format!("TCP gateway link lost: {}", e)),
+        };
+        self.drop_link(&mut reader);
+        reader.last_attempt = Some(Instant::now());
+        log::warn!("{}; reconnecting", lost);
+        Err(lost)
+    }
+
+    fn describe(&self) -> String {
+        let peer = self
+            .writer
+            .lock()
+            .ok()
+            .and_then(|w| w.as_ref().and_then(|s| s.peer_addr().ok()))
+            .map(|a| a.to_string())
+            .unwrap_or_else(|| "not connected".to_string());
+        match &self.role {
+            TcpRole::Connect(addr) => format!("Waveshare TCP client → {} ({})", addr, peer),
+            TcpRole::Listen(listener) => format!(
+                "Waveshare TCP server on {} (gateway: {})",
+                listener.local_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".to_string()),
+                peer
+            ),
+        }
+    }
+}
+
+// ── SLCAN (Lawicel ASCII over serial) ───────────────────────────────
+
+/// `S<n>` setup codes for the standard bitrates
+const SLCAN_BITRATES: &[(u32, u8)] = &[
+    (10_000, 0),
+    (20_000, 1),
+    (50_000, 2),
+    (100_000, 3),
+    (125_000, 4),
+    (250_000, 5),
+    (500_000, 6),
+    (800_000, 7),
+    (1_000_000, 8),
+];
+
+/// SLCAN error reply
+const SLCAN_BEL: u8 = 0x07;
+/// How long the adapter gets to answer each setup command
+const SLCAN_REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Parse one SLCAN line (`tiiildd..`, `Tiiiiiiiildd..`, `r`/`R` remote)
+/// into the `(frame_info, can_id, data)` tuple `parse_can_frame` yields.
+/// Anything else (acks, status replies) gives None.
+pub fn parse_slcan_frame(line: &[u8]) -> Option<(u8, u32, [u8; 8])> {
+    let (id_len, kind) = match line.first()? {
+        b't' => (3, 0x00),
+        b'T' => (8, 0x80),
+        b'r' => (3, 0x40),
+        b'R' => (8, 0xC0),
+        _ => return None,
+    };
+    let hex = |s: &[u8]| u32::from_str_radix(std::str::from_utf8(s).ok()?, 16).ok();
+    let id = hex(line.get(1..1 + id_len)?)?;
+    let dlc = hex(line.get(1 + id_len..2 + id_len)?)? as usize;
+    if dlc > 8 || id > if id_len == 3 { 0x7FF } else { 0x1FFF_FFFF } {
+        return None;
+    }
+    let mut data = [0u8; 8];
+    if kind & 0x40 == 0 {
+        let bytes = line.get(2 + id_len..2 + id_len + 2 * dlc)?;
+        for (i, pair) in bytes.chunks_exact(2).enumerate() {
+            data[i] = hex(pair)? as u8;
+        }
+    }
+    Some((kind | dlc as u8, id, data))
+}
+
+/// SLCAN transmit command for a frame, including the trailing CR
+pub fn encode_slcan_frame(frame: &CanFrame) -> String {
+    let mut line = if frame.extended {
+        format!("T{:08X}{}", frame.id & 0x1FFF_FFFF, frame.payload().len())
+    } else {
+        format!("t{:03X}{}", frame.id & 0x7FF, frame.payload().len())
+    };
+    for b in frame.payload() {
+        line.push_str(&format!("{:02X}", b));
+    }
+    line.push('\r');
+    line
+}
+
+struct SlcanReader {
+    port: Box<dyn serialport::SerialPort>,
+    line: Vec<u8>,
+}
+
+/// Lawicel SLCAN adapter on a serial port. The channel is opened at the
+/// given CAN bitrate and closed again on drop.
+pub struct Slcan {
+    name: String,
+    bitrate: u32,
+    writer: Mutex<Box<dyn serialport::SerialPort>>,
+    reader: Mutex<SlcanReader>,
+}
+
+impl Slcan {
+    /// Open `port_name` (serial `baud_rate`; ignored by USB CDC dongles) and
+    /// start the CAN channel at `bitrate`
+    pub fn open(port_name: &str, baud_rate: u32, bitrate: u32) -> Result<Self, String> {
+        let port = serialport::new(port_name, baud_rate)
+            .timeout(RECV_TIMEOUT)
+            .open()
+            .map_err(|e| format!("Failed to open {}: {}", port_name, e))?;
+        Self::from_port(port, bitrate)
+    }
+
+    /// Set up an adapter on an already open port
+    pub fn from_port(mut port: Box<dyn serialport::SerialPort>, bitrate: u32) -> Result<Self, String> {
+        let code = SLCAN_BITRATES
+            .iter()
+            .find(|(rate, _)| *rate == bitrate)
+            .map(|(_, code)| *code)
+            .ok_or_else(|| format!("Unsupported SLCAN bitrate {} (10k~1M standard rates)", bitrate))?;
+        let name = port.name().unwrap_or_else(|| "serial".to_string());
+
+        // Close first in case the channel was left open (a closed channel answers BEL),
+        // then set bitrate and open; those two must be acknowledged
+        slcan_command(&mut *port, &name, "C")?;
+        if !slcan_command(&mut *port, &name, &format!("S{}", code))? {
+            return Err(format!("SLCAN adapter {} rejected bitrate {}", name, bitrate));
+        }
+        if !slcan_command(&mut *port, &name, "O")? {
+            return Err(format!("SLCAN adapter {} refused to open the CAN channel", name));
+        }
+        port.set_timeout(RECV_TIMEOUT).map_err(|e| e.to_string())?;
+
+        let reader = port.try_clone().map_err(|e| format!("Failed to clone {}: {}", name, e))?;
+        Ok(Self {
+            name,
+            bitrate,
+            writer: Mutex::new(port),
+            reader: Mutex::new(SlcanReader { port: reader, line: Vec::new() }),
+        })
+    }
+}
+
+/// Send one setup command and wait for its reply: true for CR (ok), false
+/// for BEL (error). Frame lines from a channel left open are skipped.
+fn slcan_command(port: &mut dyn serialport::SerialPort, name: &str, command: &str) -> Result<bool, String> {
+    port.write_all(format!("{}\r", command).as_bytes())
+        .and_then(|_| port.flush())
+        .map_err(|e| format!("SLCAN setup on {} failed: {}", name, e))?;
+
+    let deadline = Instant::now() + SLCAN_REPLY_TIMEOUT;
+    let mut line_len = 0;
+    let mut byte = [0u8; 1];
+    while Instant::now() < deadline {
+        match port.read(&mut byte) {
+            Ok(1) => match byte[0] {
+                b'\r' if line_len == 0 => return Ok(true),
+                b'\r' => line_len = 0,
+                SLCAN_BEL => return Ok(false),
+                _ => line_len += 1,
+            },
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {}
+            Err(e) => return Err(format!("SLCAN setup on {} failed: {}", name, e)),
+        }
+    }
+    Err(format!("SLCAN adapter {} did not answer '{}'", name, command))
+}
+
+impl Drop for Slcan {
+    fn drop(&mut self) {
+        if let Ok(mut port) = self.writer.lock() {
+            let _ = port.write_all(b"C\r");
+            let _ = port.flush();
+        }
+    }
+}
+
+impl CanTransport for Slcan {
+    fn send(&self, frames: &[CanFrame]) -> io::Result<()> {
+        let text: String = frames.iter().map(encode_slcan_frame).collect();
+        let mut port = self.writer.lock().map_err(|_| io::Error::other("SLCAN writer lock poisoned"))?;
+        port.write_all(text.as_bytes())?;
+        port.flush()
+    }
+
+    fn recv(&self) -> io::Result<Vec<CanFrame>> {
+        let mut reader = self.reader.lock().map_err(|_| io::Error::other("SLCAN reader lock poisoned"))?;
+        let mut buf = [0u8; 256];
+        let n = reader.port.read(&mut buf)?;
+        let mut frames = Vec::new();
+        for &b in &buf[..n] {
+            match b {
+                b'\r' => {
+                    if let Some((info, id, data)) = parse_slcan_frame(&reader.line) {
+                        frames.extend(CanFrame::from_parts(info, id, data));
+                    }
+                    reader.line.clear();
+                }
+                SLCAN_BEL => {
+                    log::warn!("SLCAN adapter {} reported an error", self.name);
+                    reader.line.clear();
+                }
+                // No line is longer than an extended frame with 8 bytes; drop runaway noise
+                _ if reader.line.len() < 32 => reader.line.push(b),
+                _ => reader.line.clear(),
+            }
+        }
+        Ok(frames)
+    }
+
+    fn describe(&self) -> String {
+        format!("SLCAN {} at {} bit/s", self.name, self.bitrate)
+    }
+}
+
+// ── Linux SocketCAN ─────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+pub use socketcan::SocketCan;
+
+#[cfg(target_os = "linux")]
+mod socketcan {
+    use std::ffi::CString;
+    use std::io;
+    use std::mem::{size_of, zeroed};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use super::{CanFrame, CanTransport, RECV_TIMEOUT};
+
+    /// Raw CAN socket bound to one interface (`can0`, `vcan0`, ...)
+    pub struct SocketCan {
+        fd: OwnedFd,
+        interface: String,
+    }
+
+    fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    impl SocketCan {
+        pub fn open(interface: &str) -> Result<Self, String> {
+            let name = CString::new(interface).map_err(|_| format!("Invalid interface name {:?}", interface))?;
+            let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+            if ifindex == 0 {
+                return Err(format!("CAN interface {} not found: {}", interface, io::Error::last_os_error()));
+            }
+
+            let fd = check(unsafe { libc::socket(libc::PF_CAN, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::CAN_RAW) })
+                .map_err(|e| format!("Failed to open CAN socket: {}", e))?;
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            let mut addr: libc::sockaddr_can = unsafe { zeroed() };
+            addr.can_family = libc::AF_CAN as libc::sa_family_t;
+            addr.can_ifindex = ifindex as libc::c_int;
+            check(unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                    size_of::<libc::sockaddr_can>() as libc::socklen_t,
+                )
+            })
+            .map_err(|e| format!("Failed to bind CAN socket to {}: {}", interface, e))?;
+
+            Ok(Self { fd, interface: interface.to_string() })
+        }
+    }
+
+    impl CanTransport for SocketCan {
+        fn send(&self, frames: &[CanFrame]) -> io::Result<()> {
+            for f in frames {
+                let mut raw: libc::can_frame = unsafe { zeroed() };
+                raw.can_id = if f.extended {
+                    (f.id & libc::CAN_EFF_MASK) | libc::CAN_EFF_FLAG
+                } else {
+                    f.id & libc::CAN_SFF_MASK
+                };
+                raw.can_dlc = f.len.min(8);
+                raw.data = f.data;
+                let n = check(unsafe {
+                    libc::write(
+                        self.fd.as_raw_fd(),
+                        &raw as *const libc::can_frame as *const libc::c_void,
+                        size_of::<libc::can_frame>(),
+                    ) as libc::c_int
+                })?;
+                if n as usize != size_of::<libc::can_frame>() {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "Short write on CAN socket"));
+                }
+            }
+            Ok(())
+        }
+
+        fn recv(&self) -> io::Result<Vec<CanFrame>> {
+            let mut pfd = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            if check(unsafe { libc::poll(&mut pfd, 1, RECV_TIMEOUT.as_millis() as libc::c_int) })? == 0 {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+
+            let mut raw: libc::can_frame = unsafe { zeroed() };
+            let n = check(unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    &mut raw as *mut libc::can_frame as *mut libc::c_void,
+                    size_of::<libc::can_frame>(),
+                ) as libc::c_int
+            })?;
+            // Error and remote frames carry no data for the decoders
+            if (n as usize) < size_of::<libc::can_frame>() || raw.can_id & (libc::CAN_ERR_FLAG | libc::CAN_RTR_FLAG) != 0 {
+                return Ok(Vec::new());
+            }
+            let extended = raw.can_id & libc::CAN_EFF_FLAG != 0;
+            let id = raw.can_id & if extended { libc::CAN_EFF_MASK } else { libc::CAN_SFF_MASK };
+            Ok(vec![CanFrame { id, extended, len: raw.can_dlc.min(8), data: raw.data }])
+        }
+
+        fn describe(&self) -> String {
+            format!("SocketCAN {}", self.interface)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waveshare_frame_round_trip() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let std = CanFrame::standard(0x17F, &data);
+        assert_eq!(std.to_waveshare(), motor_protocol::build_can_frame(0x17F, &data));
+        let ext = CanFrame::extended(0x1200FD01, &data);
+        assert_eq!(ext.to_waveshare(), motor_protocol::build_ext_can_frame(0x1200FD01, &data));
+        let nmt = CanFrame::standard_dlc(0x000, &[0x01, 0x05]).unwrap();
+        assert_eq!(nmt.to_waveshare(), motor_protocol::build_can_frame_dlc(0x000, &[0x01, 0x05]));
+        assert!(CanFrame::standard_dlc(0x000, &[0; 9]).is_err());
+
+        for f in [std, ext, nmt] {
+            assert_eq!(CanFrame::from_waveshare(&f.to_waveshare()), Some(f));
+        }
+        assert_eq!(CanFrame::from_waveshare(&nmt.to_waveshare()).unwrap().payload(), &[0x01, 0x05]);
+
+        // Remote frame (bit6) is dropped
+        let mut remote = std.to_waveshare();
+        remote[0] |= 0x40;
+        assert_eq!(CanFrame::from_waveshare(&remote), None);
+    }
+
+    #[test]
+    fn test_udp_transport_batches_frames() {
+        let gateway = UdpSocket::bind("127.0.0.1:0").unwrap();
+        gateway.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let transport = WaveshareUdp::connect(0, &gateway.local_addr().unwrap().to_string()).unwrap();
+
+        let frames = [
+            CanFrame::standard(0x001, &[0; 8]),
+            CanFrame::extended(0x0200FD01, &[9; 8]),
+        ];
+        transport.send(&frames).unwrap();
+        let mut buf = [0u8; 64];
+        let (n, from) = gateway.recv_from(&mut buf).unwrap();
+        assert_eq!(n, 2 * CAN_FRAME_SIZE);
+
+        // Reply with both frames plus a trailing partial frame, which is ignored
+        let mut reply = buf[..n].to_vec();
+        reply.extend_from_slice(&[0x08, 0, 0]);
+        gateway.send_to(&reply, from).unwrap();
+        assert_eq!(transport.recv().unwrap(), frames.to_vec());
+    }
+
+    #[test]
+    fn test_reassembler_handles_partial_and_coalesced_records() {
+        let a = CanFrame::standard(0x0FD, &[1; 8]).to_waveshare();
+        let b = CanFrame::extended(0x0200FD01, &[2; 8]).to_waveshare();
+        let mut stream = a.to_vec();
+        stream.extend_from_slice(&b);
+        stream.extend_from_slice(&a);
+
+        let mut r = FrameReassembler::default();
+        // Split mid-record, then two and a half records in one read
+        assert!(r.push(&stream[..5]).is_empty());
+        let got = r.push(&stream[5..33]);
+        assert_eq!(got.len(), 2);
+        assert!(got[1].extended);
+        assert_eq!(r.push(&stream[33..]).len(), 1);
+
+        r.push(&stream[..7]);
+        r.reset();
+        assert_eq!(r.push(&a).len(), 1);
+    }
+
+    #[test]
+    fn test_tcp_transport_reconnects() {
+        let gateway = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = gateway.local_addr().unwrap().to_string();
+        let transport = WaveshareTcp::connect(&addr).unwrap();
+        let (mut conn, _) = gateway.accept().unwrap();
+
+        // Frames written byte by byte still arrive whole
+        let frame = CanFrame::extended(0x0200FD01, &[7; 8]);
+        for byte in frame.to_waveshare() {
+            conn.write_all(&[byte]).unwrap();
+        }
+        let mut got = Vec::new();
+        while got.is_empty() {
+            match transport.recv() {
+                Ok(frames) => got.extend(frames),
+                Err(e) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            }
+        }
+        assert_eq!(got, vec![frame]);
+
+        // Gateway drops the link: reported once, then the transport reconnects
+        drop(conn);
+        let err = loop {
+            match transport.recv() {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                other => break other.unwrap_err(),
+            }
+        };
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        assert_eq!(transport.send(&[frame]).unwrap_err().kind(), io::ErrorKind::NotConnected);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let (mut conn, _) = loop {
+            let _ = transport.recv();
+            gateway.set_nonblocking(true).unwrap();
+            if let Ok(c) = gateway.accept() {
+                break c;
+            }
+            assert!(Instant::now() < deadline, "no reconnect");
+        };
+        conn.set_nonblocking(false).unwrap();
+        transport.send(&[frame]).unwrap();
+        let mut buf = [0u8; CAN_FRAME_SIZE];
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, frame.to_waveshare());
+    }
+
+    #[test]
+    fn test_slcan_parse_and_encode() {
+        let std = CanFrame::standard(0x17F, &[1, 2, 3, 4, 5, 6, 7, 0xAB]);
+        let ext = CanFrame::extended(0x0200FD01, &[0; 8]);
+        let nmt = CanFrame::standard_dlc(0x000, &[0x01, 0x05]).unwrap();
+        assert_eq!(encode_slcan_frame(&std), "t17F801020304050607AB\r");
+        assert_eq!(encode_slcan_frame(&nmt), "t00020105\r");
+
+        for f in [std, ext, nmt] {
+            let line = encode_slcan_frame(&f);
+            let (info, id, data) = parse_slcan_frame(line.trim_end().as_bytes()).unwrap();
+            // Same tuple as the Waveshare encoding of the frame
+            assert_eq!((info, id, data), motor_protocol::parse_can_frame(&f.to_waveshare()));
+            assert_eq!(CanFrame::from_parts(info, id, data), Some(f));
+        }
+
+        // Remote frame parses but is not a data frame
+        let (info, id, _) = parse_slcan_frame(b"r1230").unwrap();
+        assert_eq!((info, id), (0x40, 0x123));
+        assert_eq!(CanFrame::from_parts(info, id, [0; 8]), None);
+        // Acks, truncated and malformed lines
+        for bad in [&b"z"[..], b"t1238010203", b"t12390000000000000000", b"tXYZ0", b"t8000", b""] {
+            assert_eq!(parse_slcan_frame(bad), None, "{:?}", bad);
+        }
+    }
+
+    /// Pty pair whose adapter end answers each setup command with the next
+    /// of `replies`; the thread returns the adapter end and what it was sent
+    #[cfg(unix)]
+    fn scripted_slcan_adapter(
+        replies: &'static [&'static [u8]],
+    ) -> (serialport::TTYPort, std::thread::JoinHandle<(serialport::TTYPort, Vec<u8>)>) {
+        use serialport::{SerialPort, TTYPort};
+
+        let (mut adapter, host) = TTYPort::pair().unwrap();
+        adapter.set_timeout(Duration::from_secs(1)).unwrap();
+        let thread = std::thread::spawn(move || {
+            let mut sent = Vec::new();
+            for reply in replies {
+                let mut byte = [0u8; 1];
+                while byte[0] != b'\r' {
+                    adapter.read_exact(&mut byte).unwrap();
+                    sent.push(byte[0]);
+                }
+                adapter.write_all(reply).unwrap();
+            }
+            (adapter, sent)
+        });
+        (host, thread)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_slcan_setup_rejected() {
+        // Closed channel (BEL), then the bitrate is refused
+        let (host, adapter) = scripted_slcan_adapter(&[b"\x07", b"\x07"]);
+        let err = Slcan::from_port(Box::new(host), 500_000).err().unwrap();
+        assert!(err.contains("rejected bitrate 500000"), "{}", err);
+        assert_eq!(adapter.join().unwrap().1, b"C\rS6\r");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_slcan_transport_over_pty() {
+        // A stray frame from the open channel comes before the close ack
+        let (host, adapter) = scripted_slcan_adapter(&[b"t0FD10A\r\r", b"\r", b"\r"]);
+        let slcan = Slcan::from_port(Box::new(host), 1_000_000).unwrap();
+        let (mut adapter, setup) = adapter.join().unwrap();
+        assert_eq!(setup, b"C\rS8\rO\r");
+
+        // Two frames, a tx ack and an error byte, split across writes
+        adapter.write_all(b"t0FD8000102030405060").unwrap();
+        adapter.write_all(b"7\rz\r\x07T0200FD0180000000000000000\r").unwrap();
+        let mut got = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while got.len() < 2 && Instant::now() < deadline {
+            if let Ok(frames) = slcan.recv() {
+                got.extend(frames);
+            }
+        }
+        assert_eq!(got, vec![
+            CanFrame::standard(0x0FD, &[0, 1, 2, 3, 4, 5, 6, 7]),
+            CanFrame::extended(0x0200FD01, &[0; 8]),
+        ]);
+
+        slcan.send(&[CanFrame::standard(0x001, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFC])]).unwrap();
+        let mut line = [0u8; 22];
+        adapter.read_exact(&mut line).unwrap();
+        assert_eq!(&line, b"t0018FFFFFFFFFFFFFFFC\r");
+    }
+
+    /// Needs a vcan interface (`ip link add dev vcan0 type vcan && ip link set up vcan0`);
+    /// skipped when there is none
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_socketcan_vcan_loop() {
+        let (Ok(a), Ok(b)) = (SocketCan::open("vcan0"), SocketCan::open("vcan0")) else {
+            return;
+        };
+        let frames = [
+            CanFrame::standard(0x17F, &[1, 2, 3, 4, 5, 6, 7, 8]),
+            CanFrame::extended(0x0200FD01, &[8; 8]),
+            CanFrame::standard_dlc(0x000, &[0x01, 0x05]).unwrap(),
+        ];
+        a.send(&frames).unwrap();
+        let mut got = Vec::new();
+        while got.len() < frames.len() {
+            got.extend(b.recv().unwrap());
+        }
+        assert_eq!(got, frames.to_vec());
+        assert!(matches!(b.recv(), Err(e) if e.kind() == io::ErrorKind::TimedOut));
+    }
+}
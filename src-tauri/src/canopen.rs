@@ -0,0 +1,908 @@
+//! CANopen (CiA 301 / CiA 402) over the Waveshare gateway
+//!
+//! Used for motors switched to protocol 1 (`cmd_change_protocol(1)`), which
+//! no longer answer MIT or private protocol frames. Everything here is plain
+//! frame encoding and state tracking; udp.rs sends the frames and feeds the
+//! responses back in from its recv thread.
+//!
+//! Standard 11-bit COB-IDs are function code | node id:
+//!   0x000 NMT, 0x080 SYNC, 0x080+id EMCY, 0x180/0x280/0x380/0x480+id TPDO1~4,
+//!   0x200/0x300/0x400/0x500+id RPDO1~4, 0x580+id SDO response,
+//!   0x600+id SDO request, 0x700+id heartbeat
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+pub const COB_NMT: u16 = 0x000;
+pub const COB_SYNC: u16 = 0x080;
+pub const COB_EMCY: u16 = 0x080;
+pub const COB_SDO_TX: u16 = 0x580;
+pub const COB_SDO_RX: u16 = 0x600;
+pub const COB_HEARTBEAT: u16 = 0x700;
+
+// ── Object dictionary entries used by the app ───────────────────────
+pub const OD_PRODUCER_HEARTBEAT: u16 = 0x1017;
+pub const OD_CONTROLWORD: u16 = 0x6040;
+pub const OD_STATUSWORD: u16 = 0x6041;
+pub const OD_MODES_OF_OPERATION: u16 = 0x6060;
+pub const OD_MODES_DISPLAY: u16 = 0x6061;
+
+/// SDO abort code sent when the server stops answering
+pub const SDO_ABORT_TIMEOUT: u32 = 0x0504_0000;
+
+/// A node counts as lost after this many missed heartbeat periods
+const HEARTBEAT_TIMEOUT_FACTOR: u32 = 3;
+
+pub fn check_node_id(node_id: u8) -> Result<(), String> {
+    if (1..=127).contains(&node_id) {
+        Ok(())
+    } else {
+        Err(format!("Invalid CANopen node id {} (1~127)", node_id))
+    }
+}
+
+// ── NMT ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NmtCommand {
+    Start,
+    Stop,
+    PreOperational,
+    ResetNode,
+    ResetCommunication,
+}
+
+/// NMT frame data (COB-ID 0x000, DLC 2). node_id 0 addresses every node.
+pub fn nmt_frame(command: NmtCommand, node_id: u8) -> [u8; 2] {
+    let cs = match command {
+        NmtCommand::Start => 0x01,
+        NmtCommand::Stop => 0x02,
+        NmtCommand::PreOperational => 0x80,
+        NmtCommand::ResetNode => 0x81,
+        NmtCommand::ResetCommunication => 0x82,
+    };
+    [cs, node_id]
+}
+
+/// NMT state byte of a heartbeat / boot-up frame
+pub fn nmt_state_name(state: u8) -> &'static str {
+    match state & 0x7F {
+        0x00 => "boot_up",
+        0x04 => "stopped",
+        0x05 => "operational",
+        0x7F => "pre_operational",
+        _ => "unknown",
+    }
+}
+
+// ── Incoming frame classification ───────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CobKind {
+    Emcy,
+    /// TPDO 1~4
+    Tpdo(u8),
+    SdoResponse,
+    Heartbeat,
+}
+
+/// Classify a standard frame sent by a CANopen node; None for anything else
+pub fn classify_cob_id(can_id: u16) -> Option<(CobKind, u8)> {
+    let node_id = (can_id & 0x7F) as u8;
+    if node_id == 0 || can_id > 0x7FF {
+        return None;
+    }
+    let kind = match can_id & 0x780 {
+        COB_EMCY => CobKind::Emcy,
+        0x180 => CobKind::Tpdo(1),
+        0x280 => CobKind::Tpdo(2),
+        0x380 => CobKind::Tpdo(3),
+        0x480 => CobKind::Tpdo(4),
+        COB_SDO_TX => CobKind::SdoResponse,
+        COB_HEARTBEAT => CobKind::Heartbeat,
+        _ => return None,
+    };
+    Some((kind, node_id))
+}
+
+/// Payload of the "canopen-emcy" event
+#[derive(Debug, Clone, Serialize)]
+pub struct EmcyMessage {
+    pub node_id: u8,
+    /// 0x0000 = error reset / no error
+    pub error_code: u16,
+    /// Object 0x1001
+    pub error_register: u8,
+    pub vendor: [u8; 5],
+}
+
+pub fn decode_emcy(node_id: u8, data: &[u8; 8]) -> EmcyMessage {
+    let mut vendor = [0u8; 5];
+    vendor.copy_from_slice(&data[3..8]);
+    EmcyMessage {
+        node_id,
+        error_code: u16::from_le_bytes([data[0], data[1]]),
+        error_register: data[2],
+        vendor,
+    }
+}
+
+// ── SDO client ──────────────────────────────────────────────────────
+//
+// Byte0 is the command specifier; initiate frames carry index (LE) and
+// subindex in bytes 1~3, segment frames carry up to 7 data bytes in 1~7.
+
+/// Next action of an SDO transfer after a server response
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdoStep {
+    Send([u8; 8]),
+    /// Transfer finished; uploaded data (empty for a download)
+    Done(Vec<u8>),
+}
+
+/// Abort frame (client -> server or server -> client)
+pub fn sdo_abort_frame(index: u16, subindex: u8, code: u32) -> [u8; 8] {
+    let mut data = [0x80, (index & 0xFF) as u8, (index >> 8) as u8, subindex, 0, 0, 0, 0];
+    data[4..8].copy_from_slice(&code.to_le_bytes());
+    data
+}
+
+pub fn sdo_abort_description(code: u32) -> &'static str {
+    match code {
+        0x0503_0000 => "Toggle bit not alternated",
+        0x0504_0000 => "SDO protocol timed out",
+        0x0504_0001 => "Command specifier not valid",
+        0x0504_0005 => "Out of memory",
+        0x0601_0000 => "Unsupported access to an object",
+        0x0601_0001 => "Attempt to read a write-only object",
+        0x0601_0002 => "Attempt to write a read-only object",
+        0x0602_0000 => "Object does not exist",
+        0x0604_0041 => "Object cannot be mapped to the PDO",
+        0x0604_0042 => "PDO length exceeded",
+        0x0604_0043 => "General parameter incompatibility",
+        0x0606_0000 => "Access failed due to a hardware error",
+        0x0607_0010 => "Data type does not match, length of service parameter does not match",
+        0x0607_0012 => "Data type does not match, length too high",
+        0x0607_0013 => "Data type does not match, length too low",
+        0x0609_0011 => "Sub-index does not exist",
+        0x0609_0030 => "Value range of parameter exceeded",
+        0x0609_0031 => "Value of parameter written too high",
+        0x0609_0032 => "Value of parameter written too low",
+        0x0800_0000 => "General error",
+        0x0800_0020 => "Data cannot be transferred or stored",
+        0x0800_0021 => "Data cannot be transferred or stored (local control)",
+        0x0800_0022 => "Data cannot be transferred or stored (device state)",
+        _ => "Unknown abort code",
+    }
+}
+
+fn initiate_frame(cs: u8, index: u16, subindex: u8) -> [u8; 8] {
+    [cs, (index & 0xFF) as u8, (index >> 8) as u8, subindex, 0, 0, 0, 0]
+}
+
+/// Common checks on a response: server abort, and the initiate echo of index/subindex
+fn check_response(resp: &[u8; 8], index: u16, subindex: u8, initiate: bool) -> Result<(), String> {
+    let echoes = |r: &[u8; 8]| u16::from_le_bytes([r[1], r[2]]) == index && r[3] == subindex;
+    if resp[0] == 0x80 {
+        let code = u32::from_le_bytes([resp[4], resp[5], resp[6], resp[7]]);
+        return Err(format!(
+            "SDO abort 0x{:08X} on 0x{:04X}:{:02X}: {}",
+            code,
+            index,
+            subindex,
+            sdo_abort_description(code)
+        ));
+    }
+    if initiate && !echoes(resp) {
+        return Err(format!(
+            "SDO response for 0x{:04X}:{:02X}, expected 0x{:04X}:{:02X}",
+            u16::from_le_bytes([resp[1], resp[2]]),
+            resp[3],
+            index,
+            subindex
+        ));
+    }
+    Ok(())
+}
+
+/// Read an object (expedited or segmented, chosen by the server)
+#[derive(Debug, Clone)]
+pub struct SdoUpload {
+    index: u16,
+    subindex: u8,
+    /// None until the initiate response arrived
+    size: Option<usize>,
+    data: Vec<u8>,
+    toggle: bool,
+}
+
+impl SdoUpload {
+    pub fn new(index: u16, subindex: u8) -> Self {
+        Self {
+            index,
+            subindex,
+            size: None,
+            data: Vec::new(),
+            toggle: false,
+        }
+    }
+
+    pub fn initiate(&self) -> [u8; 8] {
+        initiate_frame(0x40, self.index, self.subindex)
+    }
+
+    fn segment_request(&self) -> [u8; 8] {
+        let mut data = [0u8; 8];
+        data[0] = 0x60 | ((self.toggle as u8) << 4);
+        data
+    }
+
+    pub fn on_response(&mut self, resp: &[u8; 8]) -> Result<SdoStep, String> {
+        let Some(size) = self.size else {
+            check_response(resp, self.index, self.subindex, true)?;
+            if resp[0] & 0xE0 != 0x40 {
+                return Err(format!("Unexpected SDO upload response 0x{:02X}", resp[0]));
+            }
+            let expedited = resp[0] & 0x02 != 0;
+            let size_indicated = resp[0] & 0x01 != 0;
+            if expedited {
+                let n = if size_indicated { 4 - ((resp[0] >> 2) & 0x03) as usize } else { 4 };
+                return Ok(SdoStep::Done(resp[4..4 + n].to_vec()));
+            }
+            // Segmented; a missing size just means "until the last segment"
+            self.size = Some(if size_indicated {
+                u32::from_le_bytes([resp[4], resp[5], resp[6], resp[7]]) as usize
+            } else {
+                usize::MAX
+            });
+            return Ok(SdoStep::Send(self.segment_request()));
+        };
+
+        check_response(resp, self.index, self.subindex, false)?;
+        if resp[0] & 0xE0 != 0x00 {
+            return Err(format!("Unexpected SDO upload segment 0x{:02X}", resp[0]));
+        }
+        if (resp[0] & 0x10 != 0) != self.toggle {
+            return Err("SDO upload segment toggle bit mismatch".to_string());
+        }
+        let unused = ((resp[0] >> 1) & 0x07) as usize;
+        self.data.extend_from_slice(&resp[1..8 - unused]);
+        if self.data.len() > size {
+            return Err(format!("SDO upload longer than the indicated {} bytes", size));
+        }
+        if resp[0] & 0x01 != 0 {
+            if size != usize::MAX && self.data.len() != size {
+                return Err(format!("SDO upload ended at {} of {} bytes", self.data.len(), size));
+            }
+            return Ok(SdoStep::Done(std::mem::take(&mut self.data)));
+        }
+        self.toggle = !self.toggle;
+        Ok(SdoStep::Send(self.segment_request()))
+    }
+}
+
+/// Write an object: expedited up to 4 bytes, segmented above
+#[derive(Debug, Clone)]
+pub struct SdoDownload {
+    index: u16,
+    subindex: u8,
+    data: Vec<u8>,
+    /// Bytes sent in segments so far; None until the initiate response arrived
+    offset: Option<usize>,
+    toggle: bool,
+}
+
+impl SdoDownload {
+    pub fn new(index: u16, subindex: u8, data: Vec<u8>) -> Self {
+        Self {
+            index,
+            subindex,
+            data,
+            offset: None,
+            toggle: false,
+        }
+    }
+
+    fn expedited(&self) -> bool {
+        !self.data.is_empty() && self.data.len() <= 4
+    }
+
+    pub fn initiate(&self) -> [u8; 8] {
+        if self.expedited() {
+            let n = 4 - self.data.len() as u8;
+            let mut frame = initiate_frame(0x23 | (n << 2), self.index, self.subindex);
+            frame[4..4 + self.data.len()].copy_from_slice(&self.data);
+            frame
+        } else {
+            let mut frame = initiate_frame(0x21, self.index, self.subindex);
+            frame[4..8].copy_from_slice(&(self.data.len() as u32).to_le_bytes());
+            frame
+        }
+    }
+
+    fn next_segment(&mut self, offset: usize) -> [u8; 8] {
+        let chunk = &self.data[offset..(offset + 7).min(self.data.len())];
+        let last = offset + chunk.len() == self.data.len();
+        let mut frame = [0u8; 8];
+        frame[0] = ((self.toggle as u8) << 4) | (((7 - chunk.len()) as u8) << 1) | last as u8;
+        frame[1..1 + chunk.len()].copy_from_slice(chunk);
+        self.offset = Some(offset + chunk.len());
+        frame
+    }
+
+    pub fn on_response(&mut self, resp: &[u8; 8]) -> Result<SdoStep, String> {
+        let Some(offset) = self.offset else {
+            check_response(resp, self.index, self.subindex, true)?;
+            if resp[0] & 0xE0 != 0x60 {
+                return Err(format!("Unexpected SDO download response 0x{:02X}", resp[0]));
+            }
+            if self.expedited() {
+                return Ok(SdoStep::Done(Vec::new()));
+            }
+            return Ok(SdoStep::Send(self.next_segment(0)));
+        };
+
+        check_response(resp, self.index, self.subindex, false)?;
+        if resp[0] & 0xE0 != 0x20 {
+            return Err(format!("Unexpected SDO download segment response 0x{:02X}", resp[0]));
+        }
+        if (resp[0] & 0x10 != 0) != self.toggle {
+            return Err("SDO download segment toggle bit mismatch".to_string());
+        }
+        if offset >= self.data.len() {
+            return Ok(SdoStep::Done(Vec::new()));
+        }
+        self.toggle = !self.toggle;
+        Ok(SdoStep::Send(self.next_segment(offset)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SdoTransfer {
+    Upload(SdoUpload),
+    Download(SdoDownload),
+}
+
+impl SdoTransfer {
+    pub fn upload(index: u16, subindex: u8) -> Self {
+        Self::Upload(SdoUpload::new(index, subindex))
+    }
+
+    pub fn download(index: u16, subindex: u8, data: Vec<u8>) -> Self {
+        Self::Download(SdoDownload::new(index, subindex, data))
+    }
+
+    pub fn object(&self) -> (u16, u8) {
+        match self {
+            Self::Upload(u) => (u.index, u.subindex),
+            Self::Download(d) => (d.index, d.subindex),
+        }
+    }
+
+    pub fn initiate(&self) -> [u8; 8] {
+        match self {
+            Self::Upload(u) => u.initiate(),
+            Self::Download(d) => d.initiate(),
+        }
+    }
+
+    pub fn on_response(&mut self, resp: &[u8; 8]) -> Result<SdoStep, String> {
+        match self {
+            Self::Upload(u) => u.on_response(resp),
+            Self::Download(d) => d.on_response(resp),
+        }
+    }
+}
+
+/// Receiver of SDO responses for the transfer in progress
+pub struct SdoWaiter {
+    pub node_id: u8,
+    pub tx: Sender<[u8; 8]>,
+}
+
+// ── PDO mapping ─────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PdoKind {
+    /// Received by the node (host -> motor)
+    Rpdo,
+    /// Transmitted by the node (motor -> host)
+    Tpdo,
+}
+
+/// One mapped object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdoEntry {
+    pub index: u16,
+    pub subindex: u8,
+    pub bits: u8,
+}
+
+fn default_transmission_type() -> u8 {
+    0xFF
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdoConfig {
+    pub kind: PdoKind,
+    /// PDO number 1~4
+    pub number: u8,
+    /// None = predefined connection set (e.g. TPDO1 = 0x180 + node id)
+    #[serde(default)]
+    pub cob_id: Option<u16>,
+    /// 1~240 = every n-th SYNC, 254/255 = event driven
+    #[serde(default = "default_transmission_type")]
+    pub transmission_type: u8,
+    /// TPDO only (0x18xx sub 5)
+    #[serde(default)]
+    pub event_timer_ms: Option<u16>,
+    /// Empty disables the PDO
+    pub entries: Vec<PdoEntry>,
+}
+
+/// COB-ID of PDO `number` in the predefined connection set
+pub fn pdo_default_cob_id(kind: PdoKind, number: u8, node_id: u8) -> u16 {
+    let base = match kind {
+        PdoKind::Tpdo => 0x180,
+        PdoKind::Rpdo => 0x200,
+    };
+    base + 0x100 * (number as u16 - 1) + node_id as u16
+}
+
+/// SDO writes that (re)map one PDO, in the order CiA 301 requires:
+/// disable the COB-ID, set communication parameters, clear the mapping
+/// count, write the entries, set the count, then re-enable the COB-ID.
+pub fn pdo_mapping_writes(config: &PdoConfig, node_id: u8) -> Result<Vec<(u16, u8, Vec<u8>)>, String> {
+    if !(1..=4).contains(&config.number) {
+        return Err(format!("Invalid PDO number {} (1~4)", config.number));
+    }
+    if config.entries.len() > 8 {
+        return Err(format!("Too many PDO entries ({}, max 8)", config.entries.len()));
+    }
+    let total_bits: u32 = config.entries.iter().map(|e| e.bits as u32).sum();
+    if total_bits > 64 || config.entries.iter().any(|e| e.bits == 0) {
+        return Err(format!("PDO mapping must be 1~64 bits per entry and in total, got {}", total_bits));
+    }
+
+    let (comm, mapping) = match config.kind {
+        PdoKind::Rpdo => (0x1400, 0x1600),
+        PdoKind::Tpdo => (0x1800, 0x1A00),
+    };
+    let n = config.number as u16 - 1;
+    let (comm, mapping) = (comm + n, mapping + n);
+    let cob_id = config
+        .cob_id
+        .unwrap_or_else(|| pdo_default_cob_id(config.kind, config.number, node_id)) as u32;
+
+    let mut writes = vec![
+        (comm, 1, (cob_id | 0x8000_0000).to_le_bytes().to_vec()),
+        (comm, 2, vec![config.transmission_type]),
+    ];
+    if let (PdoKind::Tpdo, Some(ms)) = (config.kind, config.event_timer_ms) {
+        writes.push((comm, 5, ms.to_le_bytes().to_vec()));
+    }
+    writes.push((mapping, 0, vec![0]));
+    for (i, e) in config.entries.iter().enumerate() {
+        let entry = ((e.index as u32) << 16) | ((e.subindex as u32) << 8) | e.bits as u32;
+        writes.push((mapping, i as u8 + 1, entry.to_le_bytes().to_vec()));
+    }
+    if !config.entries.is_empty() {
+        writes.push((mapping, 0, vec![config.entries.len() as u8]));
+        writes.push((comm, 1, cob_id.to_le_bytes().to_vec()));
+    }
+    Ok(writes)
+}
+
+// ── Heartbeat monitoring ────────────────────────────────────────────
+
+/// Payload of the "canopen-heartbeat" event and `canopen_heartbeat_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeHeartbeat {
+    pub node_id: u8,
+    pub state: u8,
+    pub state_name: &'static str,
+    /// Time since the last heartbeat
+    pub age_ms: u64,
+    /// Configured producer time, or the last measured interval
+    pub period_ms: Option<u64>,
+    pub alive: bool,
+}
+
+struct NodeEntry {
+    state: u8,
+    last: Instant,
+    interval: Option<Duration>,
+    expected: Option<Duration>,
+    lost: bool,
+}
+
+impl NodeEntry {
+    fn period(&self) -> Option<Duration> {
+        self.expected.or(self.interval)
+    }
+
+    fn timed_out(&self, now: Instant) -> bool {
+        self.period()
+            .is_some_and(|p| now.duration_since(self.last) > p * HEARTBEAT_TIMEOUT_FACTOR)
+    }
+}
+
+/// Tracks the NMT state and liveness of every node seen on the bus
+#[derive(Default)]
+pub struct HeartbeatMonitor {
+    nodes: BTreeMap<u8, NodeEntry>,
+}
+
+impl HeartbeatMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Producer heartbeat time written to the node (0 = disabled)
+    pub fn set_expected(&mut self, node_id: u8, period_ms: u16, now: Instant) {
+        let expected = (period_ms > 0).then(|| Duration::from_millis(period_ms as u64));
+        let entry = self.nodes.entry(node_id).or_insert(NodeEntry {
+            state: 0xFF,
+            last: now,
+            interval: None,
+            expected: None,
+            lost: false,
+        });
+        entry.expected = expected;
+    }
+
+    /// Record a heartbeat; returns the node status when it is new, changed
+    /// state or came back after being lost
+    pub fn record(&mut self, node_id: u8, state: u8, now: Instant) -> Option<NodeHeartbeat> {
+        let state = state & 0x7F;
+        let changed = match self.nodes.get_mut(&node_id) {
+            Some(e) => {
+                let changed = e.lost || e.state != state;
+                // A boot-up restarts the producer, so its gap is not a period
+                if state != 0x00 && e.state != 0xFF {
+                    e.interval = Some(now.duration_since(e.last));
+                }
+                e.state = state;
+                e.last = now;
+                e.lost = false;
+                changed
+            }
+            None => {
+                self.nodes.insert(
+                    node_id,
+                    NodeEntry {
+                        state,
+                        last: now,
+                        interval: None,
+                        expected: None,
+                        lost: false,
+                    },
+                );
+                true
+            }
+        };
+        changed.then(|| self.node_status(node_id, now)).flatten()
+    }
+
+    /// Nodes that missed their heartbeat since the last check
+    pub fn check(&mut self, now: Instant) -> Vec<NodeHeartbeat> {
+        let newly_lost: Vec<u8> = self
+            .nodes
+            .iter_mut()
+            .filter(|(_, e)| !e.lost && e.timed_out(now))
+            .map(|(&id, e)| {
+                e.lost = true;
+                id
+            })
+            .collect();
+        newly_lost.into_iter().filter_map(|id| self.node_status(id, now)).collect()
+    }
+
+    pub fn status(&self, now: Instant) -> Vec<NodeHeartbeat> {
+        self.nodes.keys().filter_map(|&id| self.node_status(id, now)).collect()
+    }
+
+    fn node_status(&self, node_id: u8, now: Instant) -> Option<NodeHeartbeat> {
+        let e = self.nodes.get(&node_id)?;
+        Some(NodeHeartbeat {
+            node_id,
+            state: e.state,
+            state_name: nmt_state_name(e.state),
+            age_ms: now.duration_since(e.last).as_millis() as u64,
+            period_ms: e.period().map(|p| p.as_millis() as u64),
+            alive: e.state != 0xFF && !e.lost && !e.timed_out(now),
+        })
+    }
+}
+
+// ── CiA 402 drive state machine ─────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cia402State {
+    NotReadyToSwitchOn,
+    SwitchOnDisabled,
+    ReadyToSwitchOn,
+    SwitchedOn,
+    OperationEnabled,
+    QuickStopActive,
+    FaultReactionActive,
+    Fault,
+}
+
+/// Decode the state from statusword 0x6041 bits 0~3, 5, 6
+pub fn decode_statusword(sw: u16) -> Option<Cia402State> {
+    use Cia402State::*;
+    match (sw & 0x4F, sw & 0x6F) {
+        (0x00, _) => Some(NotReadyToSwitchOn),
+        (0x40, _) => Some(SwitchOnDisabled),
+        (0x0F, _) => Some(FaultReactionActive),
+        (0x08, _) => Some(Fault),
+        (_, 0x21) => Some(ReadyToSwitchOn),
+        (_, 0x23) => Some(SwitchedOn),
+        (_, 0x27) => Some(OperationEnabled),
+        (_, 0x07) => Some(QuickStopActive),
+        _ => None,
+    }
+}
+
+// Controlword 0x6040 commands
+pub const CW_DISABLE_VOLTAGE: u16 = 0x0000;
+pub const CW_QUICK_STOP: u16 = 0x0002;
+pub const CW_SHUTDOWN: u16 = 0x0006;
+pub const CW_SWITCH_ON: u16 = 0x0007;
+pub const CW_ENABLE_OPERATION: u16 = 0x000F;
+pub const CW_FAULT_RESET: u16 = 0x0080;
+
+/// What to do next to move the drive from `current` towards `target`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cia402Step {
+    Done,
+    /// The drive is in a transient state and moves on by itself
+    Wait,
+    Write(u16),
+}
+
+/// One transition of the CiA 402 state machine towards `target`
+pub fn cia402_next_step(current: Cia402State, target: Cia402State) -> Result<Cia402Step, String> {
+    use Cia402State::*;
+    if current == target {
+        return Ok(Cia402Step::Done);
+    }
+    // Rank of the power states along the normal enable path
+    let rank = |s: Cia402State| match s {
+        SwitchOnDisabled => Some(0),
+        ReadyToSwitchOn => Some(1),
+        SwitchedOn => Some(2),
+        OperationEnabled => Some(3),
+        _ => None,
+    };
+    let cw = match (current, target) {
+        (NotReadyToSwitchOn | FaultReactionActive, _) => return Ok(Cia402Step::Wait),
+        (_, NotReadyToSwitchOn | FaultReactionActive | Fault) => {
+            return Err(format!("{:?} cannot be requested", target))
+        }
+        (Fault, _) => CW_FAULT_RESET,
+        (OperationEnabled, QuickStopActive) => CW_QUICK_STOP,
+        (_, QuickStopActive) => return Err("Quick stop is only possible from operation enabled".to_string()),
+        (QuickStopActive, _) => CW_DISABLE_VOLTAGE,
+        (_, SwitchOnDisabled) => CW_DISABLE_VOLTAGE,
+        _ => match (rank(current), rank(target)) {
+            (Some(c), Some(t)) if c < t => [CW_SHUTDOWN, CW_SWITCH_ON, CW_ENABLE_OPERATION][c],
+            // Going down: SwitchedOn/OperationEnabled -> Ready, OperationEnabled -> SwitchedOn
+            (_, Some(1)) => CW_SHUTDOWN,
+            _ => CW_SWITCH_ON,
+        },
+    };
+    Ok(Cia402Step::Write(cw))
+}
+
+/// Result of `canopen_cia402_status` / `canopen_cia402_transition`
+#[derive(Debug, Clone, Serialize)]
+pub struct Cia402Status {
+    pub node_id: u8,
+    pub statusword: u16,
+    pub state: Option<Cia402State>,
+    /// 0x6061; None when the node does not implement it
+    pub mode_display: Option<i8>,
+}
+
+// ── Shared state ────────────────────────────────────────────────────
+
+/// CANopen state shared between the command handlers and the UDP recv thread
+#[derive(Default)]
+pub struct CanopenState {
+    /// Serialises SDO transfers (one outstanding request per client)
+    pub sdo_lock: Mutex<()>,
+    pub sdo_waiter: Mutex<Option<SdoWaiter>>,
+    pub heartbeat: Mutex<HeartbeatMonitor>,
+}
+
+impl CanopenState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run a transfer against a scripted server, returning the client frames and the result
+    fn run(mut transfer: SdoTransfer, responses: &[[u8; 8]]) -> (Vec<[u8; 8]>, Result<Vec<u8>, String>) {
+        let mut sent = vec![transfer.initiate()];
+        for resp in responses {
+            match transfer.on_response(resp) {
+                Ok(SdoStep::Send(frame)) => sent.push(frame),
+                Ok(SdoStep::Done(data)) => return (sent, Ok(data)),
+                Err(e) => return (sent, Err(e)),
+            }
+        }
+        (sent, Err("server ran out of responses".to_string()))
+    }
+
+    #[test]
+    fn test_sdo_expedited() {
+        // Read statusword 0x6041 = 0x0237
+        let (sent, data) = run(
+            SdoTransfer::upload(0x6041, 0),
+            &[[0x4B, 0x41, 0x60, 0x00, 0x37, 0x02, 0x00, 0x00]],
+        );
+        assert_eq!(sent, vec![[0x40, 0x41, 0x60, 0x00, 0, 0, 0, 0]]);
+        assert_eq!(data.unwrap(), vec![0x37, 0x02]);
+
+        // Write controlword 0x6040 = 0x000F
+        let (sent, data) = run(
+            SdoTransfer::download(0x6040, 0, vec![0x0F, 0x00]),
+            &[[0x60, 0x40, 0x60, 0x00, 0, 0, 0, 0]],
+        );
+        assert_eq!(sent, vec![[0x2B, 0x40, 0x60, 0x00, 0x0F, 0x00, 0, 0]]);
+        assert!(data.unwrap().is_empty());
+
+        // Abort from the server
+        let (_, data) = run(
+            SdoTransfer::upload(0x2000, 1),
+            &[sdo_abort_frame(0x2000, 1, 0x0602_0000)],
+        );
+        assert!(data.unwrap_err().contains("Object does not exist"));
+    }
+
+    #[test]
+    fn test_sdo_segmented_upload_and_download() {
+        // Device name 0x1008 = "RS00-CANopen" (12 bytes)
+        let name = b"RS00-CANopen";
+        let mut seg1 = [0x00; 8];
+        seg1[1..8].copy_from_slice(&name[0..7]);
+        let mut seg2 = [0x10 | (2 << 1) | 1; 8];
+        seg2[1..6].copy_from_slice(&name[7..12]);
+        seg2[6] = 0;
+        seg2[7] = 0;
+        let (sent, data) = run(
+            SdoTransfer::upload(0x1008, 0),
+            &[[0x41, 0x08, 0x10, 0x00, 12, 0, 0, 0], seg1, seg2],
+        );
+        assert_eq!(data.unwrap(), name.to_vec());
+        assert_eq!(sent[1][0], 0x60);
+        assert_eq!(sent[2][0], 0x70);
+
+        // Toggle error is reported
+        let (_, data) = run(
+            SdoTransfer::upload(0x1008, 0),
+            &[[0x41, 0x08, 0x10, 0x00, 12, 0, 0, 0], seg2],
+        );
+        assert!(data.is_err());
+
+        let payload: Vec<u8> = (1..=10).collect();
+        let (sent, data) = run(
+            SdoTransfer::download(0x2001, 2, payload.clone()),
+            &[
+                [0x60, 0x01, 0x20, 0x02, 0, 0, 0, 0],
+                [0x20, 0, 0, 0, 0, 0, 0, 0],
+                [0x30, 0, 0, 0, 0, 0, 0, 0],
+            ],
+        );
+        assert!(data.unwrap().is_empty());
+        assert_eq!(sent[0], [0x21, 0x01, 0x20, 0x02, 10, 0, 0, 0]);
+        assert_eq!(sent[1], [0x00, 1, 2, 3, 4, 5, 6, 7]);
+        // Toggle set, 4 unused bytes, last segment
+        assert_eq!(sent[2], [0x10 | (4 << 1) | 1, 8, 9, 10, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_pdo_mapping_sequence() {
+        let config = PdoConfig {
+            kind: PdoKind::Tpdo,
+            number: 1,
+            cob_id: None,
+            transmission_type: 0xFF,
+            event_timer_ms: Some(10),
+            entries: vec![
+                PdoEntry { index: 0x6041, subindex: 0, bits: 16 },
+                PdoEntry { index: 0x6064, subindex: 0, bits: 32 },
+            ],
+        };
+        let writes = pdo_mapping_writes(&config, 5).unwrap();
+        let objects: Vec<(u16, u8)> = writes.iter().map(|(i, s, _)| (*i, *s)).collect();
+        assert_eq!(
+            objects,
+            vec![(0x1800, 1), (0x1800, 2), (0x1800, 5), (0x1A00, 0), (0x1A00, 1), (0x1A00, 2), (0x1A00, 0), (0x1800, 1)]
+        );
+        assert_eq!(writes[0].2, 0x8000_0185u32.to_le_bytes().to_vec());
+        assert_eq!(writes[4].2, 0x6041_0010u32.to_le_bytes().to_vec());
+        assert_eq!(writes[6].2, vec![2]);
+        assert_eq!(writes[7].2, 0x185u32.to_le_bytes().to_vec());
+
+        let too_wide = PdoConfig {
+            entries: vec![PdoEntry { index: 0x6064, subindex: 0, bits: 32 }; 3],
+            ..config
+        };
+        assert!(pdo_mapping_writes(&too_wide, 5).is_err());
+    }
+
+    #[test]
+    fn test_cia402_enable_and_fault_paths() {
+        use Cia402State::*;
+        assert_eq!(decode_statusword(0x0250), Some(SwitchOnDisabled));
+        assert_eq!(decode_statusword(0x0231), Some(ReadyToSwitchOn));
+        assert_eq!(decode_statusword(0x0233), Some(SwitchedOn));
+        assert_eq!(decode_statusword(0x0237), Some(OperationEnabled));
+        assert_eq!(decode_statusword(0x0217), Some(QuickStopActive));
+        assert_eq!(decode_statusword(0x0218), Some(Fault));
+
+        // Walk a simulated drive from fault to operation enabled
+        let apply = |s: Cia402State, cw: u16| match (s, cw) {
+            (Fault, CW_FAULT_RESET) => SwitchOnDisabled,
+            (SwitchOnDisabled, CW_SHUTDOWN) => ReadyToSwitchOn,
+            (ReadyToSwitchOn, CW_SWITCH_ON) => SwitchedOn,
+            (SwitchedOn, CW_ENABLE_OPERATION) => OperationEnabled,
+            (OperationEnabled, CW_SWITCH_ON) => SwitchedOn,
+            (_, CW_DISABLE_VOLTAGE) => SwitchOnDisabled,
+            _ => panic!("invalid controlword 0x{:04X} in {:?}", cw, s),
+        };
+        let mut state = Fault;
+        let mut words = Vec::new();
+        while let Cia402Step::Write(cw) = cia402_next_step(state, OperationEnabled).unwrap() {
+            words.push(cw);
+            state = apply(state, cw);
+        }
+        assert_eq!(words, vec![CW_FAULT_RESET, CW_SHUTDOWN, CW_SWITCH_ON, CW_ENABLE_OPERATION]);
+        assert_eq!(cia402_next_step(OperationEnabled, SwitchedOn), Ok(Cia402Step::Write(CW_SWITCH_ON)));
+        assert_eq!(cia402_next_step(QuickStopActive, OperationEnabled), Ok(Cia402Step::Write(CW_DISABLE_VOLTAGE)));
+        assert_eq!(cia402_next_step(FaultReactionActive, SwitchOnDisabled), Ok(Cia402Step::Wait));
+        assert!(cia402_next_step(SwitchedOn, QuickStopActive).is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_monitor_loss_and_recovery() {
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+        let mut hb = HeartbeatMonitor::new();
+
+        assert_eq!(hb.record(5, 0x00, ms(0)).unwrap().state_name, "boot_up");
+        assert_eq!(hb.record(5, 0x7F, ms(100)).unwrap().state_name, "pre_operational");
+        // Same state again: no event, but the interval is learned
+        assert!(hb.record(5, 0x7F, ms(200)).is_none());
+        assert_eq!(hb.status(ms(200))[0].period_ms, Some(100));
+
+        assert!(hb.check(ms(450)).is_empty());
+        let lost = hb.check(ms(550));
+        assert_eq!(lost.len(), 1);
+        assert!(!lost[0].alive);
+        assert!(hb.check(ms(600)).is_empty(), "lost is reported once");
+
+        let back = hb.record(5, 0x05, ms(700)).unwrap();
+        assert!(back.alive);
+        assert_eq!(back.state_name, "operational");
+
+        assert_eq!(classify_cob_id(0x705), Some((CobKind::Heartbeat, 5)));
+        assert_eq!(classify_cob_id(0x285), Some((CobKind::Tpdo(2), 5)));
+        assert_eq!(classify_cob_id(0x585), Some((CobKind::SdoResponse, 5)));
+        assert_eq!(classify_cob_id(0x600), None);
+    }
+}
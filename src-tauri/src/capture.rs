@@ -0,0 +1,248 @@
+//! Raw serial stream capture
+//!
+//! Captures store the bytes exactly as read from the port, so a replay runs the
+//! decoder over the same chunk boundaries, resync noise and AT text as the
+//! original session.
+//!
+//! File layout (little-endian):
+//!   header:  magic "HCAP" | version u8 | reserved [u8; 3] | start_ms u64 | baud_rate u32
+//!   records: t_us u64 (since capture start) | len u32 | bytes[len]
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::recorder::now_ms;
+
+pub const CAPTURE_MAGIC: &[u8; 4] = b"HCAP";
+pub const CAPTURE_VERSION: u8 = 1;
+const HEADER_SIZE: usize = 20;
+/// Upper bound on one record; read chunks are a few hundred bytes
+const MAX_RECORD_LEN: u32 = 1 << 20;
+
+/// Capture progress reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStats {
+    pub file_path: String,
+    pub chunks: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub last_error: Option<String>,
+}
+
+pub struct CaptureWriter {
+    writer: BufWriter<File>,
+    file_path: String,
+    started: Instant,
+    chunks: u64,
+    bytes: u64,
+    last_error: Option<String>,
+}
+
+impl CaptureWriter {
+    /// Create the file and write the header
+    pub fn create(file_path: &str, baud_rate: u32) -> Result<Self, String> {
+        let file = File::create(file_path).map_err(|e| format!("Failed to create file: {}", e))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut header = [0u8; HEADER_SIZE];
+        header[0..4].copy_from_slice(CAPTURE_MAGIC);
+        header[4] = CAPTURE_VERSION;
+        header[8..16].copy_from_slice(&now_ms().to_le_bytes());
+        header[16..20].copy_from_slice(&baud_rate.to_le_bytes());
+        writer.write_all(&header).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            writer,
+            file_path: file_path.to_string(),
+            started: Instant::now(),
+            chunks: 0,
+            bytes: 0,
+            last_error: None,
+        })
+    }
+
+    /// Append one chunk, stamped with the time since the capture started
+    pub fn write_chunk(&mut self, data: &[u8]) {
+        let t_us = self.started.elapsed().as_micros() as u64;
+        self.write_chunk_at(t_us, data);
+    }
+
+    fn write_chunk_at(&mut self, t_us: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let result = self
+            .writer
+            .write_all(&t_us.to_le_bytes())
+            .and_then(|_| self.writer.write_all(&(data.len() as u32).to_le_bytes()))
+            .and_then(|_| self.writer.write_all(data));
+
+        match result {
+            Ok(()) => {
+                self.chunks += 1;
+                self.bytes += data.len() as u64;
+            }
+            Err(e) => {
+                if self.last_error.is_none() {
+                    log::error!("Capture write failed ({}): {}", self.file_path, e);
+                }
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            file_path: self.file_path.clone(),
+            chunks: self.chunks,
+            bytes: self.bytes,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// Flush buffered records to disk and return the final stats
+    pub fn finish(mut self) -> Result<CaptureStats, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush {}: {}", self.file_path, e))?;
+        Ok(self.stats())
+    }
+}
+
+/// Metadata from the capture header
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureHeader {
+    /// Host wall-clock time when the capture started (ms since UNIX epoch)
+    pub start_ms: u64,
+    pub baud_rate: u32,
+}
+
+/// One chunk as it was read from the port
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureChunk {
+    pub t_us: u64,
+    pub data: Vec<u8>,
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    header: CaptureHeader,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(file_path: &str) -> Result<Self, String> {
+        let file = File::open(file_path).map_err(|e| format!("Failed to open {}: {}", file_path, e))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read and validate the header
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; HEADER_SIZE];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| "Not a capture file (header too short)".to_string())?;
+        if &header[0..4] != CAPTURE_MAGIC {
+            return Err("Not a capture file (bad magic)".to_string());
+        }
+        if header[4] != CAPTURE_VERSION {
+            return Err(format!("Unsupported capture version {}", header[4]));
+        }
+
+        let start_ms = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let baud_rate = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        Ok(Self {
+            reader,
+            header: CaptureHeader { start_ms, baud_rate },
+        })
+    }
+
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Next chunk, or None at a clean end of file.
+    /// A record cut short (capture interrupted mid-write) also ends the stream.
+    pub fn next_chunk(&mut self) -> Result<Option<CaptureChunk>, String> {
+        let mut rec_hdr = [0u8; 12];
+        match self.reader.read_exact(&mut rec_hdr) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+        let t_us = u64::from_le_bytes(rec_hdr[0..8].try_into().unwrap());
+        let len = u32::from_le_bytes(rec_hdr[8..12].try_into().unwrap());
+        if len > MAX_RECORD_LEN {
+            return Err(format!("Corrupt capture record (length {})", len));
+        }
+
+        let mut data = vec![0u8; len as usize];
+        match self.reader.read_exact(&mut data) {
+            Ok(()) => Ok(Some(CaptureChunk { t_us, data })),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                log::warn!("Capture ends with a truncated record at t={} us", t_us);
+                Ok(None)
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_round_trip() {
+        let path = std::env::temp_dir().join(format!("hecate_cap_{}.bin", std::process::id()));
+        let path_str = path.to_string_lossy().to_string();
+
+        let mut cap = CaptureWriter::create(&path_str, 115200).unwrap();
+        cap.write_chunk_at(0, &[0x5A, 0xA5, 0x4C]);
+        cap.write_chunk_at(1_500, &[]);
+        cap.write_chunk_at(2_000, b"OK\r\n");
+        let stats = cap.finish().unwrap();
+        assert_eq!(stats.chunks, 2, "empty reads are not recorded");
+        assert_eq!(stats.bytes, 7);
+
+        let mut reader = CaptureReader::open(&path_str).unwrap();
+        assert_eq!(reader.header().baud_rate, 115200);
+        assert_eq!(
+            reader.next_chunk().unwrap(),
+            Some(CaptureChunk { t_us: 0, data: vec![0x5A, 0xA5, 0x4C] })
+        );
+        assert_eq!(
+            reader.next_chunk().unwrap(),
+            Some(CaptureChunk { t_us: 2_000, data: b"OK\r\n".to_vec() })
+        );
+        assert_eq!(reader.next_chunk().unwrap(), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_truncated_record_ends_stream() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CAPTURE_MAGIC);
+        bytes.extend_from_slice(&[CAPTURE_VERSION, 0, 0, 0]);
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&921600u32.to_le_bytes());
+        bytes.extend_from_slice(&10u64.to_le_bytes());
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2, 3]); // 5 bytes missing
+
+        let mut reader = CaptureReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.header().baud_rate, 921600);
+        assert_eq!(reader.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    fn test_rejects_non_capture_file() {
+        assert!(CaptureReader::new(&b"timestamp_ms,system_time,acc_x"[..]).is_err());
+    }
+}
@@ -0,0 +1,500 @@
+//! Software Waveshare CAN-ETH gateway for tests and bench work without hardware
+//!
+//! `GatewaySim` binds a UDP socket (normally on localhost), unpacks the
+//! 13-byte frames the app sends, hands each one to a `BusModel` and sends the
+//! frames the model puts on the bus back to the last sender, packed the same
+//! way. `SimMotorBus` models RobStride motors in private protocol or MIT mode,
+//! enough for scans, parameter access, enable/stop and feedback.
+//!
+//! The `gateway_sim` binary runs the same emulator standalone.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::can_transport::CanFrame;
+use crate::motor_protocol::{self, ParamAccess, ParamValue, CAN_FRAME_SIZE};
+
+/// Frames per reply datagram (the gateway's 650-byte packet limit)
+const MAX_FRAMES_PER_PACKET: usize = 50;
+/// Socket poll interval; also how often `BusModel::poll` runs
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The CAN bus behind the emulated gateway
+pub trait BusModel: Send {
+    /// A frame sent by the host; returns the frames devices answer with
+    fn on_frame(&mut self, frame: &CanFrame) -> Vec<CanFrame>;
+
+    /// Spontaneous traffic (active reports, heartbeats), called every poll interval
+    fn poll(&mut self) -> Vec<CanFrame> {
+        Vec::new()
+    }
+}
+
+/// Emulated gateway; stops on drop
+pub struct GatewaySim {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<CanFrame>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GatewaySim {
+    /// Bind `bind_addr` ("127.0.0.1:0" for any free port) and start serving
+    pub fn start(bind_addr: &str, bus: Box<dyn BusModel>) -> Result<Self, String> {
+        let socket = UdpSocket::bind(bind_addr).map_err(|e| format!("Failed to bind {}: {}", bind_addr, e))?;
+        socket.set_read_timeout(Some(POLL_INTERVAL)).map_err(|e| e.to_string())?;
+        let addr = socket.local_addr().map_err(|e| e.to_string())?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let thread = {
+            let running = Arc::clone(&running);
+            let received = Arc::clone(&received);
+            std::thread::spawn(move || sim_thread(socket, bus, running, received))
+        };
+        log::info!("Gateway emulator listening on {}", addr);
+        Ok(Self { addr, running, received, thread: Some(thread) })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Every frame received from the host so far
+    pub fn received(&self) -> Vec<CanFrame> {
+        self.received.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for GatewaySim {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn send_frames(socket: &UdpSocket, to: SocketAddr, frames: &[CanFrame]) {
+    for chunk in frames.chunks(MAX_FRAMES_PER_PACKET) {
+        let packet: Vec<u8> = chunk.iter().flat_map(|f| f.to_waveshare()).collect();
+        if let Err(e) = socket.send_to(&packet, to) {
+            log::warn!("Gateway emulator send to {} failed: {}", to, e);
+        }
+    }
+}
+
+fn sim_thread(
+    socket: UdpSocket,
+    mut bus: Box<dyn BusModel>,
+    running: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<CanFrame>>>,
+) {
+    let mut buf = [0u8; 2048];
+    // Like the real gateway in UDP mode, answer whoever last sent to us
+    let mut host: Option<SocketAddr> = None;
+
+    while running.load(Ordering::SeqCst) {
+        match socket.recv_from(&mut buf) {
+            Ok((n, from)) => {
+                host = Some(from);
+                let frames: Vec<CanFrame> = buf[..n]
+                    .chunks_exact(CAN_FRAME_SIZE)
+                    .filter_map(|chunk| CanFrame::from_waveshare(chunk.try_into().ok()?))
+                    .collect();
+                if let Ok(mut r) = received.lock() {
+                    r.extend_from_slice(&frames);
+                }
+                let replies: Vec<CanFrame> = frames.iter().flat_map(|f| bus.on_frame(f)).collect();
+                send_frames(&socket, from, &replies);
+            }
+            Err(ref e) if matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) => {}
+            Err(e) => {
+                // e.g. ICMP port unreachable after the host went away (Windows)
+                log::debug!("Gateway emulator recv error: {}", e);
+            }
+        }
+
+        let spontaneous = bus.poll();
+        if let (Some(to), false) = (host, spontaneous.is_empty()) {
+            send_frames(&socket, to, &spontaneous);
+        }
+    }
+}
+
+// ── Simulated motors ────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimProtocol {
+    /// Private protocol (extended frames)
+    Private,
+    /// MIT protocol (standard frames)
+    Mit,
+}
+
+/// One simulated RobStride motor
+pub struct SimMotor {
+    pub id: u8,
+    pub protocol: SimProtocol,
+    pub device_id: u64,
+    pub version: [u8; 4],
+    /// MIT feedback goes to this host ID
+    pub mit_master_id: u8,
+    pub enabled: bool,
+    pub position: f32,
+    pub velocity: f32,
+    pub torque: f32,
+    pub temperature: f32,
+    pub fault_word: u32,
+    /// Parameter value bytes by index; starts at the table defaults
+    pub params: HashMap<u16, [u8; 4]>,
+    /// Number of type 22 saves received
+    pub saves: u32,
+}
+
+impl SimMotor {
+    pub fn new(id: u8, protocol: SimProtocol) -> Self {
+        let params = motor_protocol::all_params()
+            .map(|def| {
+                let bytes = def
+                    .default_str
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|v| ParamValue::from_f64(&def.param_type, v).ok())
+                    .map(|v| v.encode())
+                    .unwrap_or_default();
+                (def.index, bytes)
+            })
+            .collect();
+        Self {
+            id,
+            protocol,
+            device_id: 0x5253_0000_0000_0000 | id as u64,
+            version: [0, 0, 3, 22],
+            mit_master_id: 0xFD,
+            enabled: false,
+            position: 0.0,
+            velocity: 0.0,
+            torque: 0.0,
+            temperature: 30.0,
+            fault_word: 0,
+            params,
+            saves: 0,
+        }
+    }
+
+    /// Type 2 feedback to `master_id`
+    fn private_feedback(&self, master_id: u8) -> CanFrame {
+        let mode = if self.enabled { 2u16 } else { 0 };
+        let area2 = (mode << 14) | self.id as u16;
+        let mut data = [0u8; 8];
+        let fields = [
+            motor_protocol::float_to_uint(self.position, motor_protocol::P_MIN, motor_protocol::P_MAX, 16) as u16,
+            motor_protocol::float_to_uint(self.velocity, motor_protocol::V_MIN, motor_protocol::V_MAX, 16) as u16,
+            motor_protocol::float_to_uint(self.torque, motor_protocol::T_MIN, motor_protocol::T_MAX, 16) as u16,
+            (self.temperature * 10.0) as u16,
+        ];
+        for (i, v) in fields.iter().enumerate() {
+            data[2 * i..2 * i + 2].copy_from_slice(&v.to_be_bytes());
+        }
+        CanFrame::extended(motor_protocol::make_ext_can_id(2, area2, master_id), &data)
+    }
+
+    /// MIT response command 1
+    fn mit_feedback(&self) -> CanFrame {
+        let angle = motor_protocol::float_to_uint(self.position, motor_protocol::P_MIN, motor_protocol::P_MAX, 16);
+        let vel = motor_protocol::float_to_uint(self.velocity, motor_protocol::V_MIN, motor_protocol::V_MAX, 12);
+        let torque = motor_protocol::float_to_uint(self.torque, motor_protocol::T_MIN, motor_protocol::T_MAX, 12);
+        let temp = (self.temperature * 10.0) as u16;
+        let data = [
+            self.id,
+            (angle >> 8) as u8,
+            angle as u8,
+            (vel >> 4) as u8,
+            (((vel & 0xF) << 4) | (torque >> 8)) as u8,
+            torque as u8,
+            (temp >> 8) as u8,
+            temp as u8,
+        ];
+        CanFrame::standard(motor_protocol::make_can_id(0, self.mit_master_id), &data)
+    }
+
+    fn on_private(&mut self, frame: &CanFrame) -> Option<CanFrame> {
+        let (comm_type, area2, target) = motor_protocol::parse_ext_can_id(frame.id);
+        if target != self.id {
+            return None;
+        }
+        let master_id = (area2 >> 8) as u8;
+        let data = frame.data;
+        match comm_type {
+            0x00 => {
+                let id = motor_protocol::make_ext_can_id(0, self.id as u16, 0xFE);
+                Some(CanFrame::extended(id, &self.device_id.to_be_bytes()))
+            }
+            0x01 => {
+                let field = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]) as u32;
+                self.position = motor_protocol::uint_to_float(field(0), motor_protocol::P_MIN, motor_protocol::P_MAX, 16);
+                self.velocity = motor_protocol::uint_to_float(field(2), motor_protocol::V_MIN, motor_protocol::V_MAX, 16);
+                self.torque = motor_protocol::uint_to_float(area2 as u32, motor_protocol::T_MIN, motor_protocol::T_MAX, 16);
+                Some(self.private_feedback(master_id))
+            }
+            0x03 => {
+                self.enabled = true;
+                Some(self.private_feedback(master_id))
+            }
+            0x04 if data[0] == 0x00 && data[1] == 0xC4 => {
+                let v = self.version;
+                let reply = [0x00, 0xC4, 0x56, v[0], v[1], v[2], v[3], 0x00];
+                Some(CanFrame::extended(motor_protocol::make_ext_can_id(2, self.id as u16, master_id), &reply))
+            }
+            0x04 => {
+                self.enabled = false;
+                Some(self.private_feedback(master_id))
+            }
+            0x06 => {
+                self.position = 0.0;
+                Some(self.private_feedback(master_id))
+            }
+            0x11 => {
+                let index = u16::from_le_bytes([data[0], data[1]]);
+                let mut reply = [0u8; 8];
+                reply[0..2].copy_from_slice(&index.to_le_bytes());
+                let status = match self.params.get(&index) {
+                    Some(value) => {
+                        reply[4..8].copy_from_slice(value);
+                        0x00
+                    }
+                    None => 0x01,
+                };
+                let id = motor_protocol::make_ext_can_id(0x11, (status << 8) | self.id as u16, master_id);
+                Some(CanFrame::extended(id, &reply))
+            }
+            0x12 => {
+                let index = u16::from_le_bytes([data[0], data[1]]);
+                let writable = motor_protocol::find_param_by_index(index).is_some_and(|d| d.access != ParamAccess::R);
+                if writable {
+                    self.params.insert(index, [data[4], data[5], data[6], data[7]]);
+                }
+                Some(self.private_feedback(master_id))
+            }
+            0x15 => {
+                let mut reply = [0u8; 8];
+                reply[0..4].copy_from_slice(&self.fault_word.to_le_bytes());
+                Some(CanFrame::extended(motor_protocol::make_ext_can_id(0x15, self.id as u16, master_id), &reply))
+            }
+            0x16 => {
+                self.saves += 1;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn on_mit(&mut self, frame: &CanFrame) -> Option<CanFrame> {
+        let mode = (frame.id >> 8) & 0x07;
+        if frame.id & 0xFF != self.id as u32 {
+            return None;
+        }
+        let data = frame.data;
+        if mode == 0 && data[..6] == [0xFF; 6] {
+            match (data[6], data[7]) {
+                (0xFF, 0xFC) => self.enabled = true,
+                (0xFF, 0xFD) => self.enabled = false,
+                (0xFF, 0xFE) => self.position = 0.0,
+                _ => {}
+            }
+        } else if mode == 0 {
+            // Command 3: take the target position and feed-forward torque
+            let pos = ((data[0] as u32) << 8) | data[1] as u32;
+            let torque = (((data[6] & 0x0F) as u32) << 8) | data[7] as u32;
+            self.position = motor_protocol::uint_to_float(pos, motor_protocol::P_MIN, motor_protocol::P_MAX, 16);
+            self.torque = motor_protocol::uint_to_float(torque, motor_protocol::T_MIN, motor_protocol::T_MAX, 12);
+        }
+        Some(self.mit_feedback())
+    }
+}
+
+/// A bus with simulated motors; each answers frames for its own ID in its protocol
+#[derive(Default)]
+pub struct SimMotorBus {
+    motors: Arc<Mutex<Vec<SimMotor>>>,
+}
+
+impl SimMotorBus {
+    pub fn new(motors: Vec<SimMotor>) -> Self {
+        Self { motors: Arc::new(Mutex::new(motors)) }
+    }
+
+    /// Handle to the motors, to inspect or change them while the emulator runs
+    pub fn motors(&self) -> Arc<Mutex<Vec<SimMotor>>> {
+        Arc::clone(&self.motors)
+    }
+}
+
+impl BusModel for SimMotorBus {
+    fn on_frame(&mut self, frame: &CanFrame) -> Vec<CanFrame> {
+        let Ok(mut motors) = self.motors.lock() else {
+            return Vec::new();
+        };
+        motors
+            .iter_mut()
+            .filter_map(|m| match (m.protocol, frame.extended) {
+                (SimProtocol::Private, true) => m.on_private(frame),
+                (SimProtocol::Mit, false) => m.on_mit(frame),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::Instant;
+
+    use serde_json::Value;
+    use tauri::test::{mock_app, MockRuntime};
+    use tauri::{App, Listener, Manager};
+
+    use crate::can_transport::{CanTransport, WaveshareUdp};
+    use crate::param_profile::ParamProfile;
+    use crate::state::AppState;
+    use crate::udp::{self, UdpConfig};
+
+    fn motors() -> Vec<SimMotor> {
+        vec![
+            SimMotor::new(1, SimProtocol::Private),
+            SimMotor::new(2, SimProtocol::Private),
+            SimMotor::new(9, SimProtocol::Mit),
+        ]
+    }
+
+    #[test]
+    fn test_transport_round_trip_with_sim_motors() {
+        let sim = GatewaySim::start("127.0.0.1:0", Box::new(SimMotorBus::new(motors()))).unwrap();
+        let transport = WaveshareUdp::connect(0, &sim.local_addr().to_string()).unwrap();
+
+        // Parameter read and MIT enable in one datagram
+        let (ext_id, data) = motor_protocol::priv_cmd_param_read(0xFD, 2, 0x701E);
+        let enable = CanFrame::standard(motor_protocol::make_can_id(0, 9), &motor_protocol::cmd_enable());
+        transport.send(&[CanFrame::extended(ext_id, &data), enable]).unwrap();
+
+        let mut got = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while got.len() < 2 && Instant::now() < deadline {
+            if let Ok(frames) = transport.recv() {
+                got.extend(frames);
+            }
+        }
+        assert_eq!(got.len(), 2);
+        let (_, area2, target) = motor_protocol::parse_ext_can_id(got[0].id);
+        assert_eq!(target, 0xFD);
+        let def = motor_protocol::find_param("loc_kp").unwrap();
+        let typed = motor_protocol::decode_param_read_response(area2, &got[0].data).typed(def).unwrap();
+        assert_eq!(typed.value, ParamValue::F32(30.0));
+
+        assert_eq!(got[1].id, 0xFD);
+        assert_eq!(motor_protocol::decode_feedback(&got[1].data).motor_id, 9);
+        assert_eq!(sim.received().len(), 2);
+    }
+
+    /// Poll `cond` until it holds or two seconds pass
+    fn wait_until(mut cond: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            if cond() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        cond()
+    }
+
+    fn listen(app: &App<MockRuntime>, event: &str) -> Receiver<Value> {
+        let (tx, rx) = channel();
+        app.listen_any(event, move |e| {
+            if let Ok(v) = serde_json::from_str(e.payload()) {
+                let _ = tx.send(v);
+            }
+        });
+        rx
+    }
+
+    #[test]
+    fn test_connect_scan_and_save_end_to_end() {
+        let bus = SimMotorBus::new(motors());
+        let shared = bus.motors();
+        let sim = GatewaySim::start("127.0.0.1:0", Box::new(bus)).unwrap();
+
+        let app = mock_app();
+        app.manage(AppState::new());
+        let private = listen(&app, "motor-scan-result");
+        let mit = listen(&app, "motor-mit-scan-result");
+        let config = UdpConfig {
+            gateway_ip: "127.0.0.1".to_string(),
+            gateway_port: sim.local_addr().port(),
+            local_port: 0,
+            motor_id: 1,
+            master_id: 0xFD,
+        };
+        udp::udp_connect(app.handle().clone(), app.state::<AppState>(), config).unwrap();
+
+        // Scan finds the private motors by device ID and the MIT motor by its feedback
+        udp::udp_scan_motors(app.handle().clone(), app.state::<AppState>()).unwrap();
+        let mut found: Vec<u64> = private.try_iter().filter_map(|v| v.as_u64()).collect();
+        found.sort();
+        assert_eq!(found, vec![1, 2]);
+        assert_eq!(mit.try_iter().filter_map(|v| v.as_u64()).collect::<Vec<_>>(), vec![9]);
+        // The scan's last frames may still be in flight when it returns
+        assert!(wait_until(|| sim.received().len() >= 3 * 128));
+        assert_eq!(sim.received().len(), 3 * 128);
+
+        // A verified write, staged, then persisted after read-back
+        let value =
+            udp::priv_write_param(app.handle().clone(), app.state::<AppState>(), 1, "loc_kp".to_string(), 45.0, None)
+                .unwrap();
+        assert_eq!(value, ParamValue::F32(45.0));
+        assert_eq!(udp::priv_param_dirty(app.state::<AppState>(), 1).len(), 1);
+        let saved = udp::priv_save_params(app.handle().clone(), app.state::<AppState>(), 1).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(udp::priv_param_dirty(app.state::<AppState>(), 1).is_empty());
+        assert_eq!(shared.lock().unwrap()[0].params[&0x701E], 45.0f32.to_le_bytes());
+        // Type 22 has no reply, so the save may not have reached the emulator yet
+        assert!(wait_until(|| shared.lock().unwrap()[0].saves == 1));
+
+        // Out-of-range writes never reach the bus
+        let before = sim.received().len();
+        let err = udp::priv_write_param(app.handle().clone(), app.state::<AppState>(), 1, "limit_torque".to_string(), 1000.0, None)
+            .unwrap_err();
+        assert!(err.contains("out of range"), "{}", err);
+        assert_eq!(sim.received().len(), before);
+
+        // A restore would flash unrelated pending writes with its own, so it is refused
+        udp::priv_write_param(app.handle().clone(), app.state::<AppState>(), 1, "loc_kp".to_string(), 50.0, None).unwrap();
+        let before = sim.received().len();
+        let err = udp::priv_param_restore(app.handle().clone(), app.state::<AppState>(), 1, ParamProfile::defaults(1), None)
+            .unwrap_err();
+        assert!(err.contains("unsaved"), "{}", err);
+        assert_eq!(sim.received().len(), before);
+        assert_eq!(shared.lock().unwrap()[0].saves, 1);
+        assert_eq!(udp::priv_param_dirty(app.state::<AppState>(), 1).len(), 1);
+
+        // Dumping a motor that is not on the bus stops after the type 0 presence check
+        let started = Instant::now();
+        let err = udp::priv_param_dump(app.handle().clone(), app.state::<AppState>(), 5, None).unwrap_err();
+        assert!(err.contains("not answering"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(sim.received()[before..].iter().all(|f| f.id >> 24 == 0));
+
+        udp::udp_disconnect(app.state::<AppState>()).unwrap();
+    }
+}
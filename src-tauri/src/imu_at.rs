@@ -0,0 +1,346 @@
+//! HiPNUC AT-style text command layer
+//!
+//! Configuration commands are plain ASCII lines terminated by `\r\n`. The device
+//! answers in text on the same UART that carries the binary frames, so replies
+//! are recovered from the bytes the frame decoder skipped (see
+//! `HipnucDecoder::take_unframed`) and assembled into lines here.
+//!
+//! Two reply styles are handled:
+//!   - acknowledged commands (LOG, SERIALCONFIG, SAVECONFIG, ...) end with a line
+//!     starting with `OK` or `ERR`/`ERROR`
+//!   - informational commands (INFO) print a block of text with no terminator;
+//!     the reply is complete once the line stream goes quiet
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Output packets that can be scheduled with `LOG <packet> ONTIME <period>`
+pub const OUTPUT_PACKETS: &[&str] = &["HI91", "HI92", "HI81", "HI83"];
+
+/// Highest output rate accepted by the rate builders (Hz)
+pub const MAX_OUTPUT_RATE_HZ: f64 = 1000.0;
+
+/// Baud rates supported by `SERIALCONFIG`
+pub const SUPPORTED_BAUD_RATES: &[u32] = &[9600, 115200, 230400, 460800, 921600];
+
+// ── Command builders ────────────────────────────────────────────────
+
+/// `LOG <packet> ONTIME <period_s>`: schedule a packet at the given rate
+pub fn cmd_log_ontime(packet: &str, rate_hz: f64) -> Result<String, String> {
+    let packet = packet.trim().to_ascii_uppercase();
+    if !OUTPUT_PACKETS.contains(&packet.as_str()) {
+        return Err(format!(
+            "Unknown output packet '{}' (expected one of {})",
+            packet,
+            OUTPUT_PACKETS.join(", ")
+        ));
+    }
+    if !(rate_hz > 0.0 && rate_hz <= MAX_OUTPUT_RATE_HZ) {
+        return Err(format!(
+            "Output rate must be in (0, {}] Hz, got {}",
+            MAX_OUTPUT_RATE_HZ, rate_hz
+        ));
+    }
+    Ok(format!("LOG {} ONTIME {}", packet, format_period(1.0 / rate_hz)))
+}
+
+/// `UNLOG`: stop every scheduled output packet
+pub fn cmd_unlog() -> String {
+    "UNLOG".to_string()
+}
+
+/// `SERIALCONFIG <baud>`: change the UART baud rate (takes effect after the reply)
+pub fn cmd_serial_config(baud_rate: u32) -> Result<String, String> {
+    if !SUPPORTED_BAUD_RATES.contains(&baud_rate) {
+        return Err(format!("Unsupported baud rate {}", baud_rate));
+    }
+    Ok(format!("SERIALCONFIG {}", baud_rate))
+}
+
+pub fn cmd_save_config() -> String {
+    "SAVECONFIG".to_string()
+}
+
+pub fn cmd_reboot() -> String {
+    "REBOOT".to_string()
+}
+
+pub fn cmd_info() -> String {
+    "INFO".to_string()
+}
+
+/// Format a period in seconds with at most 6 decimals and no trailing zeros
+fn format_period(period_s: f64) -> String {
+    let s = format!("{:.6}", period_s);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    s.to_string()
+}
+
+// ── Response collection ─────────────────────────────────────────────
+
+/// How the reply to a command is recognised as complete
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtExpect {
+    /// Wait for an `OK` / `ERR` line
+    Ack,
+    /// Collect text until no new line arrives for `idle`
+    Text { idle: Duration },
+}
+
+/// Structured reply to one command
+#[derive(Debug, Clone, Serialize)]
+pub struct AtResponse {
+    pub command: String,
+    pub ok: bool,
+    /// Reply lines, excluding the echoed command and the OK/ERR terminator
+    pub lines: Vec<String>,
+    /// Text of the ERR line when the device rejected the command
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+struct PendingAt {
+    command: String,
+    expect: AtExpect,
+    started: Instant,
+    last_line: Option<Instant>,
+    lines: Vec<String>,
+    /// Set once a terminator line has been seen: Ok(()) or Err(err_line)
+    outcome: Option<Result<(), String>>,
+}
+
+/// Splits unframed serial bytes into text lines and matches them against the
+/// command currently waiting for a reply
+#[derive(Default)]
+pub struct AtLineBuffer {
+    partial: Vec<u8>,
+    pending: Option<PendingAt>,
+}
+
+/// Longest line kept; anything longer is binary noise, not a reply
+const MAX_LINE_LEN: usize = 256;
+
+impl AtLineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start waiting for the reply to `command`
+    pub fn begin(&mut self, command: &str, expect: AtExpect, now: Instant) -> Result<(), String> {
+        if let Some(p) = &self.pending {
+            return Err(format!("AT command '{}' still waiting for a reply", p.command));
+        }
+        self.partial.clear();
+        self.pending = Some(PendingAt {
+            command: command.to_string(),
+            expect,
+            started: now,
+            last_line: None,
+            lines: Vec::new(),
+            outcome: None,
+        });
+        Ok(())
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Feed unframed bytes; returns every complete text line for display
+    pub fn push_bytes(&mut self, bytes: &[u8], now: Instant) -> Vec<String> {
+        let mut completed = Vec::new();
+        for &b in bytes {
+            match b {
+                b'\n' => {
+                    let line = String::from_utf8_lossy(&self.partial).trim().to_string();
+                    self.partial.clear();
+                    if !line.is_empty() {
+                        completed.push(line);
+                    }
+                }
+                b'\r' => {}
+                0x20..=0x7E | b'\t' => {
+                    if self.partial.len() < MAX_LINE_LEN {
+                        self.partial.push(b);
+                    } else {
+                        self.partial.clear();
+                    }
+                }
+                _ => {
+                    // Non-printable byte: whatever was collected is not text
+                    self.partial.clear();
+                }
+            }
+        }
+
+        if let Some(p) = self.pending.as_mut() {
+            for line in &completed {
+                if p.outcome.is_some() {
+                    break;
+                }
+                if line.eq_ignore_ascii_case(&p.command) {
+                    // Command echo
+                    continue;
+                }
+                p.last_line = Some(now);
+                let upper = line.to_ascii_uppercase();
+                if upper.starts_with("OK") {
+                    p.outcome = Some(Ok(()));
+                } else if upper.starts_with("ERR") {
+                    p.outcome = Some(Err(line.clone()));
+                } else {
+                    p.lines.push(line.clone());
+                }
+            }
+        }
+
+        completed
+    }
+
+    /// Return the reply once it is complete, clearing the pending command
+    pub fn poll(&mut self, now: Instant) -> Option<AtResponse> {
+        let p = self.pending.as_ref()?;
+        let done = match (&p.outcome, p.expect) {
+            (Some(_), _) => true,
+            (None, AtExpect::Text { idle }) => p
+                .last_line
+                .map(|t| now.duration_since(t) >= idle)
+                .unwrap_or(false),
+            (None, AtExpect::Ack) => false,
+        };
+        if !done {
+            return None;
+        }
+
+        let p = self.pending.take()?;
+        let error = match &p.outcome {
+            Some(Err(e)) => Some(e.clone()),
+            _ => None,
+        };
+        Some(AtResponse {
+            command: p.command,
+            ok: error.is_none(),
+            lines: p.lines,
+            error,
+            elapsed_ms: now.duration_since(p.started).as_millis() as u64,
+        })
+    }
+}
+
+// ── INFO parsing ────────────────────────────────────────────────────
+
+/// Device identity parsed from the INFO reply
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ImuDeviceInfo {
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub firmware: Option<String>,
+    /// Every `key: value` / `key=value` pair found, keys lower-cased
+    pub fields: BTreeMap<String, String>,
+    pub raw_lines: Vec<String>,
+}
+
+pub fn parse_info(lines: &[String]) -> ImuDeviceInfo {
+    let mut info = ImuDeviceInfo {
+        raw_lines: lines.to_vec(),
+        ..Default::default()
+    };
+
+    for line in lines {
+        let Some(pos) = line.find([':', '=']) else {
+            continue;
+        };
+        let key = line[..pos].trim().to_ascii_lowercase();
+        let value = line[pos + 1..].trim().to_string();
+        if key.is_empty() || value.is_empty() {
+            continue;
+        }
+        info.fields.insert(key, value);
+    }
+
+    let pick = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| info.fields.get(*k))
+            .cloned()
+    };
+    info.model = pick(&["model", "product", "name"]);
+    info.serial_number = pick(&["sn", "serial", "serial number", "serial_number", "uuid"]);
+    info.firmware = pick(&["firmware", "fw", "version", "app version", "fw version"]);
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_ontime_period() {
+        assert_eq!(cmd_log_ontime("hi91", 200.0).unwrap(), "LOG HI91 ONTIME 0.005");
+        assert_eq!(cmd_log_ontime("HI91", 400.0).unwrap(), "LOG HI91 ONTIME 0.0025");
+        assert_eq!(cmd_log_ontime("HI92", 1.0).unwrap(), "LOG HI92 ONTIME 1");
+        assert!(cmd_log_ontime("HI99", 100.0).is_err());
+        assert!(cmd_log_ontime("HI91", 0.0).is_err());
+        assert!(cmd_log_ontime("HI91", 5000.0).is_err());
+    }
+
+    #[test]
+    fn test_ack_reply_with_echo() {
+        let t0 = Instant::now();
+        let mut buf = AtLineBuffer::new();
+        buf.begin("SAVECONFIG", AtExpect::Ack, t0).unwrap();
+        assert!(buf.begin("INFO", AtExpect::Ack, t0).is_err(), "only one pending command");
+
+        let lines = buf.push_bytes(b"SAVECONFIG\r\nO", t0);
+        assert_eq!(lines, vec!["SAVECONFIG"]);
+        assert!(buf.poll(t0).is_none());
+
+        buf.push_bytes(b"K\r\n", t0 + Duration::from_millis(20));
+        let resp = buf.poll(t0 + Duration::from_millis(20)).unwrap();
+        assert!(resp.ok);
+        assert!(resp.lines.is_empty());
+        assert_eq!(resp.elapsed_ms, 20);
+        assert!(buf.begin("INFO", AtExpect::Ack, t0).is_ok(), "reply completed the command");
+    }
+
+    #[test]
+    fn test_error_reply() {
+        let t0 = Instant::now();
+        let mut buf = AtLineBuffer::new();
+        buf.begin("LOG HI81 ONTIME 0.01", AtExpect::Ack, t0).unwrap();
+        buf.push_bytes(b"ERR: unsupported packet\r\n", t0);
+        let resp = buf.poll(t0).unwrap();
+        assert!(!resp.ok);
+        assert_eq!(resp.error.as_deref(), Some("ERR: unsupported packet"));
+    }
+
+    #[test]
+    fn test_text_reply_completes_when_idle() {
+        let t0 = Instant::now();
+        let idle = Duration::from_millis(100);
+        let mut buf = AtLineBuffer::new();
+        buf.begin("INFO", AtExpect::Text { idle }, t0).unwrap();
+
+        // Binary noise between lines must not leak into the text
+        buf.push_bytes(b"Model: HI12\r\n\x91\x08\x15SN: 123456\r\n", t0);
+        buf.push_bytes(b"Firmware=1.2.3\r\n", t0 + Duration::from_millis(50));
+        assert!(buf.poll(t0 + Duration::from_millis(100)).is_none());
+
+        let resp = buf.poll(t0 + Duration::from_millis(150)).unwrap();
+        assert_eq!(resp.lines, vec!["Model: HI12", "SN: 123456", "Firmware=1.2.3"]);
+
+        let info = parse_info(&resp.lines);
+        assert_eq!(info.model.as_deref(), Some("HI12"));
+        assert_eq!(info.serial_number.as_deref(), Some("123456"));
+        assert_eq!(info.firmware.as_deref(), Some("1.2.3"));
+    }
+
+    #[test]
+    fn test_lines_without_pending_command() {
+        let mut buf = AtLineBuffer::new();
+        let lines = buf.push_bytes(b"hello\r\n\r\nworld\n", Instant::now());
+        assert_eq!(lines, vec!["hello", "world"]);
+        assert!(buf.poll(Instant::now()).is_none());
+    }
+}
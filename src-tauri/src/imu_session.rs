@@ -0,0 +1,269 @@
+//! Named IMU sessions
+//!
+//! Each session owns one data source (serial port, capture replay or virtual
+//! IMU) and everything hanging off it: the thread flags, recording, raw
+//! capture, AT reply collector and link health.
+//! Sessions are keyed by a caller-chosen id ("torso", "foot_l", ...); commands
+//! that omit the id use `DEFAULT_SESSION`, so a single-IMU frontend works
+//! unchanged. Every event a session emits carries its `session_id`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::ahrs::Ahrs;
+use crate::allan::NoiseCollector;
+use crate::calibration::CalibrationState;
+use crate::capture::CaptureWriter;
+use crate::imu_at::AtLineBuffer;
+use crate::link_stats::LinkStats;
+use crate::output_profile::OutputProfile;
+use crate::reconnect::{ConnectionState, ConnectionStatus};
+use crate::recorder::CsvRecorder;
+
+pub const DEFAULT_SESSION: &str = "default";
+
+pub struct ImuSession {
+    pub id: String,
+    /// Currently open serial port (None if disconnected; the read thread swaps
+    /// in a new handle after a reconnect)
+    pub port: Mutex<Option<Box<dyn serialport::SerialPort>>>,
+    /// Flag to signal the serial read thread to stop
+    pub read_running: AtomicBool,
+    /// Flag to signal the capture replay thread to stop
+    pub replay_running: AtomicBool,
+    /// Flag to signal the virtual IMU thread to stop
+    pub virtual_running: AtomicBool,
+    /// Thread feeding the session (read, replay or virtual IMU). Held while a
+    /// source starts, and joined once its flag is cleared, so an old thread
+    /// never overlaps the next one or clears its flag on the way out
+    pub source_thread: Mutex<Option<JoinHandle<()>>>,
+    /// Active CSV recording
+    pub recorder: Mutex<Option<CsvRecorder>>,
+    /// Active raw byte capture
+    pub capture: Mutex<Option<CaptureWriter>>,
+    /// Text reply collector for AT commands
+    pub at_lines: Mutex<AtLineBuffer>,
+    /// Last link health snapshot published by the read thread
+    pub link_stats: Mutex<Option<LinkStats>>,
+    /// Connected / reconnecting / closed, as last reported by "serial-connection"
+    pub connection: Mutex<ConnectionStatus>,
+    /// Host-side attitude filter, when enabled
+    pub ahrs: Mutex<Option<Ahrs>>,
+    /// Active sensor calibration and any procedure in progress
+    pub calibration: Mutex<CalibrationState>,
+    /// Units, mounting rotation and derived fields of published data
+    pub output_profile: Mutex<OutputProfile>,
+    /// Static noise run for Allan analysis, while active
+    pub noise: Mutex<Option<NoiseCollector>>,
+}
+
+/// Event payload with the session id added next to the payload's own fields
+#[derive(Serialize)]
+pub struct SessionEvent<'a, T: Serialize> {
+    pub session_id: &'a str,
+    #[serde(flatten)]
+    pub data: &'a T,
+}
+
+// Only references inside, so no `T: Clone` bound (which derive would add)
+impl<T: Serialize> Clone for SessionEvent<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            session_id: self.session_id,
+            data: self.data,
+        }
+    }
+}
+
+impl ImuSession {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            port: Mutex::new(None),
+            read_running: AtomicBool::new(false),
+            replay_running: AtomicBool::new(false),
+            virtual_running: AtomicBool::new(false),
+            source_thread: Mutex::new(None),
+            recorder: Mutex::new(None),
+            capture: Mutex::new(None),
+            at_lines: Mutex::new(AtLineBuffer::new()),
+            link_stats: Mutex::new(None),
+            connection: Mutex::new(ConnectionStatus::default()),
+            ahrs: Mutex::new(None),
+            calibration: Mutex::new(CalibrationState::default()),
+            output_profile: Mutex::new(OutputProfile::default()),
+            noise: Mutex::new(None),
+        }
+    }
+
+    /// Emit `payload` tagged with this session's id
+    pub fn emit<T: Serialize, R: Runtime>(&self, app: &AppHandle<R>, event: &str, payload: &T) {
+        let tagged = SessionEvent {
+            session_id: &self.id,
+            data: payload,
+        };
+        if let Err(e) = app.emit(event, tagged) {
+            log::error!("Failed to emit {} event: {}", event, e);
+        }
+    }
+
+    /// Update the connection status and emit it as "serial-connection"
+    pub fn set_connection<R: Runtime>(&self, app: &AppHandle<R>, update: impl FnOnce(&mut ConnectionStatus)) {
+        let Ok(mut c) = self.connection.lock() else {
+            return;
+        };
+        update(&mut c);
+        self.emit(app, "serial-connection", &*c);
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        let connection = self
+            .connection
+            .lock()
+            .map(|c| c.clone())
+            .unwrap_or_default();
+        SessionInfo {
+            session_id: self.id.clone(),
+            port_name: connection.port_name,
+            baud_rate: connection.baud_rate,
+            state: connection.state,
+            recording: self.recorder.lock().map(|r| r.is_some()).unwrap_or(false),
+            capturing: self.capture.lock().map(|c| c.is_some()).unwrap_or(false),
+            replaying: self.replay_running.load(Ordering::SeqCst),
+            simulating: self.virtual_running.load(Ordering::SeqCst),
+            ahrs: self.ahrs.lock().map(|a| a.is_some()).unwrap_or(false),
+        }
+    }
+}
+
+/// Summary returned by `imu_list_sessions`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub port_name: Option<String>,
+    pub baud_rate: u32,
+    pub state: ConnectionState,
+    pub recording: bool,
+    pub capturing: bool,
+    pub replaying: bool,
+    pub simulating: bool,
+    pub ahrs: bool,
+}
+
+/// Session registry held in `AppState`
+#[derive(Default)]
+pub struct ImuSessions {
+    sessions: Mutex<HashMap<String, Arc<ImuSession>>>,
+}
+
+fn session_key(id: Option<&str>) -> &str {
+    match id.map(str::trim) {
+        Some(id) if !id.is_empty() => id,
+        _ => DEFAULT_SESSION,
+    }
+}
+
+impl ImuSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Existing session (the default one when `id` is None)
+    pub fn get(&self, id: Option<&str>) -> Result<Arc<ImuSession>, String> {
+        let key = session_key(id);
+        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions
+            .get(key)
+            .cloned()
+            .ok_or_else(|| format!("No IMU session '{}'", key))
+    }
+
+    /// Existing session, or a new idle one
+    pub fn get_or_create(&self, id: Option<&str>) -> Result<Arc<ImuSession>, String> {
+        let key = session_key(id);
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        let session = sessions
+            .entry(key.to_string())
+            .or_insert_with(|| Arc::new(ImuSession::new(key)));
+        Ok(Arc::clone(session))
+    }
+
+    /// Another running session already reading `port_name`, if any
+    pub fn port_owner(&self, port_name: &str, except: &str) -> Option<String> {
+        let sessions = self.sessions.lock().ok()?;
+        sessions
+            .values()
+            .filter(|s| s.id != except && s.read_running.load(Ordering::SeqCst))
+            .find(|s| {
+                s.connection
+                    .lock()
+                    .map(|c| c.port_name.as_deref() == Some(port_name))
+                    .unwrap_or(false)
+            })
+            .map(|s| s.id.clone())
+    }
+
+    pub fn remove(&self, id: Option<&str>) -> Result<Arc<ImuSession>, String> {
+        let key = session_key(id);
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions
+            .remove(key)
+            .ok_or_else(|| format!("No IMU session '{}'", key))
+    }
+
+    /// Every session, sorted by id
+    pub fn list(&self) -> Vec<Arc<ImuSession>> {
+        let Ok(sessions) = self.sessions.lock() else {
+            return Vec::new();
+        };
+        let mut list: Vec<_> = sessions.values().cloned().collect();
+        list.sort_by(|a, b| a.id.cmp(&b.id));
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::HipnucPacket;
+
+    #[test]
+    fn test_session_id_defaults_and_lookup() {
+        let sessions = ImuSessions::new();
+        assert!(sessions.get(None).is_err());
+
+        let default = sessions.get_or_create(Some("  ")).unwrap();
+        assert_eq!(default.id, DEFAULT_SESSION);
+        let torso = sessions.get_or_create(Some("torso")).unwrap();
+        assert!(Arc::ptr_eq(&torso, &sessions.get(Some("torso")).unwrap()));
+
+        torso.read_running.store(true, Ordering::SeqCst);
+        torso.connection.lock().unwrap().port_name = Some("COM3".to_string());
+        assert_eq!(sessions.port_owner("COM3", DEFAULT_SESSION).as_deref(), Some("torso"));
+        assert_eq!(sessions.port_owner("COM3", "torso"), None);
+
+        let ids: Vec<String> = sessions.list().iter().map(|s| s.id.clone()).collect();
+        assert_eq!(ids, vec!["default", "torso"]);
+        sessions.remove(Some("torso")).unwrap();
+        assert!(sessions.get(Some("torso")).is_err());
+    }
+
+    #[test]
+    fn test_event_payload_keeps_fields_flat() {
+        let packet = HipnucPacket::UserId(3);
+        let json = serde_json::to_value(SessionEvent {
+            session_id: "foot_l",
+            data: &packet,
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "session_id": "foot_l", "type": "UserId", "data": 3 })
+        );
+    }
+}
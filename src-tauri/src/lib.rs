@@ -1,9 +1,29 @@
+mod ahrs;
+mod allan;
+mod baud_detect;
+mod calibration;
+mod can_transport;
+mod canopen;
+mod capture;
+pub mod gateway_sim;
+mod imu_at;
+mod imu_session;
+mod link_stats;
 #[allow(dead_code)]
 mod motor_protocol;
+mod output_profile;
+mod param_profile;
+mod param_staging;
+mod pending;
 mod protocol;
+#[cfg(all(test, unix))]
+mod pty_sim;
+mod reconnect;
+mod recorder;
 mod serial;
 mod state;
 mod udp;
+mod virtual_imu;
 
 use state::AppState;
 
@@ -19,11 +39,48 @@ pub fn run() {
             serial::list_ports,
             serial::open_port,
             serial::close_port,
+            serial::serial_connection_status,
+            serial::imu_list_sessions,
+            serial::imu_remove_session,
+            serial::imu_detect_baud,
             serial::send_command,
+            serial::imu_at_command,
+            serial::imu_set_output_rate,
+            serial::imu_set_output_packets,
+            serial::imu_set_baud_rate,
+            serial::imu_save_config,
+            serial::imu_reboot,
+            serial::imu_query_info,
+            serial::imu_link_stats,
+            serial::imu_ahrs_start,
+            serial::imu_ahrs_configure,
+            serial::imu_ahrs_stop,
+            serial::imu_calib_gyro,
+            serial::imu_calib_accel_face,
+            serial::imu_calib_accel_solve,
+            serial::imu_calib_mag,
+            serial::imu_calib_get,
+            serial::imu_calib_set,
+            serial::imu_calib_reset,
+            serial::imu_set_output_profile,
+            serial::imu_get_output_profile,
+            serial::imu_noise_start,
+            serial::imu_noise_status,
+            serial::imu_noise_stop,
             serial::start_recording,
             serial::stop_recording,
+            serial::recording_status,
+            serial::start_capture,
+            serial::stop_capture,
+            serial::replay_capture,
+            serial::stop_replay,
+            serial::imu_virtual_start,
+            serial::imu_virtual_stop,
             // UDP / Motor (MIT standard frame)
             udp::udp_connect,
+            udp::tcp_connect,
+            udp::slcan_connect,
+            udp::socketcan_connect,
             udp::udp_disconnect,
             udp::udp_update_motor_ids,
             udp::udp_send_batch,
@@ -53,6 +110,20 @@ pub fn run() {
             udp::priv_change_protocol,
             udp::priv_read_version,
             udp::priv_fault_feedback,
+            udp::priv_motion_control,
+            udp::priv_param_read_await,
+            udp::priv_read_version_await,
+            udp::priv_fault_feedback_await,
+            udp::priv_get_device_id_await,
+            udp::priv_read_param,
+            udp::priv_write_param,
+            udp::priv_param_dump,
+            udp::param_profile_load,
+            udp::param_profile_save,
+            udp::param_profile_diff,
+            udp::priv_param_restore,
+            udp::priv_param_dirty,
+            udp::priv_param_revert,
             udp::get_param_table,
             udp::udp_diagnose,
             udp::udp_send_raw,
@@ -67,6 +138,20 @@ pub fn run() {
             udp::udp_mit_loop_start,
             udp::udp_mit_loop_update,
             udp::udp_mit_loop_stop,
+            udp::udp_priv_loop_start,
+            udp::udp_priv_loop_update,
+            udp::udp_priv_loop_stop,
+            udp::canopen_nmt,
+            udp::canopen_sync,
+            udp::canopen_sdo_upload,
+            udp::canopen_sdo_download,
+            udp::canopen_pdo_configure,
+            udp::canopen_rpdo_send,
+            udp::canopen_heartbeat_configure,
+            udp::canopen_heartbeat_status,
+            udp::canopen_cia402_status,
+            udp::canopen_cia402_transition,
+            udp::canopen_cia402_set_mode,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
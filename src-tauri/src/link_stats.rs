@@ -0,0 +1,208 @@
+//! IMU link health
+//!
+//! Combines the decoder counters with what the sample stream itself says:
+//! the effective packet rate, and gaps in the device `system_time` sequence.
+//! A bad cable shows up as CRC failures and resync bytes with gaps; a wrong
+//! output rate shows up as a clean link whose interval is simply not the one
+//! configured.
+
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::protocol::DecoderStats;
+
+/// An interval counts as a gap when it exceeds this multiple of the median
+const GAP_FACTOR: f64 = 1.5;
+/// Cap on intervals kept per window (a 1 s window at 1 kHz fits comfortably)
+const MAX_WINDOW_INTERVALS: usize = 4096;
+
+/// Snapshot emitted as "imu-link-stats"
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStats {
+    /// Decoder counters since the port was opened
+    pub decoder: DecoderStats,
+    /// Length of the measurement window (time since the previous snapshot)
+    pub window_ms: u64,
+    /// Distinct timestamped samples per second over the window
+    pub packet_rate_hz: f64,
+    /// Raw bytes per second over the window
+    pub byte_rate: f64,
+    /// CRC failures / frames received over the window
+    pub crc_error_ratio: f64,
+    /// Median `system_time` step over the window (the device's actual output interval)
+    pub interval_median_ms: Option<f64>,
+    /// Largest `system_time` step over the window
+    pub interval_max_ms: Option<u32>,
+    /// Steps over the window longer than 1.5× the median
+    pub gaps: u64,
+    pub gaps_total: u64,
+    /// Estimated samples lost inside gaps, since the port was opened
+    pub missed_estimate_total: u64,
+    /// Times `system_time` went backwards (device reset)
+    pub time_resets: u64,
+}
+
+pub struct LinkMonitor {
+    window_start: Instant,
+    window_bytes: u64,
+    window_samples: u64,
+    intervals: Vec<u32>,
+    last_system_time: Option<u32>,
+    prev_decoder: DecoderStats,
+    gaps_total: u64,
+    missed_estimate_total: u64,
+    time_resets: u64,
+}
+
+impl LinkMonitor {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            window_bytes: 0,
+            window_samples: 0,
+            intervals: Vec::new(),
+            last_system_time: None,
+            prev_decoder: DecoderStats::default(),
+            gaps_total: 0,
+            missed_estimate_total: 0,
+            time_resets: 0,
+        }
+    }
+
+    pub fn on_bytes(&mut self, n: usize) {
+        self.window_bytes += n as u64;
+    }
+
+    /// Account for one timestamped packet (device `system_time`, ms).
+    /// Packets repeating the previous timestamp are the same sample sent in
+    /// another format (e.g. HI91 + HI92 in one frame) and are not counted again.
+    pub fn on_sample(&mut self, system_time: u32) {
+        if let Some(last) = self.last_system_time {
+            if system_time == last {
+                return;
+            }
+            if system_time < last {
+                self.time_resets += 1;
+            } else if self.intervals.len() < MAX_WINDOW_INTERVALS {
+                self.intervals.push(system_time - last);
+            }
+        }
+        self.window_samples += 1;
+        self.last_system_time = Some(system_time);
+    }
+
+    /// Close the current window and report it together with the decoder totals
+    pub fn snapshot(&mut self, now: Instant, decoder: &DecoderStats) -> LinkStats {
+        let window_s = now.duration_since(self.window_start).as_secs_f64();
+        let per_s = |n: u64| if window_s > 0.0 { n as f64 / window_s } else { 0.0 };
+
+        let mut sorted = self.intervals.clone();
+        sorted.sort_unstable();
+        let median = match sorted.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(sorted[n / 2] as f64),
+            n => Some((sorted[n / 2 - 1] + sorted[n / 2]) as f64 / 2.0),
+        };
+
+        let mut gaps = 0;
+        if let Some(median) = median.filter(|m| *m > 0.0) {
+            for &dt in &self.intervals {
+                let dt = dt as f64;
+                // +1 ms: system_time is whole ms, so 2/3 ms jitter at 400 Hz is not a gap
+                if dt > median * GAP_FACTOR && dt > median + 1.0 {
+                    gaps += 1;
+                    self.missed_estimate_total += ((dt / median).round() as u64).saturating_sub(1);
+                }
+            }
+        }
+        self.gaps_total += gaps;
+
+        let frames = decoder.frames_ok.saturating_sub(self.prev_decoder.frames_ok);
+        let crc_failures = decoder.crc_failures.saturating_sub(self.prev_decoder.crc_failures);
+        let crc_error_ratio = if frames + crc_failures > 0 {
+            crc_failures as f64 / (frames + crc_failures) as f64
+        } else {
+            0.0
+        };
+
+        let stats = LinkStats {
+            decoder: decoder.clone(),
+            window_ms: (window_s * 1000.0) as u64,
+            packet_rate_hz: per_s(self.window_samples),
+            byte_rate: per_s(self.window_bytes),
+            crc_error_ratio,
+            interval_median_ms: median,
+            interval_max_ms: sorted.last().copied(),
+            gaps,
+            gaps_total: self.gaps_total,
+            missed_estimate_total: self.missed_estimate_total,
+            time_resets: self.time_resets,
+        };
+
+        self.window_start = now;
+        self.window_bytes = 0;
+        self.window_samples = 0;
+        self.intervals.clear();
+        self.prev_decoder = decoder.clone();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_and_gaps() {
+        let t0 = Instant::now();
+        let mut mon = LinkMonitor::new(t0);
+
+        // 100 Hz stream with two samples missing after t=500
+        let mut t = 0;
+        for i in 0..100 {
+            mon.on_sample(t);
+            t += if i == 50 { 30 } else { 10 };
+        }
+        mon.on_bytes(8_200);
+
+        let decoder = DecoderStats {
+            frames_ok: 99,
+            crc_failures: 1,
+            ..Default::default()
+        };
+        let stats = mon.snapshot(t0 + Duration::from_secs(1), &decoder);
+        assert!((stats.packet_rate_hz - 100.0).abs() < 1e-9);
+        assert!((stats.byte_rate - 8_200.0).abs() < 1e-9);
+        assert_eq!(stats.interval_median_ms, Some(10.0));
+        assert_eq!(stats.interval_max_ms, Some(30));
+        assert_eq!(stats.gaps, 1);
+        assert_eq!(stats.missed_estimate_total, 2);
+        assert!((stats.crc_error_ratio - 0.01).abs() < 1e-9);
+
+        // Next window: ratios are per window, totals carry over
+        mon.on_sample(t);
+        let stats = mon.snapshot(t0 + Duration::from_secs(2), &decoder);
+        assert_eq!(stats.gaps, 0);
+        assert_eq!(stats.gaps_total, 1);
+        assert_eq!(stats.crc_error_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_ms_jitter_is_not_a_gap() {
+        let t0 = Instant::now();
+        let mut mon = LinkMonitor::new(t0);
+        // 400 Hz with 1 ms timestamps: steps alternate 2/3 ms
+        let mut t = 1_000;
+        for i in 0..400 {
+            mon.on_sample(t);
+            t += if i % 2 == 0 { 2 } else { 3 };
+        }
+        mon.on_sample(t - 3); // same sample again as HI92: ignored
+        mon.on_sample(10); // device reboot
+        let stats = mon.snapshot(t0 + Duration::from_secs(1), &DecoderStats::default());
+        assert_eq!(stats.gaps, 0);
+        assert_eq!(stats.time_resets, 1);
+    }
+}
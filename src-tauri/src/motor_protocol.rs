@@ -44,6 +44,17 @@ pub fn build_can_frame(can_id: u16, data: &[u8; 8]) -> [u8; CAN_FRAME_SIZE] {
     frame
 }
 
+/// Build a Waveshare 13-byte frame for a standard CAN data frame with DLC = data.len() (0~8)
+pub fn build_can_frame_dlc(can_id: u16, data: &[u8]) -> [u8; CAN_FRAME_SIZE] {
+    let len = data.len().min(8);
+    let mut frame = [0u8; CAN_FRAME_SIZE];
+    frame[0] = len as u8;
+    frame[3] = (can_id >> 8) as u8;
+    frame[4] = (can_id & 0xFF) as u8;
+    frame[5..5 + len].copy_from_slice(&data[..len]);
+    frame
+}
+
 /// Build a Waveshare 13-byte frame for an extended CAN data frame (29-bit ID, 8 bytes data)
 pub fn build_ext_can_frame(ext_can_id: u32, data: &[u8; 8]) -> [u8; CAN_FRAME_SIZE] {
     let mut frame = [0u8; CAN_FRAME_SIZE];
@@ -306,6 +317,33 @@ pub fn priv_cmd_get_device_id(master_id: u8, motor_id: u8) -> (u32, [u8; 8]) {
     (ext_id, data)
 }
 
+/// Private protocol type 1: Motion control (impedance, same five terms as MIT command 3)
+/// data_area2 = torque [0~65535] → (-14~14 N.m)
+/// Byte0~1: position, Byte2~3: velocity, Byte4~5: Kp, Byte6~7: Kd (16-bit, high byte first)
+pub fn priv_cmd_motion_control(
+    motor_id: u8,
+    position: f32,
+    velocity: f32,
+    kp: f32,
+    kd: f32,
+    torque: f32,
+) -> (u32, [u8; 8]) {
+    let torq_u = float_to_uint(torque, T_MIN, T_MAX, 16) as u16;
+    let ext_id = make_ext_can_id(1, torq_u, motor_id);
+
+    let pos_u = float_to_uint(position, P_MIN, P_MAX, 16) as u16;
+    let vel_u = float_to_uint(velocity, V_MIN, V_MAX, 16) as u16;
+    let kp_u = float_to_uint(kp, KP_MIN, KP_MAX, 16) as u16;
+    let kd_u = float_to_uint(kd, KD_MIN, KD_MAX, 16) as u16;
+
+    let mut data = [0u8; 8];
+    data[0..2].copy_from_slice(&pos_u.to_be_bytes());
+    data[2..4].copy_from_slice(&vel_u.to_be_bytes());
+    data[4..6].copy_from_slice(&kp_u.to_be_bytes());
+    data[6..8].copy_from_slice(&kd_u.to_be_bytes());
+    (ext_id, data)
+}
+
 /// Private protocol type 3: Enable motor
 pub fn priv_cmd_enable(master_id: u8, motor_id: u8) -> (u32, [u8; 8]) {
     let ext_id = make_ext_can_id(3, (master_id as u16) << 8, motor_id);
@@ -459,7 +497,17 @@ pub enum ParamAccess {
     RW,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Accepted values of a writable parameter
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParamRange {
+    Any,
+    Between { min: f64, max: f64 },
+    AtLeast { min: f64 },
+    OneOf { values: &'static [u32] },
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ParamDef {
     pub index: u16,
     pub name: &'static str,
@@ -467,33 +515,37 @@ pub struct ParamDef {
     pub param_type: ParamType,
     pub access: ParamAccess,
     pub default_str: &'static str,
+    pub range: ParamRange,
+    /// Changes protocol-level behaviour (reporting, timeout, position frame);
+    /// writes need explicit confirmation
+    pub confirm: bool,
 }
 
 /// Writable parameters accessible via private protocol type 17/18
 /// Default values measured from RS00 motor (firmware 0.0.3.22)
 pub static WRITABLE_PARAMS: &[ParamDef] = &[
-    ParamDef { index: 0x7005, name: "run_mode",       desc: "Run mode (0=MIT,1=PP,2=Speed,3=Current,5=CSP)", param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x7006, name: "iq_ref",         desc: "Current mode Iq command (A)",                   param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x700A, name: "spd_ref",        desc: "Speed command (rad/s)",                         param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x700B, name: "limit_torque",   desc: "Torque limit (N.m)",                            param_type: ParamType::F32, access: ParamAccess::RW, default_str: "14" },
-    ParamDef { index: 0x7010, name: "cur_kp",         desc: "Current loop Kp",                               param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.125" },
-    ParamDef { index: 0x7011, name: "cur_ki",         desc: "Current loop Ki",                               param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.0158" },
-    ParamDef { index: 0x7014, name: "cur_filt_gain",  desc: "Current filter coefficient (0~1)",              param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.1" },
-    ParamDef { index: 0x7016, name: "loc_ref",        desc: "Position command (rad)",                        param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x7017, name: "limit_spd",      desc: "CSP speed limit (rad/s)",                       param_type: ParamType::F32, access: ParamAccess::RW, default_str: "33" },
-    ParamDef { index: 0x7018, name: "limit_cur",      desc: "Current limit (A)",                             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "16" },
-    ParamDef { index: 0x701E, name: "loc_kp",         desc: "Position loop Kp",                              param_type: ParamType::F32, access: ParamAccess::RW, default_str: "30" },
-    ParamDef { index: 0x701F, name: "spd_kp",         desc: "Speed loop Kp",                                 param_type: ParamType::F32, access: ParamAccess::RW, default_str: "5" },
-    ParamDef { index: 0x7020, name: "spd_ki",         desc: "Speed loop Ki",                                 param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.02" },
-    ParamDef { index: 0x7021, name: "spd_filt_gain",  desc: "Speed filter coefficient (0~1)",                param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.05" },
-    ParamDef { index: 0x7022, name: "acc_rad",        desc: "Speed mode acceleration (rad/s^2)",             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "100" },
-    ParamDef { index: 0x7024, name: "vel_max",        desc: "PP mode velocity (rad/s)",                      param_type: ParamType::F32, access: ParamAccess::RW, default_str: "10" },
-    ParamDef { index: 0x7025, name: "acc_set",        desc: "PP mode acceleration (rad/s^2)",                param_type: ParamType::F32, access: ParamAccess::RW, default_str: "10" },
-    ParamDef { index: 0x7026, name: "EPScan_time",    desc: "Report interval (1=10ms, +1 adds 5ms)",        param_type: ParamType::U16, access: ParamAccess::RW, default_str: "1" },
-    ParamDef { index: 0x7028, name: "canTimeout",     desc: "CAN timeout (20000=1s, 0=disabled)",            param_type: ParamType::U32, access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x7029, name: "zero_sta",       desc: "Zero mode (0=0~2pi, 1=-pi~pi)",                 param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x702A, name: "damper",         desc: "Damper switch (0=on, 1=off)",                   param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0" },
-    ParamDef { index: 0x702B, name: "add_offset",     desc: "Zero offset (rad)",                             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0" },
+    ParamDef { index: 0x7005, name: "run_mode",       desc: "Run mode (0=MIT,1=PP,2=Speed,3=Current,5=CSP)", param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0", range: ParamRange::OneOf { values: &[0, 1, 2, 3, 5] }, confirm: false },
+    ParamDef { index: 0x7006, name: "iq_ref",         desc: "Current mode Iq command (A)",                   param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0", range: ParamRange::Between { min: -16.0, max: 16.0 }, confirm: false },
+    ParamDef { index: 0x700A, name: "spd_ref",        desc: "Speed command (rad/s)",                         param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0", range: ParamRange::Between { min: -33.0, max: 33.0 }, confirm: false },
+    ParamDef { index: 0x700B, name: "limit_torque",   desc: "Torque limit (N.m)",                            param_type: ParamType::F32, access: ParamAccess::RW, default_str: "14", range: ParamRange::Between { min: 0.0, max: 14.0 }, confirm: false },
+    ParamDef { index: 0x7010, name: "cur_kp",         desc: "Current loop Kp",                               param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.125", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7011, name: "cur_ki",         desc: "Current loop Ki",                               param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.0158", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7014, name: "cur_filt_gain",  desc: "Current filter coefficient (0~1)",              param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.1", range: ParamRange::Between { min: 0.0, max: 1.0 }, confirm: false },
+    ParamDef { index: 0x7016, name: "loc_ref",        desc: "Position command (rad)",                        param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x7017, name: "limit_spd",      desc: "CSP speed limit (rad/s)",                       param_type: ParamType::F32, access: ParamAccess::RW, default_str: "33", range: ParamRange::Between { min: 0.0, max: 33.0 }, confirm: false },
+    ParamDef { index: 0x7018, name: "limit_cur",      desc: "Current limit (A)",                             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "16", range: ParamRange::Between { min: 0.0, max: 16.0 }, confirm: false },
+    ParamDef { index: 0x701E, name: "loc_kp",         desc: "Position loop Kp",                              param_type: ParamType::F32, access: ParamAccess::RW, default_str: "30", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x701F, name: "spd_kp",         desc: "Speed loop Kp",                                 param_type: ParamType::F32, access: ParamAccess::RW, default_str: "5", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7020, name: "spd_ki",         desc: "Speed loop Ki",                                 param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.02", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7021, name: "spd_filt_gain",  desc: "Speed filter coefficient (0~1)",                param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0.05", range: ParamRange::Between { min: 0.0, max: 1.0 }, confirm: false },
+    ParamDef { index: 0x7022, name: "acc_rad",        desc: "Speed mode acceleration (rad/s^2)",             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "100", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7024, name: "vel_max",        desc: "PP mode velocity (rad/s)",                      param_type: ParamType::F32, access: ParamAccess::RW, default_str: "10", range: ParamRange::Between { min: 0.0, max: 33.0 }, confirm: false },
+    ParamDef { index: 0x7025, name: "acc_set",        desc: "PP mode acceleration (rad/s^2)",                param_type: ParamType::F32, access: ParamAccess::RW, default_str: "10", range: ParamRange::AtLeast { min: 0.0 }, confirm: false },
+    ParamDef { index: 0x7026, name: "EPScan_time",    desc: "Report interval (1=10ms, +1 adds 5ms)",        param_type: ParamType::U16, access: ParamAccess::RW, default_str: "1", range: ParamRange::AtLeast { min: 1.0 }, confirm: true },
+    ParamDef { index: 0x7028, name: "canTimeout",     desc: "CAN timeout (20000=1s, 0=disabled)",            param_type: ParamType::U32, access: ParamAccess::RW, default_str: "0", range: ParamRange::Any, confirm: true },
+    ParamDef { index: 0x7029, name: "zero_sta",       desc: "Zero mode (0=0~2pi, 1=-pi~pi)",                 param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0", range: ParamRange::OneOf { values: &[0, 1] }, confirm: true },
+    ParamDef { index: 0x702A, name: "damper",         desc: "Damper switch (0=on, 1=off)",                   param_type: ParamType::U8,  access: ParamAccess::RW, default_str: "0", range: ParamRange::OneOf { values: &[0, 1] }, confirm: false },
+    ParamDef { index: 0x702B, name: "add_offset",     desc: "Zero offset (rad)",                             param_type: ParamType::F32, access: ParamAccess::RW, default_str: "0", range: ParamRange::Any, confirm: false },
 ];
 
 /// Key read-only parameters (0x3xxx index space)
@@ -501,17 +553,17 @@ pub static WRITABLE_PARAMS: &[ParamDef] = &[
 /// Full parameter read support requires firmware >= 0.0.3.5.
 /// Real-time observation data is also available via type 2 feedback frames.
 pub static READONLY_PARAMS: &[ParamDef] = &[
-    ParamDef { index: 0x3005, name: "mcuTemp",      desc: "MCU temperature (*10)",           param_type: ParamType::I16, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3006, name: "motorTemp",     desc: "Motor NTC temperature (*10)",     param_type: ParamType::I16, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3007, name: "vBus_mv",       desc: "Bus voltage (mV)",                param_type: ParamType::U16, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x300C, name: "VBUS",          desc: "Bus voltage (V)",                 param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x300E, name: "cmdIq",         desc: "Iq command (A)",                  param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3015, name: "modPos",        desc: "Single-turn angle (rad)",         param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3016, name: "mechPos",       desc: "Multi-turn position (rad)",       param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3017, name: "mechVel",       desc: "Load-side velocity (rad/s)",      param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x301E, name: "iqf",           desc: "Filtered Iq (A)",                 param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x3022, name: "faultSta",      desc: "Fault status word",               param_type: ParamType::U32, access: ParamAccess::R, default_str: "" },
-    ParamDef { index: 0x302C, name: "torque_fdb",    desc: "Torque feedback (N.m)",           param_type: ParamType::F32, access: ParamAccess::R, default_str: "" },
+    ParamDef { index: 0x3005, name: "mcuTemp",      desc: "MCU temperature (*10)",           param_type: ParamType::I16, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3006, name: "motorTemp",     desc: "Motor NTC temperature (*10)",     param_type: ParamType::I16, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3007, name: "vBus_mv",       desc: "Bus voltage (mV)",                param_type: ParamType::U16, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x300C, name: "VBUS",          desc: "Bus voltage (V)",                 param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x300E, name: "cmdIq",         desc: "Iq command (A)",                  param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3015, name: "modPos",        desc: "Single-turn angle (rad)",         param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3016, name: "mechPos",       desc: "Multi-turn position (rad)",       param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3017, name: "mechVel",       desc: "Load-side velocity (rad/s)",      param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x301E, name: "iqf",           desc: "Filtered Iq (A)",                 param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x3022, name: "faultSta",      desc: "Fault status word",               param_type: ParamType::U32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
+    ParamDef { index: 0x302C, name: "torque_fdb",    desc: "Torque feedback (N.m)",           param_type: ParamType::F32, access: ParamAccess::R, default_str: "", range: ParamRange::Any, confirm: false },
 ];
 
 /// Parse a parameter read response (type 17 reply).
@@ -542,6 +594,171 @@ pub fn decode_param_read_response(data_area2: u16, data: &[u8; 8]) -> ParamReadR
     }
 }
 
+// ── Typed parameter access ──────────────────────────────────────────
+
+/// A parameter value in its table type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ParamValue {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    I16(i16),
+    F32(f32),
+}
+
+impl ParamValue {
+    /// Convert a UI number to `param_type`, rejecting values that do not fit
+    pub fn from_f64(param_type: &ParamType, value: f64) -> Result<Self, String> {
+        fn int<T: TryFrom<i64>>(value: f64, name: &str) -> Result<T, String> {
+            if value.fract() != 0.0 || !value.is_finite() {
+                return Err(format!("{} parameter needs an integer, got {}", name, value));
+            }
+            T::try_from(value as i64).map_err(|_| format!("{} out of range for {}", value, name))
+        }
+        match param_type {
+            ParamType::U8 => int(value, "u8").map(ParamValue::U8),
+            ParamType::U16 => int(value, "u16").map(ParamValue::U16),
+            ParamType::U32 => int(value, "u32").map(ParamValue::U32),
+            ParamType::I16 => int(value, "i16").map(ParamValue::I16),
+            ParamType::F32 if value.is_finite() => Ok(ParamValue::F32(value as f32)),
+            ParamType::F32 => Err(format!("Invalid f32 value {}", value)),
+            ParamType::Str => Err("String parameters cannot be written".to_string()),
+        }
+    }
+
+    /// Decode the 4 value bytes of a type 17 reply (LE, narrow types in the low bytes)
+    pub fn decode(param_type: &ParamType, bytes: [u8; 4]) -> Result<Self, String> {
+        Ok(match param_type {
+            ParamType::U8 => ParamValue::U8(bytes[0]),
+            ParamType::U16 => ParamValue::U16(u16::from_le_bytes([bytes[0], bytes[1]])),
+            ParamType::U32 => ParamValue::U32(u32::from_le_bytes(bytes)),
+            ParamType::I16 => ParamValue::I16(i16::from_le_bytes([bytes[0], bytes[1]])),
+            ParamType::F32 => ParamValue::F32(f32::from_le_bytes(bytes)),
+            ParamType::Str => return Err("String parameters cannot be decoded".to_string()),
+        })
+    }
+
+    /// Value bytes (Byte4~7 of a type 18 write)
+    pub fn encode(&self) -> [u8; 4] {
+        let mut bytes = [0u8; 4];
+        match *self {
+            ParamValue::U8(v) => bytes[0] = v,
+            ParamValue::U16(v) => bytes[0..2].copy_from_slice(&v.to_le_bytes()),
+            ParamValue::I16(v) => bytes[0..2].copy_from_slice(&v.to_le_bytes()),
+            ParamValue::U32(v) => bytes = v.to_le_bytes(),
+            ParamValue::F32(v) => bytes = v.to_le_bytes(),
+        }
+        bytes
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            ParamValue::U8(v) => v as f64,
+            ParamValue::U16(v) => v as f64,
+            ParamValue::U32(v) => v as f64,
+            ParamValue::I16(v) => v as f64,
+            ParamValue::F32(v) => v as f64,
+        }
+    }
+}
+
+/// All parameters in the tables
+pub fn all_params() -> impl Iterator<Item = &'static ParamDef> {
+    WRITABLE_PARAMS.iter().chain(READONLY_PARAMS.iter())
+}
+
+pub fn find_param_by_index(index: u16) -> Option<&'static ParamDef> {
+    all_params().find(|p| p.index == index)
+}
+
+/// Look up a parameter by name ("loc_kp") or index ("0x701E" or decimal)
+pub fn find_param(key: &str) -> Result<&'static ParamDef, String> {
+    let key = key.trim();
+    let index = match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => key.parse::<u16>().ok(),
+    };
+    index
+        .and_then(find_param_by_index)
+        .or_else(|| all_params().find(|p| p.name == key))
+        .ok_or_else(|| format!("Unknown parameter: {}", key))
+}
+
+/// Reject reads of write-only and writes of read-only parameters
+pub fn check_param_access(def: &ParamDef, write: bool) -> Result<(), String> {
+    let allowed = match def.access {
+        ParamAccess::RW => true,
+        ParamAccess::R => !write,
+        ParamAccess::W => write,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "Parameter {} (0x{:04X}) is {}",
+            def.name,
+            def.index,
+            if write { "read-only" } else { "write-only" }
+        ))
+    }
+}
+
+/// Check a value against the parameter's range or allowed values
+pub fn validate_param_value(def: &ParamDef, value: ParamValue) -> Result<(), String> {
+    let v = value.as_f64();
+    let (ok, allowed) = match def.range {
+        ParamRange::Any => return Ok(()),
+        ParamRange::Between { min, max } => ((min..=max).contains(&v), format!("{} ~ {}", min, max)),
+        ParamRange::AtLeast { min } => (v >= min, format!(">= {}", min)),
+        ParamRange::OneOf { values } => (values.iter().any(|&a| a as f64 == v), format!("{:?}", values)),
+    };
+    if ok {
+        return Ok(());
+    }
+    Err(format!("{} out of range for {} (0x{:04X}): allowed {}", v, def.name, def.index, allowed))
+}
+
+/// Type 18 write of a table parameter, typed, access- and range-checked
+pub fn priv_cmd_param_write_value(
+    master_id: u8,
+    motor_id: u8,
+    def: &ParamDef,
+    value: ParamValue,
+) -> Result<(u32, [u8; 8]), String> {
+    check_param_access(def, true)?;
+    if ParamValue::decode(&def.param_type, value.encode())? != value {
+        return Err(format!("{:?} does not match {} type {:?}", value, def.name, def.param_type));
+    }
+    validate_param_value(def, value)?;
+    let ext_id = make_ext_can_id(0x12, (master_id as u16) << 8, motor_id);
+    let mut data = [0u8; 8];
+    data[0..2].copy_from_slice(&def.index.to_le_bytes());
+    data[4..8].copy_from_slice(&value.encode());
+    Ok((ext_id, data))
+}
+
+/// Decoded type 17 reply for a table parameter
+#[derive(Debug, Clone, Serialize)]
+pub struct TypedParam {
+    pub index: u16,
+    pub name: &'static str,
+    pub value: ParamValue,
+}
+
+impl ParamReadResponse {
+    /// Interpret the value bytes with the parameter's table type
+    pub fn typed(&self, def: &ParamDef) -> Result<TypedParam, String> {
+        if !self.success {
+            return Err(format!("Motor rejected read of {} (0x{:04X})", def.name, def.index));
+        }
+        Ok(TypedParam {
+            index: def.index,
+            name: def.name,
+            value: ParamValue::decode(&def.param_type, self.value_bytes)?,
+        })
+    }
+}
+
 // ── Tests ───────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -672,6 +889,16 @@ mod tests {
         assert_eq!(&frame[5..13], &data);
     }
 
+    #[test]
+    fn test_build_can_frame_dlc() {
+        // CANopen NMT start, node 5: DLC 2
+        let frame = build_can_frame_dlc(0x000, &[0x01, 0x05]);
+        assert_eq!(frame, [0x02, 0, 0, 0, 0, 0x01, 0x05, 0, 0, 0, 0, 0, 0]);
+        let (info, can_id, _) = parse_can_frame(&build_can_frame_dlc(0x605, &[0x40; 8]));
+        assert_eq!(info, 0x08);
+        assert_eq!(can_id, 0x605);
+    }
+
     #[test]
     fn test_parse_can_frame_roundtrip() {
         let can_id = make_can_id(1, 5);
@@ -722,6 +949,23 @@ mod tests {
         assert!((val - 30.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_priv_cmd_motion_control() {
+        let (ext_id, data) = priv_cmd_motion_control(0x7F, 1.0, -2.0, 50.0, 1.0, 3.0);
+        let (comm_type, data_area2, target_id) = parse_ext_can_id(ext_id);
+        assert_eq!(comm_type, 1);
+        assert_eq!(target_id, 0x7F);
+        // Torque rides in the CAN ID
+        let torq_back = uint_to_float(data_area2 as u32, T_MIN, T_MAX, 16);
+        assert!((torq_back - 3.0).abs() < 0.001);
+
+        let field = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]) as u32;
+        assert!((uint_to_float(field(0), P_MIN, P_MAX, 16) - 1.0).abs() < 0.001);
+        assert!((uint_to_float(field(2), V_MIN, V_MAX, 16) + 2.0).abs() < 0.001);
+        assert!((uint_to_float(field(4), KP_MIN, KP_MAX, 16) - 50.0).abs() < 0.01);
+        assert!((uint_to_float(field(6), KD_MIN, KD_MAX, 16) - 1.0).abs() < 0.001);
+    }
+
     #[test]
     fn test_priv_cmd_param_read() {
         let (ext_id, data) = priv_cmd_param_read(0xFD, 0x7F, 0x701E);
@@ -745,6 +989,58 @@ mod tests {
         assert!((resp.value_f32 - 30.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_typed_param_encode_decode() {
+        let run_mode = find_param("run_mode").unwrap();
+        assert_eq!(find_param("0x7005").unwrap().name, "run_mode");
+        assert_eq!(find_param("28677").unwrap().name, "run_mode");
+        assert!(find_param("no_such_param").is_err());
+
+        // U8 goes in Byte4 only
+        let value = ParamValue::from_f64(&run_mode.param_type, 5.0).unwrap();
+        let (_, data) = priv_cmd_param_write_value(0xFD, 1, run_mode, value).unwrap();
+        assert_eq!(data, [0x05, 0x70, 0, 0, 5, 0, 0, 0]);
+        assert!(ParamValue::from_f64(&ParamType::U8, 256.0).is_err());
+        assert!(ParamValue::from_f64(&ParamType::U16, 1.5).is_err());
+        // Type mismatch is caught before sending
+        assert!(priv_cmd_param_write_value(0xFD, 1, run_mode, ParamValue::F32(5.0)).is_err());
+
+        // I16 keeps its sign on read
+        let motor_temp = find_param("motorTemp").unwrap();
+        let t = (-123i16).to_le_bytes();
+        let data = [0x06, 0x30, 0, 0, t[0], t[1], 0, 0];
+        let typed = decode_param_read_response(0x0001, &data).typed(motor_temp).unwrap();
+        assert_eq!(typed.value, ParamValue::I16(-123));
+        assert_eq!(typed.value.as_f64(), -123.0);
+
+        // Read-only parameters cannot be written
+        let err = priv_cmd_param_write_value(0xFD, 1, motor_temp, ParamValue::I16(0)).unwrap_err();
+        assert!(err.contains("read-only"));
+    }
+
+    #[test]
+    fn test_param_range_validation() {
+        let run_mode = find_param("run_mode").unwrap();
+        assert!(validate_param_value(run_mode, ParamValue::U8(5)).is_ok());
+        let err = priv_cmd_param_write_value(0xFD, 1, run_mode, ParamValue::U8(4)).unwrap_err();
+        assert!(err.contains("[0, 1, 2, 3, 5]"), "{}", err);
+
+        let limit_torque = find_param("limit_torque").unwrap();
+        assert!(validate_param_value(limit_torque, ParamValue::F32(14.0)).is_ok());
+        assert!(validate_param_value(limit_torque, ParamValue::F32(1000.0)).is_err());
+        assert!(validate_param_value(limit_torque, ParamValue::F32(-1.0)).is_err());
+        assert!(validate_param_value(find_param("EPScan_time").unwrap(), ParamValue::U16(0)).is_err());
+        assert!(validate_param_value(find_param("loc_ref").unwrap(), ParamValue::F32(-1e6)).is_ok());
+
+        // Every table default is within its own range
+        for def in WRITABLE_PARAMS {
+            let v = ParamValue::from_f64(&def.param_type, def.default_str.parse().unwrap()).unwrap();
+            assert!(validate_param_value(def, v).is_ok(), "default of {}", def.name);
+        }
+        let confirm: Vec<&str> = all_params().filter(|p| p.confirm).map(|p| p.name).collect();
+        assert_eq!(confirm, vec!["EPScan_time", "canTimeout", "zero_sta"]);
+    }
+
     #[test]
     fn test_position_cmd_le_bytes() {
         // PDF example: position=5rad, speed=5rad/s → 00 00 A0 40 00 00 A0 40
@@ -0,0 +1,188 @@
+//! Output profile: how IMU data is presented to the rest of the app
+//!
+//! Applied to every packet after calibration, in this order:
+//!   1. mounting rotation: acc/gyr/mag and attitude move from the IMU frame to
+//!      the robot body frame
+//!   2. gravity-free `linear_acc` from the (rotated) quaternion, on "imu-data"
+//!   3. unit conversion of acc and gyr
+//!
+//! The host AHRS runs between 1 and 3, so it always sees m/s^2 and deg/s.
+//! Earth-frame navigation fields (HI81/HI83 `vel_enu`, `acc_enu`) are left as is.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ahrs::{euler_to_quat, quat_conj, quat_mul, quat_rotate, quat_to_euler};
+use crate::protocol::{Hi91Data, SensorsMut, GRAVITY};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccUnit {
+    /// m/s^2 (decoder default)
+    MS2,
+    G,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GyrUnit {
+    /// deg/s (decoder default)
+    DegS,
+    RadS,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputProfile {
+    pub acc_unit: AccUnit,
+    pub gyr_unit: GyrUnit,
+    /// IMU-to-body rotation (w, x, y, z): v_body = q * v_imu * q^-1
+    pub mounting: [f64; 4],
+    /// Add gravity-free `linear_acc` to "imu-data"
+    pub linear_acc: bool,
+}
+
+impl Default for OutputProfile {
+    fn default() -> Self {
+        Self {
+            acc_unit: AccUnit::MS2,
+            gyr_unit: GyrUnit::DegS,
+            mounting: [1.0, 0.0, 0.0, 0.0],
+            linear_acc: true,
+        }
+    }
+}
+
+impl OutputProfile {
+    /// Normalise the mounting quaternion; rejects a zero one
+    pub fn validated(mut self) -> Result<Self, String> {
+        let n = self.mounting.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if !n.is_finite() || n < 1e-6 {
+            return Err("Mounting quaternion must be non-zero".to_string());
+        }
+        self.mounting = self.mounting.map(|v| v / n);
+        Ok(self)
+    }
+
+    fn has_mounting(&self) -> bool {
+        self.mounting != [1.0, 0.0, 0.0, 0.0]
+    }
+
+    /// Move vectors and attitude from the IMU frame to the body frame
+    pub fn apply_mounting(&self, sensors: SensorsMut<'_>) {
+        if !self.has_mounting() {
+            return;
+        }
+        let m = self.mounting;
+        for v in [sensors.acc, sensors.gyr, sensors.mag].into_iter().flatten() {
+            *v = quat_rotate(m, *v);
+        }
+
+        // body -> earth = (imu -> earth) * (body -> imu)
+        let quat = match (&sensors.quat, &sensors.euler) {
+            (Some(q), _) if q.iter().any(|v| *v != 0.0) => Some(**q),
+            (_, Some([r, p, y])) => Some(euler_to_quat(**r, **p, **y)),
+            _ => None,
+        };
+        let Some(quat) = quat else {
+            return;
+        };
+        let body = quat_mul(quat, quat_conj(m));
+        if let Some(q) = sensors.quat {
+            *q = body;
+        }
+        if let Some([r, p, y]) = sensors.euler {
+            (*r, *p, *y) = quat_to_euler(body);
+        }
+    }
+
+    /// Fill in `linear_acc` (while still in m/s^2), then convert units
+    pub fn finish_sample(&self, s: &mut Hi91Data) {
+        if self.linear_acc && s.quat.iter().any(|v| *v != 0.0) {
+            let gravity = quat_rotate(quat_conj(s.quat), [0.0, 0.0, GRAVITY]);
+            s.linear_acc = Some(std::array::from_fn(|i| s.acc[i] - gravity[i]));
+        }
+        if self.acc_unit == AccUnit::G {
+            if let Some(lin) = s.linear_acc.as_mut() {
+                *lin = lin.map(|v| v / GRAVITY);
+            }
+        }
+        self.convert_units(SensorsMut {
+            acc: Some(&mut s.acc),
+            gyr: Some(&mut s.gyr),
+            mag: None,
+            quat: None,
+            euler: None,
+        });
+    }
+
+    pub fn convert_units(&self, sensors: SensorsMut<'_>) {
+        if let (AccUnit::G, Some(acc)) = (self.acc_unit, sensors.acc) {
+            *acc = acc.map(|v| v / GRAVITY);
+        }
+        if let (GyrUnit::RadS, Some(gyr)) = (self.gyr_unit, sensors.gyr) {
+            *gyr = gyr.map(f64::to_radians);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::HipnucPacket;
+
+    /// IMU lying flat, rotated 90 deg about z relative to the body
+    fn flat_sample() -> Hi91Data {
+        Hi91Data {
+            acc: [0.0, 0.0, GRAVITY],
+            gyr: [10.0, 0.0, 0.0],
+            mag: [20.0, 0.0, -40.0],
+            quat: [1.0, 0.0, 0.0, 0.0],
+            ..Default::default()
+        }
+    }
+
+    fn close(a: &[f64], b: &[f64]) -> bool {
+        a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_mounting_rotates_vectors_and_attitude() {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        // IMU x axis points along body y
+        let profile = OutputProfile {
+            mounting: [h, 0.0, 0.0, h],
+            ..Default::default()
+        };
+        let mut packet = HipnucPacket::Hi91(flat_sample());
+        profile.apply_mounting(packet.sensors_mut().unwrap());
+        let HipnucPacket::Hi91(d) = packet else {
+            unreachable!()
+        };
+        assert!(close(&d.gyr, &[0.0, 10.0, 0.0]), "gyr {:?}", d.gyr);
+        assert!(close(&d.mag, &[0.0, 20.0, -40.0]), "mag {:?}", d.mag);
+        assert!(close(&d.acc, &[0.0, 0.0, GRAVITY]));
+        // The body is turned -90 deg about z relative to the IMU
+        assert!((d.yaw + 90.0).abs() < 1e-9, "yaw {}", d.yaw);
+        assert!(d.roll.abs() < 1e-9 && d.pitch.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_linear_acc_and_units() {
+        let profile = OutputProfile {
+            acc_unit: AccUnit::G,
+            gyr_unit: GyrUnit::RadS,
+            ..Default::default()
+        };
+        // Pitched 30 deg, accelerating 1 m/s^2 along body y
+        let mut s = flat_sample();
+        s.quat = euler_to_quat(0.0, 30.0, 0.0);
+        let gravity_body = quat_rotate(quat_conj(s.quat), [0.0, 0.0, GRAVITY]);
+        s.acc = [gravity_body[0], gravity_body[1] + 1.0, gravity_body[2]];
+
+        profile.finish_sample(&mut s);
+        let lin = s.linear_acc.unwrap();
+        assert!(close(&lin, &[0.0, 1.0 / GRAVITY, 0.0]), "linear_acc {:?}", lin);
+        assert!((s.gyr[0] - 10f64.to_radians()).abs() < 1e-12);
+        assert!(OutputProfile { mounting: [0.0; 4], ..Default::default() }.validated().is_err());
+    }
+}
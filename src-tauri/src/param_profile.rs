@@ -0,0 +1,216 @@
+//! Motor parameter profiles: snapshot, diff and restore
+//!
+//! A profile is every parameter in `WRITABLE_PARAMS` and `READONLY_PARAMS`
+//! read from one motor, together with its firmware version and device id.
+//! Profiles are stored as JSON or TOML, chosen by the file extension.
+//!
+//! Restore only writes writable parameters whose value differs from the
+//! motor, and never the setpoints (`SETPOINT_PARAMS`): replaying a stored
+//! iq_ref / spd_ref / loc_ref would move the motor.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::motor_protocol::{self, ParamAccess, ParamDef, ParamValue};
+
+/// Bumped when the file layout changes
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Writable parameters that are commands rather than configuration
+pub const SETPOINT_PARAMS: &[&str] = &["iq_ref", "spd_ref", "loc_ref"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub index: u16,
+    pub name: String,
+    /// None when the read failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<ParamValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParamProfile {
+    pub version: u32,
+    pub motor_id: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// Unix time of the dump (ms)
+    pub created_ms: u64,
+    pub params: Vec<ProfileEntry>,
+}
+
+/// One parameter that differs between two profiles
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamDiff {
+    pub index: u16,
+    pub name: String,
+    /// Value on the left / right; None = missing or unreadable
+    pub left: Option<ParamValue>,
+    pub right: Option<ParamValue>,
+}
+
+impl ParamProfile {
+    pub fn value(&self, index: u16) -> Option<ParamValue> {
+        self.params.iter().find(|e| e.index == index).and_then(|e| e.value)
+    }
+
+    /// Table defaults (`default_str`) as a profile; read-only entries have none
+    pub fn defaults(motor_id: u8) -> Self {
+        let params = motor_protocol::all_params()
+            .map(|def| ProfileEntry {
+                index: def.index,
+                name: def.name.to_string(),
+                value: default_value(def),
+                error: None,
+            })
+            .collect();
+        Self {
+            version: PROFILE_VERSION,
+            motor_id,
+            firmware: None,
+            device_id: None,
+            created_ms: 0,
+            params,
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let profile: Self = if is_toml(path) {
+            toml::from_str(&text).map_err(|e| format!("Invalid TOML profile {}: {}", path, e))?
+        } else {
+            serde_json::from_str(&text).map_err(|e| format!("Invalid JSON profile {}: {}", path, e))?
+        };
+        if profile.version > PROFILE_VERSION {
+            return Err(format!(
+                "Profile version {} is newer than supported ({})",
+                profile.version, PROFILE_VERSION
+            ));
+        }
+        Ok(profile)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = if is_toml(path) {
+            toml::to_string_pretty(self).map_err(|e| e.to_string())?
+        } else {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())?
+        };
+        std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+fn is_toml(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+}
+
+pub fn default_value(def: &ParamDef) -> Option<ParamValue> {
+    let v: f64 = def.default_str.parse().ok()?;
+    ParamValue::from_f64(&def.param_type, v).ok()
+}
+
+/// Equal within f32 rounding of the stored value
+pub fn values_equal(a: ParamValue, b: ParamValue) -> bool {
+    match (a, b) {
+        (ParamValue::F32(x), ParamValue::F32(y)) => (x - y).abs() <= 1e-6 * x.abs().max(y.abs()).max(1.0),
+        _ => a == b,
+    }
+}
+
+/// Parameters whose values differ, in table order (entries only one side has are included)
+pub fn diff(left: &ParamProfile, right: &ParamProfile) -> Vec<ParamDiff> {
+    let mut indices: Vec<(u16, String)> = Vec::new();
+    for e in left.params.iter().chain(&right.params) {
+        if !indices.iter().any(|(i, _)| *i == e.index) {
+            indices.push((e.index, e.name.clone()));
+        }
+    }
+    indices
+        .into_iter()
+        .filter_map(|(index, name)| {
+            let (l, r) = (left.value(index), right.value(index));
+            let same = match (l, r) {
+                (Some(a), Some(b)) => values_equal(a, b),
+                (None, None) => true,
+                _ => false,
+            };
+            (!same).then_some(ParamDiff { index, name, left: l, right: r })
+        })
+        .collect()
+}
+
+/// Writes needed to bring a motor at `current` to `target`: writable,
+/// non-setpoint parameters whose target value differs
+pub fn restore_writes(target: &ParamProfile, current: &ParamProfile) -> Vec<(&'static ParamDef, ParamValue)> {
+    motor_protocol::WRITABLE_PARAMS
+        .iter()
+        .filter(|def| def.access != ParamAccess::R && !SETPOINT_PARAMS.contains(&def.name))
+        .filter_map(|def| {
+            let value = target.value(def.index)?;
+            match current.value(def.index) {
+                Some(now) if values_equal(now, value) => None,
+                _ => Some((def, value)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_and_restore_writes() {
+        let defaults = ParamProfile::defaults(1);
+        assert_eq!(defaults.value(0x701E), Some(ParamValue::F32(30.0)));
+        assert_eq!(defaults.value(0x7026), Some(ParamValue::U16(1)));
+        assert_eq!(defaults.value(0x3005), None);
+
+        let mut tuned = defaults.clone();
+        for e in tuned.params.iter_mut() {
+            match e.name.as_str() {
+                "loc_kp" => e.value = Some(ParamValue::F32(45.0)),
+                "spd_ref" => e.value = Some(ParamValue::F32(3.0)),
+                "motorTemp" => e.value = Some(ParamValue::I16(251)),
+                _ => {}
+            }
+        }
+        let d = diff(&defaults, &tuned);
+        let names: Vec<&str> = d.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["spd_ref", "loc_kp", "motorTemp"]);
+
+        // Only the configuration change is written back; setpoints and read-only are skipped
+        let writes = restore_writes(&tuned, &defaults);
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].0.name, "loc_kp");
+        assert_eq!(writes[0].1, ParamValue::F32(45.0));
+        assert!(restore_writes(&tuned, &tuned).is_empty());
+    }
+
+    #[test]
+    fn test_profile_round_trips_json_and_toml() {
+        let mut profile = ParamProfile::defaults(7);
+        profile.firmware = Some("0.0.3.22".to_string());
+        profile.params[0].value = None;
+        profile.params[0].error = Some("No reply".to_string());
+
+        let dir = std::env::temp_dir();
+        for name in ["h26rdk_profile_test.json", "h26rdk_profile_test.toml"] {
+            let path = dir.join(name).to_string_lossy().to_string();
+            profile.save(&path).unwrap();
+            let loaded = ParamProfile::load(&path).unwrap();
+            std::fs::remove_file(&path).ok();
+            assert_eq!(loaded.motor_id, 7);
+            assert_eq!(loaded.firmware.as_deref(), Some("0.0.3.22"));
+            assert!(diff(&profile, &loaded).is_empty(), "{} differs", name);
+            assert_eq!(loaded.params[0].error.as_deref(), Some("No reply"));
+        }
+    }
+}
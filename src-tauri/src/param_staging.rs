@@ -0,0 +1,173 @@
+//! Per-motor tracking of parameter writes that are only in RAM
+//!
+//! Type 18 writes change RAM; type 22 persists whatever RAM holds. Every
+//! table write made in this session is staged here as pending, together with
+//! the value last read before the first write, so the dirty set can be shown,
+//! reverted, or verified before it is saved. Reads of a pending parameter do
+//! not move its baseline.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::motor_protocol::{ParamDef, ParamValue};
+
+/// A parameter written this session but not yet saved to flash
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedParam {
+    pub index: u16,
+    pub name: &'static str,
+    /// Last read value before the first write; None if it was never read
+    pub original: Option<ParamValue>,
+    /// Value now in RAM
+    pub value: ParamValue,
+}
+
+#[derive(Default)]
+struct MotorStaging {
+    last_read: HashMap<u16, ParamValue>,
+    pending: BTreeMap<u16, StagedParam>,
+}
+
+#[derive(Default)]
+pub struct ParamStaging {
+    motors: Mutex<HashMap<u8, MotorStaging>>,
+}
+
+impl ParamStaging {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a value read from the motor; ignored while the index is pending
+    pub fn record_read(&self, motor_id: u8, index: u16, value: ParamValue) {
+        let Ok(mut motors) = self.motors.lock() else {
+            return;
+        };
+        let motor = motors.entry(motor_id).or_default();
+        if !motor.pending.contains_key(&index) {
+            motor.last_read.insert(index, value);
+        }
+    }
+
+    /// Baseline for `index`: the original of a pending write, else the last read
+    pub fn baseline(&self, motor_id: u8, index: u16) -> Option<ParamValue> {
+        let motors = self.motors.lock().ok()?;
+        let motor = motors.get(&motor_id)?;
+        match motor.pending.get(&index) {
+            Some(staged) => staged.original,
+            None => motor.last_read.get(&index).copied(),
+        }
+    }
+
+    /// Stage a write; the first write of an index keeps the last read as original
+    pub fn record_write(&self, motor_id: u8, def: &ParamDef, value: ParamValue) {
+        let Ok(mut motors) = self.motors.lock() else {
+            return;
+        };
+        let motor = motors.entry(motor_id).or_default();
+        let original = motor.last_read.get(&def.index).copied();
+        motor
+            .pending
+            .entry(def.index)
+            .and_modify(|s| s.value = value)
+            .or_insert(StagedParam { index: def.index, name: def.name, original, value });
+    }
+
+    /// Pending writes in index order
+    pub fn dirty(&self, motor_id: u8) -> Vec<StagedParam> {
+        let Ok(motors) = self.motors.lock() else {
+            return Vec::new();
+        };
+        motors
+            .get(&motor_id)
+            .map(|m| m.pending.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop a pending write whose RAM value is back to `value` (after a revert)
+    pub fn discard(&self, motor_id: u8, index: u16, value: ParamValue) {
+        if let Ok(mut motors) = self.motors.lock() {
+            let motor = motors.entry(motor_id).or_default();
+            motor.pending.remove(&index);
+            motor.last_read.insert(index, value);
+        }
+    }
+
+    /// The motor changed CAN ID: carry its reads and pending writes over
+    pub fn move_motor(&self, from: u8, to: u8) {
+        if from == to {
+            return;
+        }
+        if let Ok(mut motors) = self.motors.lock() {
+            if let Some(motor) = motors.remove(&from) {
+                motors.insert(to, motor);
+            }
+        }
+    }
+
+    /// The given writes were saved to flash: they become the new baseline
+    pub fn mark_persisted(&self, motor_id: u8, saved: &[StagedParam]) {
+        if let Ok(mut motors) = self.motors.lock() {
+            let motor = motors.entry(motor_id).or_default();
+            for s in saved {
+                match motor.pending.get_mut(&s.index) {
+                    Some(p) if p.value == s.value => {
+                        motor.pending.remove(&s.index);
+                    }
+                    // Written again after the save was verified: the saved value is its new original
+                    Some(p) => p.original = Some(s.value),
+                    None => {}
+                }
+                motor.last_read.insert(s.index, s.value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motor_protocol::find_param;
+
+    #[test]
+    fn test_writes_are_staged_until_persisted() {
+        let staging = ParamStaging::new();
+        let loc_kp = find_param("loc_kp").unwrap();
+        let spd_kp = find_param("spd_kp").unwrap();
+
+        staging.record_read(1, loc_kp.index, ParamValue::F32(30.0));
+        staging.record_write(1, loc_kp, ParamValue::F32(40.0));
+        staging.record_write(1, loc_kp, ParamValue::F32(45.0));
+        // The read-back of a pending write does not become the baseline
+        staging.record_read(1, loc_kp.index, ParamValue::F32(45.0));
+        staging.record_write(1, spd_kp, ParamValue::F32(6.0));
+
+        let dirty = staging.dirty(1);
+        assert_eq!(dirty.len(), 2);
+        assert_eq!(dirty[0].name, "loc_kp");
+        assert_eq!(dirty[0].original, Some(ParamValue::F32(30.0)));
+        assert_eq!(dirty[0].value, ParamValue::F32(45.0));
+        assert_eq!(dirty[1].original, None);
+        assert!(staging.dirty(2).is_empty());
+
+        staging.discard(1, spd_kp.index, ParamValue::F32(5.0));
+        assert_eq!(staging.baseline(1, spd_kp.index), Some(ParamValue::F32(5.0)));
+
+        // A write after the save was verified stays pending
+        let saved = staging.dirty(1);
+        staging.record_write(1, loc_kp, ParamValue::F32(50.0));
+        staging.mark_persisted(1, &saved);
+        assert_eq!(staging.dirty(1).len(), 1);
+        assert_eq!(staging.dirty(1)[0].original, Some(ParamValue::F32(45.0)));
+        staging.mark_persisted(1, &staging.dirty(1));
+        assert!(staging.dirty(1).is_empty());
+        assert_eq!(staging.baseline(1, loc_kp.index), Some(ParamValue::F32(50.0)));
+
+        staging.record_write(1, spd_kp, ParamValue::F32(7.0));
+        staging.move_motor(1, 4);
+        assert!(staging.dirty(1).is_empty());
+        assert_eq!(staging.dirty(4)[0].original, Some(ParamValue::F32(5.0)));
+    }
+}
@@ -0,0 +1,134 @@
+//! Request/response correlation for private protocol commands
+//!
+//! A command registers what reply it expects (reply kind, motor id and, for
+//! parameter reads, the index) before sending its frame. The UDP recv thread
+//! offers every decoded reply to the registry, which hands it to all matching
+//! waiters. The loose "motor-*" events are still emitted as before.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use crate::motor_protocol::{FaultStatus, ParamReadResponse};
+
+/// What a pending request waits for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseKey {
+    /// Type 0 reply
+    DeviceId { motor_id: u8 },
+    /// Type 2 reply with the version signature
+    Version { motor_id: u8 },
+    /// Type 17 reply for one index
+    ParamRead { motor_id: u8, index: u16 },
+    /// Type 21 reply
+    Fault { motor_id: u8 },
+}
+
+#[derive(Debug, Clone)]
+pub enum PrivResponse {
+    /// 64-bit MCU identifier as hex
+    DeviceId(String),
+    Version(String),
+    ParamRead(ParamReadResponse),
+    Fault(FaultStatus),
+}
+
+struct Waiter {
+    id: u64,
+    key: ResponseKey,
+    tx: Sender<PrivResponse>,
+}
+
+/// Requests waiting for a reply from the recv thread
+#[derive(Default)]
+pub struct PendingRequests {
+    waiters: Mutex<Vec<Waiter>>,
+    next_id: AtomicU64,
+}
+
+/// Registration of one request; unregisters on drop
+pub struct PendingReply<'a> {
+    registry: &'a PendingRequests,
+    id: u64,
+    pub rx: Receiver<PrivResponse>,
+}
+
+impl Drop for PendingReply<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut waiters) = self.registry.waiters.lock() {
+            waiters.retain(|w| w.id != self.id);
+        }
+    }
+}
+
+impl PendingRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register before sending, so a fast reply cannot be missed
+    pub fn register(&self, key: ResponseKey) -> PendingReply<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = channel();
+        if let Ok(mut waiters) = self.waiters.lock() {
+            waiters.push(Waiter { id, key, tx });
+        }
+        PendingReply { registry: self, id, rx }
+    }
+
+    /// Deliver a reply to every waiter for `key`; returns how many got it
+    pub fn complete(&self, key: ResponseKey, response: &PrivResponse) -> usize {
+        let Ok(mut waiters) = self.waiters.lock() else {
+            return 0;
+        };
+        let mut delivered = 0;
+        waiters.retain(|w| {
+            if w.key != key {
+                return true;
+            }
+            if w.tx.send(response.clone()).is_ok() {
+                delivered += 1;
+            }
+            false
+        });
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(index: u16, value: f32) -> PrivResponse {
+        PrivResponse::ParamRead(ParamReadResponse {
+            index,
+            success: true,
+            value_bytes: value.to_le_bytes(),
+            value_f32: value,
+            value_u32: value.to_bits(),
+        })
+    }
+
+    #[test]
+    fn test_replies_match_on_motor_and_index() {
+        let pending = PendingRequests::new();
+        let kp = pending.register(ResponseKey::ParamRead { motor_id: 1, index: 0x701E });
+        let other_motor = pending.register(ResponseKey::ParamRead { motor_id: 2, index: 0x701E });
+
+        // Different index, then the matching reply
+        assert_eq!(pending.complete(ResponseKey::ParamRead { motor_id: 1, index: 0x701F }, &param(0x701F, 5.0)), 0);
+        assert_eq!(pending.complete(ResponseKey::ParamRead { motor_id: 1, index: 0x701E }, &param(0x701E, 30.0)), 1);
+        match kp.rx.try_recv() {
+            Ok(PrivResponse::ParamRead(r)) => assert_eq!(r.value_f32, 30.0),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(other_motor.rx.try_recv().is_err());
+
+        // A completed waiter is gone; a duplicate reply goes nowhere
+        assert_eq!(pending.complete(ResponseKey::ParamRead { motor_id: 1, index: 0x701E }, &param(0x701E, 30.0)), 0);
+
+        // Dropping the registration unregisters it
+        drop(other_motor);
+        assert_eq!(pending.complete(ResponseKey::ParamRead { motor_id: 2, index: 0x701E }, &param(0x701E, 1.0)), 0);
+    }
+}
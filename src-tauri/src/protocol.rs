@@ -1,11 +1,16 @@
-/// HiPNUC binary protocol parser
-///
-/// Frame format:
-///   [0x5A] [0xA5] [len_lo] [len_hi] [crc_lo] [crc_hi] [payload...]
-///
-/// CRC16-CCITT (poly 0x1021, init 0) over bytes[0..4] + payload (excludes CRC field)
-///
-/// HI91 payload (76 bytes): tag(0x91) + main_status + temp + pressure + time + acc[3] + gyr[3] + mag[3] + roll + pitch + yaw + quat[4]
+//! HiPNUC binary protocol parser
+//!
+//! Frame format:
+//!   [0x5A] [0xA5] [len_lo] [len_hi] [crc_lo] [crc_hi] [payload...]
+//!
+//! CRC16-CCITT (poly 0x1021, init 0) over bytes[0..4] + payload (excludes CRC field)
+//!
+//! The payload is a sequence of sub-packets, each starting with a one-byte tag:
+//!   0x91 HI91 (76 bytes): tag + main_status + temp + pressure + time + acc[3] + gyr[3] + mag[3] + roll + pitch + yaw + quat[4]
+//!   0x92 HI92 (48 bytes): compact fixed-point IMU packet
+//!   0x81 HI81 (104 bytes): INS/GNSS combined navigation packet
+//!   0x83 HI83 (7 + N bytes): tag + main_status + data_bitmap, followed by the fields selected in the bitmap
+//!   0x90 legacy user ID (2 bytes), 0xD1 legacy quaternion (17 bytes)
 
 use serde::Serialize;
 
@@ -16,6 +21,18 @@ const CH_HDR_SIZE: usize = 6;
 const HIPNUC_MAX_RAW_SIZE: usize = 512;
 
 const FRAME_TAG_HI91: u8 = 0x91;
+const FRAME_TAG_HI92: u8 = 0x92;
+const FRAME_TAG_HI81: u8 = 0x81;
+const FRAME_TAG_HI83: u8 = 0x83;
+const FRAME_TAG_USER_ID: u8 = 0x90;
+const FRAME_TAG_QUAT: u8 = 0xD1;
+
+const HI91_SIZE: usize = 76;
+const HI92_SIZE: usize = 48;
+const HI81_SIZE: usize = 104;
+const HI83_HDR_SIZE: usize = 7;
+const USER_ID_SIZE: usize = 2;
+const QUAT_SIZE: usize = 17;
 
 /// CRC16-CCITT with polynomial 0x1021, initial value 0
 /// Matches the reference C implementation in hipnuc_dec.c
@@ -54,18 +71,22 @@ pub struct Hi91Data {
     pub quat: [f64; 4],
     /// Temperature in degrees Celsius
     pub temperature: i8,
-    /// Air pressure in Pa
+    /// Air pressure in Pa; NaN when the packet has none (HI92)
     pub air_pressure: f64,
     /// System timestamp in ms
     pub system_time: u32,
+    /// Gravity-free body acceleration, computed host-side from `quat` when the
+    /// output profile asks for it (not part of the packet)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linear_acc: Option<[f64; 3]>,
 }
 
-const GRAVITY: f64 = 9.80665;
+pub const GRAVITY: f64 = 9.80665;
 
 /// Parse HI91 payload (76 bytes starting with tag 0x91)
 /// Layout matches reference: hipnuc_dec.h hi91_t struct
 fn parse_hi91(data: &[u8]) -> Option<Hi91Data> {
-    if data.len() < 76 {
+    if data.len() < HI91_SIZE {
         return None;
     }
     if data[0] != FRAME_TAG_HI91 {
@@ -114,9 +135,499 @@ fn parse_hi91(data: &[u8]) -> Option<Hi91Data> {
         temperature,
         air_pressure,
         system_time,
+        linear_acc: None,
+    })
+}
+
+/// Encode a HI91 sub-packet (76 bytes), the inverse of `parse_hi91`.
+/// `Hi91Data` does not keep `main_status`, so it is written as 0.
+pub fn encode_hi91(d: &Hi91Data) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HI91_SIZE);
+    out.push(FRAME_TAG_HI91);
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.push(d.temperature as u8);
+    out.extend_from_slice(&(d.air_pressure as f32).to_le_bytes());
+    out.extend_from_slice(&d.system_time.to_le_bytes());
+    let floats = d
+        .acc
+        .iter()
+        .map(|a| a / GRAVITY)
+        .chain(d.gyr)
+        .chain(d.mag)
+        .chain([d.roll, d.pitch, d.yaw])
+        .chain(d.quat);
+    for v in floats {
+        out.extend_from_slice(&(v as f32).to_le_bytes());
+    }
+    out
+}
+
+/// Wrap one or more sub-packets in a frame: sync, length, CRC16
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let len = payload.len() as u16;
+    let mut frame = Vec::with_capacity(CH_HDR_SIZE + payload.len());
+    frame.extend_from_slice(&[CHSYNC1, CHSYNC2]);
+    frame.extend_from_slice(&len.to_le_bytes());
+    let crc = crc16_update(crc16_update(0, &frame[0..4]), payload);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// ── Little-endian field readers for the fixed-point packets ─────────
+
+fn rd_u16(d: &[u8], o: usize) -> u16 {
+    u16::from_le_bytes([d[o], d[o + 1]])
+}
+
+fn rd_i16(d: &[u8], o: usize) -> i16 {
+    i16::from_le_bytes([d[o], d[o + 1]])
+}
+
+fn rd_u32(d: &[u8], o: usize) -> u32 {
+    u32::from_le_bytes([d[o], d[o + 1], d[o + 2], d[o + 3]])
+}
+
+fn rd_i32(d: &[u8], o: usize) -> i32 {
+    i32::from_le_bytes([d[o], d[o + 1], d[o + 2], d[o + 3]])
+}
+
+fn rd_f32(d: &[u8], o: usize) -> f64 {
+    f32::from_le_bytes([d[o], d[o + 1], d[o + 2], d[o + 3]]) as f64
+}
+
+fn rd_f64(d: &[u8], o: usize) -> f64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&d[o..o + 8]);
+    f64::from_le_bytes(b)
+}
+
+/// Read `N` consecutive i16 values starting at `o`, each multiplied by `scale`
+fn rd_i16_scaled<const N: usize>(d: &[u8], o: usize, scale: f64) -> [f64; N] {
+    let mut out = [0.0; N];
+    for (i, v) in out.iter_mut().enumerate() {
+        *v = rd_i16(d, o + i * 2) as f64 * scale;
+    }
+    out
+}
+
+/// Read `N` consecutive f32 values starting at `o`, each multiplied by `scale`
+fn rd_f32_scaled<const N: usize>(d: &[u8], o: usize, scale: f64) -> [f64; N] {
+    let mut out = [0.0; N];
+    for (i, v) in out.iter_mut().enumerate() {
+        *v = rd_f32(d, o + i * 4) * scale;
+    }
+    out
+}
+
+// Fixed-point scale factors used by HI81 / HI92
+const ACC_LSB: f64 = 0.0048828; // m/s^2
+const GYR_LSB: f64 = 0.001; // rad/s
+const MAG_LSB: f64 = 0.030517; // uT
+const QUAT_LSB: f64 = 0.0001;
+const RAD_TO_DEG: f64 = 180.0 / std::f64::consts::PI;
+
+/// Parsed HI92 compact IMU packet, converted to the same units as `Hi91Data`
+///
+/// Layout (48 bytes): tag(0x92) + status(u16) + temp(i8) + rev(u16) + system_time(u32)
+/// + acc_b(i16x3, 0.0048828 m/s^2) + gyr_b(i16x3, 0.001 rad/s) + mag_b(i16x3, 0.030517 uT)
+/// + roll/pitch/yaw(i32, 0.001 deg) + quat(i16x4, 0.0001)
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Hi92Data {
+    pub status: u16,
+    /// Accelerometer X/Y/Z in m/s^2
+    pub acc: [f64; 3],
+    /// Gyroscope X/Y/Z in deg/s
+    pub gyr: [f64; 3],
+    /// Magnetometer X/Y/Z in uT
+    pub mag: [f64; 3],
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+    /// Quaternion (w, x, y, z)
+    pub quat: [f64; 4],
+    pub temperature: i8,
+    pub system_time: u32,
+}
+
+fn parse_hi92(data: &[u8]) -> Option<Hi92Data> {
+    if data.len() < HI92_SIZE || data[0] != FRAME_TAG_HI92 {
+        return None;
+    }
+
+    let gyr_rad: [f64; 3] = rd_i16_scaled(data, 16, GYR_LSB);
+
+    Some(Hi92Data {
+        status: rd_u16(data, 1),
+        temperature: data[3] as i8,
+        system_time: rd_u32(data, 6),
+        acc: rd_i16_scaled(data, 10, ACC_LSB),
+        gyr: gyr_rad.map(|g| g * RAD_TO_DEG),
+        mag: rd_i16_scaled(data, 22, MAG_LSB),
+        roll: rd_i32(data, 28) as f64 * 0.001,
+        pitch: rd_i32(data, 32) as f64 * 0.001,
+        yaw: rd_i32(data, 36) as f64 * 0.001,
+        quat: rd_i16_scaled(data, 40, QUAT_LSB),
+    })
+}
+
+/// UTC time carried by HI81 / HI83
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct UtcTime {
+    /// Years since 2000
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// Milliseconds within the minute
+    pub msec: u16,
+}
+
+/// Parsed HI81 INS/GNSS packet (104 bytes)
+///
+/// Fixed-point fields are converted to SI-ish units matching `Hi91Data`
+/// (acc m/s^2, gyr deg/s, mag uT, angles deg); positions are degrees / metres.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Hi81Data {
+    pub main_status: u16,
+    pub ins_status: u8,
+    /// GPS week number
+    pub gpst_wn: u16,
+    /// GPS time of week in ms
+    pub gpst_tow: u32,
+    pub gyr: [f64; 3],
+    pub acc: [f64; 3],
+    pub mag: [f64; 3],
+    /// Air pressure in Pa
+    pub air_pressure: f64,
+    pub temperature: i8,
+    pub utc: UtcTime,
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+    pub quat: [f64; 4],
+    /// INS solution: longitude / latitude in degrees, height above MSL in m
+    pub ins_lon: f64,
+    pub ins_lat: f64,
+    pub ins_msl: f64,
+    pub pdop: f64,
+    pub hdop: f64,
+    pub solq_pos: u8,
+    pub nv_pos: u8,
+    pub solq_heading: u8,
+    pub nv_heading: u8,
+    pub diff_age: u8,
+    /// Geoid undulation in m
+    pub undulation: f64,
+    pub ant_status: u8,
+    /// Velocity in ENU frame, m/s
+    pub vel_enu: [f64; 3],
+    /// Acceleration in ENU frame, m/s^2
+    pub acc_enu: [f64; 3],
+    /// Raw GNSS solution: longitude / latitude in degrees, height above MSL in m
+    pub gnss_lon: f64,
+    pub gnss_lat: f64,
+    pub gnss_msl: f64,
+}
+
+fn parse_hi81(data: &[u8]) -> Option<Hi81Data> {
+    if data.len() < HI81_SIZE || data[0] != FRAME_TAG_HI81 {
+        return None;
+    }
+
+    let gyr_rad: [f64; 3] = rd_i16_scaled(data, 12, GYR_LSB);
+
+    Some(Hi81Data {
+        main_status: rd_u16(data, 1),
+        ins_status: data[3],
+        gpst_wn: rd_u16(data, 4),
+        gpst_tow: rd_u32(data, 6),
+        // bytes 10..12 reserved
+        gyr: gyr_rad.map(|g| g * RAD_TO_DEG),
+        acc: rd_i16_scaled(data, 18, ACC_LSB),
+        mag: rd_i16_scaled(data, 24, MAG_LSB),
+        air_pressure: rd_i32(data, 30) as f64,
+        // bytes 34..36 reserved
+        temperature: data[36] as i8,
+        utc: UtcTime {
+            year: data[37],
+            month: data[38],
+            day: data[39],
+            hour: data[40],
+            minute: data[41],
+            msec: rd_u16(data, 42),
+        },
+        roll: rd_i16(data, 44) as f64 * 0.01,
+        pitch: rd_i16(data, 46) as f64 * 0.01,
+        yaw: rd_u16(data, 48) as f64 * 0.01,
+        quat: rd_i16_scaled(data, 50, QUAT_LSB),
+        ins_lon: rd_i32(data, 58) as f64 * 1e-7,
+        ins_lat: rd_i32(data, 62) as f64 * 1e-7,
+        ins_msl: rd_i32(data, 66) as f64 * 0.001,
+        pdop: data[70] as f64 * 0.1,
+        hdop: data[71] as f64 * 0.1,
+        solq_pos: data[72],
+        nv_pos: data[73],
+        solq_heading: data[74],
+        nv_heading: data[75],
+        diff_age: data[76],
+        undulation: rd_i16(data, 77) as f64 * 0.01,
+        ant_status: data[79],
+        vel_enu: rd_i16_scaled(data, 80, 0.01),
+        acc_enu: rd_i16_scaled(data, 86, ACC_LSB),
+        gnss_lon: rd_i32(data, 92) as f64 * 1e-7,
+        gnss_lat: rd_i32(data, 96) as f64 * 1e-7,
+        gnss_msl: rd_i32(data, 100) as f64 * 0.001,
     })
 }
 
+// HI83 data bitmap: each set bit appends one field, in bit order
+pub const HI83_BMAP_ACC: u32 = 1 << 0; // f32x3, G
+pub const HI83_BMAP_GYR: u32 = 1 << 1; // f32x3, deg/s
+pub const HI83_BMAP_MAG: u32 = 1 << 2; // f32x3, uT
+pub const HI83_BMAP_RPY: u32 = 1 << 3; // f32x3, deg (roll, pitch, yaw)
+pub const HI83_BMAP_QUAT: u32 = 1 << 4; // f32x4 (w, x, y, z)
+pub const HI83_BMAP_SYSTEM_TIME: u32 = 1 << 5; // u32, ms
+pub const HI83_BMAP_UTC: u32 = 1 << 6; // year, month, day, hour, minute (u8) + msec (u16)
+pub const HI83_BMAP_AIR_PRESSURE: u32 = 1 << 7; // f32, Pa
+pub const HI83_BMAP_TEMPERATURE: u32 = 1 << 8; // f32, °C
+pub const HI83_BMAP_INS_POSITION: u32 = 1 << 9; // f64 lat, f64 lon (deg) + f32 msl (m)
+pub const HI83_BMAP_GNSS_QUALITY: u32 = 1 << 10; // solq_pos, nv_pos, solq_heading, nv_heading (u8)
+pub const HI83_BMAP_VEL_ENU: u32 = 1 << 11; // f32x3, m/s
+pub const HI83_BMAP_ACC_ENU: u32 = 1 << 12; // f32x3, m/s^2
+
+/// Size in bytes of each HI83 bitmap field, indexed by bit number
+const HI83_FIELD_SIZES: [usize; 13] = [12, 12, 12, 12, 16, 4, 7, 4, 4, 20, 4, 12, 12];
+
+/// GNSS solution quality reported by HI83
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct GnssQuality {
+    pub solq_pos: u8,
+    pub nv_pos: u8,
+    pub solq_heading: u8,
+    pub nv_heading: u8,
+}
+
+/// Parsed HI83 packet: only the fields selected by `data_bitmap` are present
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Hi83Data {
+    pub main_status: u16,
+    pub data_bitmap: u32,
+    /// m/s^2 (converted from G)
+    pub acc: Option<[f64; 3]>,
+    /// deg/s
+    pub gyr: Option<[f64; 3]>,
+    /// uT
+    pub mag: Option<[f64; 3]>,
+    /// roll, pitch, yaw in degrees
+    pub rpy: Option<[f64; 3]>,
+    pub quat: Option<[f64; 4]>,
+    pub system_time: Option<u32>,
+    pub utc: Option<UtcTime>,
+    pub air_pressure: Option<f64>,
+    pub temperature: Option<f64>,
+    /// latitude (deg), longitude (deg), height above MSL (m)
+    pub ins_position: Option<[f64; 3]>,
+    pub gnss_quality: Option<GnssQuality>,
+    pub vel_enu: Option<[f64; 3]>,
+    pub acc_enu: Option<[f64; 3]>,
+}
+
+/// Total HI83 sub-packet size implied by its bitmap, or None if the bitmap
+/// selects a field this decoder does not know the size of.
+fn hi83_size(data_bitmap: u32) -> Option<usize> {
+    if data_bitmap >> HI83_FIELD_SIZES.len() != 0 {
+        return None;
+    }
+    let fields: usize = HI83_FIELD_SIZES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| data_bitmap & (1 << bit) != 0)
+        .map(|(_, size)| size)
+        .sum();
+    Some(HI83_HDR_SIZE + fields)
+}
+
+fn parse_hi83(data: &[u8]) -> Option<Hi83Data> {
+    if data.len() < HI83_HDR_SIZE || data[0] != FRAME_TAG_HI83 {
+        return None;
+    }
+    let data_bitmap = rd_u32(data, 3);
+    if data.len() < hi83_size(data_bitmap)? {
+        return None;
+    }
+
+    let mut out = Hi83Data {
+        main_status: rd_u16(data, 1),
+        data_bitmap,
+        ..Default::default()
+    };
+
+    let mut o = HI83_HDR_SIZE;
+    for (bit, &size) in HI83_FIELD_SIZES.iter().enumerate() {
+        let flag = 1u32 << bit;
+        if data_bitmap & flag == 0 {
+            continue;
+        }
+        match flag {
+            HI83_BMAP_ACC => out.acc = Some(rd_f32_scaled(data, o, GRAVITY)),
+            HI83_BMAP_GYR => out.gyr = Some(rd_f32_scaled(data, o, 1.0)),
+            HI83_BMAP_MAG => out.mag = Some(rd_f32_scaled(data, o, 1.0)),
+            HI83_BMAP_RPY => out.rpy = Some(rd_f32_scaled(data, o, 1.0)),
+            HI83_BMAP_QUAT => out.quat = Some(rd_f32_scaled(data, o, 1.0)),
+            HI83_BMAP_SYSTEM_TIME => out.system_time = Some(rd_u32(data, o)),
+            HI83_BMAP_UTC => {
+                out.utc = Some(UtcTime {
+                    year: data[o],
+                    month: data[o + 1],
+                    day: data[o + 2],
+                    hour: data[o + 3],
+                    minute: data[o + 4],
+                    msec: rd_u16(data, o + 5),
+                })
+            }
+            HI83_BMAP_AIR_PRESSURE => out.air_pressure = Some(rd_f32(data, o)),
+            HI83_BMAP_TEMPERATURE => out.temperature = Some(rd_f32(data, o)),
+            HI83_BMAP_INS_POSITION => {
+                out.ins_position = Some([rd_f64(data, o), rd_f64(data, o + 8), rd_f32(data, o + 16)])
+            }
+            HI83_BMAP_GNSS_QUALITY => {
+                out.gnss_quality = Some(GnssQuality {
+                    solq_pos: data[o],
+                    nv_pos: data[o + 1],
+                    solq_heading: data[o + 2],
+                    nv_heading: data[o + 3],
+                })
+            }
+            HI83_BMAP_VEL_ENU => out.vel_enu = Some(rd_f32_scaled(data, o, 1.0)),
+            HI83_BMAP_ACC_ENU => out.acc_enu = Some(rd_f32_scaled(data, o, 1.0)),
+            _ => {}
+        }
+        o += size;
+    }
+
+    Some(out)
+}
+
+/// One decoded sub-packet. A single frame may carry several of these.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum HipnucPacket {
+    Hi91(Hi91Data),
+    Hi92(Hi92Data),
+    Hi81(Hi81Data),
+    Hi83(Hi83Data),
+    /// Legacy 0x90: user-assigned device ID
+    UserId(u8),
+    /// Legacy 0xD1: quaternion (w, x, y, z)
+    Quaternion([f64; 4]),
+}
+
+impl HipnucPacket {
+    /// Short name of the sub-packet type, as used in `LOG` commands
+    pub fn name(&self) -> &'static str {
+        match self {
+            HipnucPacket::Hi91(_) => "HI91",
+            HipnucPacket::Hi92(_) => "HI92",
+            HipnucPacket::Hi81(_) => "HI81",
+            HipnucPacket::Hi83(_) => "HI83",
+            HipnucPacket::UserId(_) => "USER_ID",
+            HipnucPacket::Quaternion(_) => "QUAT",
+        }
+    }
+
+    /// Device `system_time` (ms) for packets that carry one
+    pub fn system_time(&self) -> Option<u32> {
+        match self {
+            HipnucPacket::Hi91(d) => Some(d.system_time),
+            HipnucPacket::Hi92(d) => Some(d.system_time),
+            HipnucPacket::Hi83(d) => d.system_time,
+            _ => None,
+        }
+    }
+
+    /// Sensor channels and attitude of packets that carry them, for host-side
+    /// corrections applied before the packet is published
+    pub fn sensors_mut(&mut self) -> Option<SensorsMut<'_>> {
+        match self {
+            HipnucPacket::Hi91(d) => Some(SensorsMut {
+                acc: Some(&mut d.acc),
+                gyr: Some(&mut d.gyr),
+                mag: Some(&mut d.mag),
+                quat: Some(&mut d.quat),
+                euler: Some([&mut d.roll, &mut d.pitch, &mut d.yaw]),
+            }),
+            HipnucPacket::Hi92(d) => Some(SensorsMut {
+                acc: Some(&mut d.acc),
+                gyr: Some(&mut d.gyr),
+                mag: Some(&mut d.mag),
+                quat: Some(&mut d.quat),
+                euler: Some([&mut d.roll, &mut d.pitch, &mut d.yaw]),
+            }),
+            HipnucPacket::Hi81(d) => Some(SensorsMut {
+                acc: Some(&mut d.acc),
+                gyr: Some(&mut d.gyr),
+                mag: Some(&mut d.mag),
+                quat: Some(&mut d.quat),
+                euler: Some([&mut d.roll, &mut d.pitch, &mut d.yaw]),
+            }),
+            HipnucPacket::Hi83(d) => Some(SensorsMut {
+                acc: d.acc.as_mut(),
+                gyr: d.gyr.as_mut(),
+                mag: d.mag.as_mut(),
+                quat: d.quat.as_mut(),
+                euler: d.rpy.as_mut().map(|[r, p, y]| [r, p, y]),
+            }),
+            HipnucPacket::Quaternion(q) => Some(SensorsMut {
+                acc: None,
+                gyr: None,
+                mag: None,
+                quat: Some(q),
+                euler: None,
+            }),
+            HipnucPacket::UserId(_) => None,
+        }
+    }
+
+    /// IMU sample in `Hi91Data` form, for packets that carry a full IMU solution
+    /// (HI91 as-is, HI92 converted). Used for the "imu-data" event and recording.
+    pub fn imu_sample(&self) -> Option<Hi91Data> {
+        match self {
+            HipnucPacket::Hi91(d) => Some(d.clone()),
+            HipnucPacket::Hi92(d) => Some(Hi91Data {
+                acc: d.acc,
+                gyr: d.gyr,
+                mag: d.mag,
+                roll: d.roll,
+                pitch: d.pitch,
+                yaw: d.yaw,
+                quat: d.quat,
+                temperature: d.temperature,
+                air_pressure: f64::NAN,
+                system_time: d.system_time,
+                linear_acc: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Mutable body-frame channels of one packet; HI83 only has the ones
+/// selected by its bitmap. Earth-frame navigation fields are not included.
+pub struct SensorsMut<'a> {
+    /// m/s^2
+    pub acc: Option<&'a mut [f64; 3]>,
+    /// deg/s
+    pub gyr: Option<&'a mut [f64; 3]>,
+    /// uT
+    pub mag: Option<&'a mut [f64; 3]>,
+    /// (w, x, y, z)
+    pub quat: Option<&'a mut [f64; 4]>,
+    /// roll, pitch, yaw in degrees
+    pub euler: Option<[&'a mut f64; 3]>,
+}
+
 /// Decoder state machine
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum DecoderState {
@@ -126,6 +637,24 @@ enum DecoderState {
     ReadPayload,
 }
 
+/// Running decoder counters since the decoder was created
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DecoderStats {
+    /// Frames that passed CRC
+    pub frames_ok: u64,
+    pub crc_failures: u64,
+    /// Bytes discarded while hunting for the 5A A5 sync pair
+    pub resync_bytes: u64,
+    /// Headers rejected for a zero or oversize payload length
+    pub oversize_lengths: u64,
+    /// Sub-packets with an unknown tag (or an HI83 bitmap we cannot size)
+    pub unknown_tags: u64,
+    /// Sub-packets cut short by the frame length
+    pub truncated_packets: u64,
+    /// Sub-packets decoded
+    pub packets: u64,
+}
+
 /// Byte-by-byte streaming protocol decoder
 /// Matches the state machine in hipnuc_dec.c hipnuc_input()
 pub struct HipnucDecoder {
@@ -133,8 +662,15 @@ pub struct HipnucDecoder {
     buf: [u8; HIPNUC_MAX_RAW_SIZE],
     nbyte: usize,
     payload_len: usize,
+    /// Keep bytes seen outside of frames (ASCII command replies)
+    capture_unframed: bool,
+    unframed: Vec<u8>,
+    stats: DecoderStats,
 }
 
+/// Cap on buffered unframed bytes if nobody drains them
+const MAX_UNFRAMED: usize = 4096;
+
 impl HipnucDecoder {
     pub fn new() -> Self {
         Self {
@@ -142,18 +678,51 @@ impl HipnucDecoder {
             buf: [0u8; HIPNUC_MAX_RAW_SIZE],
             nbyte: 0,
             payload_len: 0,
+            capture_unframed: false,
+            unframed: Vec::new(),
+            stats: DecoderStats::default(),
         }
     }
 
+    pub fn stats(&self) -> &DecoderStats {
+        &self.stats
+    }
+
+    /// Enable collection of bytes that are not part of any frame.
+    /// Drain them with `take_unframed`.
+    pub fn set_capture_unframed(&mut self, enable: bool) {
+        self.capture_unframed = enable;
+        self.unframed.clear();
+    }
+
+    /// Take the bytes skipped while hunting for sync since the last call
+    pub fn take_unframed(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.unframed)
+    }
+
+    fn push_unframed(&mut self, byte: u8) {
+        if !self.capture_unframed {
+            return;
+        }
+        if self.unframed.len() >= MAX_UNFRAMED {
+            self.unframed.clear();
+        }
+        self.unframed.push(byte);
+    }
+
     /// Feed one byte into the decoder.
-    /// Returns Some(Hi91Data) when a complete valid HI91 frame is decoded.
-    pub fn input(&mut self, byte: u8) -> Option<Hi91Data> {
+    /// Returns Some(packets) when a complete frame passes CRC; the vector holds
+    /// every known sub-packet carried by that frame, in order.
+    pub fn input(&mut self, byte: u8) -> Option<Vec<HipnucPacket>> {
         match self.state {
             DecoderState::WaitSync1 => {
                 if byte == CHSYNC1 {
                     self.buf[0] = byte;
                     self.nbyte = 1;
                     self.state = DecoderState::WaitSync2;
+                } else {
+                    self.stats.resync_bytes += 1;
+                    self.push_unframed(byte);
                 }
                 None
             }
@@ -162,8 +731,15 @@ impl HipnucDecoder {
                     self.buf[1] = byte;
                     self.nbyte = 2;
                     self.state = DecoderState::ReadHeader;
+                } else if byte == CHSYNC1 {
+                    // 5A 5A: the first one was noise, the second may start a frame
+                    self.stats.resync_bytes += 1;
+                    self.push_unframed(CHSYNC1);
                 } else {
                     // Not sync2, reset
+                    self.stats.resync_bytes += 2;
+                    self.push_unframed(CHSYNC1);
+                    self.push_unframed(byte);
                     self.state = DecoderState::WaitSync1;
                     self.nbyte = 0;
                 }
@@ -182,6 +758,7 @@ impl HipnucDecoder {
                     if self.payload_len == 0
                         || CH_HDR_SIZE + self.payload_len > HIPNUC_MAX_RAW_SIZE
                     {
+                        self.stats.oversize_lengths += 1;
                         self.reset();
                         return None;
                     }
@@ -207,17 +784,17 @@ impl HipnucDecoder {
     }
 
     /// Feed a buffer of bytes, collecting all parsed packets
-    pub fn input_bytes(&mut self, data: &[u8]) -> Vec<Hi91Data> {
+    pub fn input_bytes(&mut self, data: &[u8]) -> Vec<HipnucPacket> {
         let mut results = Vec::new();
         for &byte in data {
-            if let Some(packet) = self.input(byte) {
-                results.push(packet);
+            if let Some(packets) = self.input(byte) {
+                results.extend(packets);
             }
         }
         results
     }
 
-    fn validate_and_parse(&self) -> Option<Hi91Data> {
+    fn validate_and_parse(&mut self) -> Option<Vec<HipnucPacket>> {
         let total_len = CH_HDR_SIZE + self.payload_len;
 
         // CRC16 is over bytes[0..4] (sync1, sync2, len_lo, len_hi) + payload[6..]
@@ -230,7 +807,8 @@ impl HipnucDecoder {
         let crc_received = u16::from_le_bytes([self.buf[4], self.buf[5]]);
 
         if crc_calculated != crc_received {
-            log::warn!(
+            self.stats.crc_failures += 1;
+            log::debug!(
                 "CRC mismatch: calculated=0x{:04X}, received=0x{:04X}",
                 crc_calculated,
                 crc_received
@@ -238,31 +816,11 @@ impl HipnucDecoder {
             return None;
         }
 
+        self.stats.frames_ok += 1;
+
         // Parse payload sub-packets
-        let payload = &self.buf[CH_HDR_SIZE..total_len];
-        self.parse_payload(payload)
-    }
-
-    fn parse_payload(&self, payload: &[u8]) -> Option<Hi91Data> {
-        let offset = 0;
-        while offset < payload.len() {
-            let tag = payload[offset];
-            match tag {
-                FRAME_TAG_HI91 => {
-                    if offset + 76 <= payload.len() {
-                        return parse_hi91(&payload[offset..]);
-                    } else {
-                        return None;
-                    }
-                }
-                _ => {
-                    // Unknown tag or other packet types (HI81/HI83) - skip
-                    // For HI12 we only expect HI91
-                    break;
-                }
-            }
-        }
-        None
+        let packets = parse_payload(&self.buf[CH_HDR_SIZE..total_len], &mut self.stats);
+        Some(packets)
     }
 
     fn reset(&mut self) {
@@ -272,6 +830,76 @@ impl HipnucDecoder {
     }
 }
 
+/// Walk every sub-packet in the payload. Decoding stops at the first
+/// unknown tag (its length cannot be known) or truncated sub-packet;
+/// packets decoded before that point are kept.
+fn parse_payload(payload: &[u8], stats: &mut DecoderStats) -> Vec<HipnucPacket> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    while offset < payload.len() {
+        let sub = &payload[offset..];
+        let (packet, size) = match sub[0] {
+            FRAME_TAG_HI91 => (parse_hi91(sub).map(HipnucPacket::Hi91), HI91_SIZE),
+            FRAME_TAG_HI92 => (parse_hi92(sub).map(HipnucPacket::Hi92), HI92_SIZE),
+            FRAME_TAG_HI81 => (parse_hi81(sub).map(HipnucPacket::Hi81), HI81_SIZE),
+            FRAME_TAG_HI83 => {
+                let size = if sub.len() >= HI83_HDR_SIZE {
+                    hi83_size(rd_u32(sub, 3))
+                } else {
+                    None
+                };
+                match size {
+                    Some(size) => (parse_hi83(sub).map(HipnucPacket::Hi83), size),
+                    None => {
+                        log::debug!("HI83 with unsupported data bitmap, dropping rest of frame");
+                        stats.unknown_tags += 1;
+                        break;
+                    }
+                }
+            }
+            FRAME_TAG_USER_ID => (
+                (sub.len() >= USER_ID_SIZE).then(|| HipnucPacket::UserId(sub[1])),
+                USER_ID_SIZE,
+            ),
+            FRAME_TAG_QUAT => (
+                (sub.len() >= QUAT_SIZE)
+                    .then(|| HipnucPacket::Quaternion(rd_f32_scaled(sub, 1, 1.0))),
+                QUAT_SIZE,
+            ),
+            tag => {
+                log::debug!("Unknown HiPNUC tag 0x{:02X} at payload offset {}", tag, offset);
+                stats.unknown_tags += 1;
+                break;
+            }
+        };
+
+        match packet {
+            Some(p) => packets.push(p),
+            None => {
+                // Sub-packet shorter than its tag requires
+                stats.truncated_packets += 1;
+                break;
+            }
+        }
+        offset += size;
+    }
+    stats.packets += packets.len() as u64;
+    packets
+}
+
+/// Reference HI91 frame from the HI12 parsing doc, shared by decoder tests
+#[cfg(test)]
+pub(crate) fn hi91_test_frame() -> Vec<u8> {
+    const HEX: &str = "5A A5 4C 00 14 BB 91 08 15 23 09 A2 C4 47 08 15 1C 00 \
+        CC E8 61 BE 9A 35 56 3E 65 EA 72 3F 31 D0 7C BD 75 DD \
+        C5 BB 6B D7 24 BC 89 88 FC 40 01 00 6A 41 AB 2A 70 C2 \
+        96 D4 50 41 ED 03 43 41 41 F4 F4 C2 CC CA F8 BE 73 6A \
+        19 BE F0 00 1C 3D 8D 37 5C 3F";
+    HEX.split_whitespace()
+        .map(|s| u8::from_str_radix(s, 16).unwrap())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,14 +907,7 @@ mod tests {
     /// Test CRC16 against the reference Python implementation
     #[test]
     fn test_crc16() {
-        // From the Python example data for HI91
-        let frame = hex_to_bytes(
-            "5A A5 4C 00 14 BB 91 08 15 23 09 A2 C4 47 08 15 1C 00 \
-             CC E8 61 BE 9A 35 56 3E 65 EA 72 3F 31 D0 7C BD 75 DD \
-             C5 BB 6B D7 24 BC 89 88 FC 40 01 00 6A 41 AB 2A 70 C2 \
-             96 D4 50 41 ED 03 43 41 41 F4 F4 C2 CC CA F8 BE 73 6A \
-             19 BE F0 00 1C 3D 8D 37 5C 3F",
-        );
+        let frame = hi91_test_frame();
 
         // CRC is over bytes[0..4] + bytes[6..]
         let mut crc_input = Vec::new();
@@ -302,20 +923,16 @@ mod tests {
     /// Test full HI91 frame parsing with reference example data
     #[test]
     fn test_parse_hi91_frame() {
-        let frame = hex_to_bytes(
-            "5A A5 4C 00 14 BB 91 08 15 23 09 A2 C4 47 08 15 1C 00 \
-             CC E8 61 BE 9A 35 56 3E 65 EA 72 3F 31 D0 7C BD 75 DD \
-             C5 BB 6B D7 24 BC 89 88 FC 40 01 00 6A 41 AB 2A 70 C2 \
-             96 D4 50 41 ED 03 43 41 41 F4 F4 C2 CC CA F8 BE 73 6A \
-             19 BE F0 00 1C 3D 8D 37 5C 3F",
-        );
+        let frame = hi91_test_frame();
 
         let mut decoder = HipnucDecoder::new();
         let packets = decoder.input_bytes(&frame);
 
         assert_eq!(packets.len(), 1, "Should parse exactly one HI91 packet");
 
-        let p = &packets[0];
+        let HipnucPacket::Hi91(p) = &packets[0] else {
+            panic!("expected HI91, got {:?}", packets[0]);
+        };
 
         // Verified by decoding the hex with Node.js against the same GRAVITY constant
         assert!(
@@ -364,13 +981,7 @@ mod tests {
     #[test]
     fn test_resync_after_garbage() {
         let garbage = vec![0x00, 0xFF, 0x12, 0x34, 0x5A, 0x00]; // garbage including a lone 0x5A
-        let frame = hex_to_bytes(
-            "5A A5 4C 00 14 BB 91 08 15 23 09 A2 C4 47 08 15 1C 00 \
-             CC E8 61 BE 9A 35 56 3E 65 EA 72 3F 31 D0 7C BD 75 DD \
-             C5 BB 6B D7 24 BC 89 88 FC 40 01 00 6A 41 AB 2A 70 C2 \
-             96 D4 50 41 ED 03 43 41 41 F4 F4 C2 CC CA F8 BE 73 6A \
-             19 BE F0 00 1C 3D 8D 37 5C 3F",
-        );
+        let frame = hi91_test_frame();
 
         let mut data = garbage;
         data.extend_from_slice(&frame);
@@ -384,13 +995,7 @@ mod tests {
     /// Test that corrupted CRC is rejected
     #[test]
     fn test_crc_mismatch_rejected() {
-        let mut frame = hex_to_bytes(
-            "5A A5 4C 00 14 BB 91 08 15 23 09 A2 C4 47 08 15 1C 00 \
-             CC E8 61 BE 9A 35 56 3E 65 EA 72 3F 31 D0 7C BD 75 DD \
-             C5 BB 6B D7 24 BC 89 88 FC 40 01 00 6A 41 AB 2A 70 C2 \
-             96 D4 50 41 ED 03 43 41 41 F4 F4 C2 CC CA F8 BE 73 6A \
-             19 BE F0 00 1C 3D 8D 37 5C 3F",
-        );
+        let mut frame = hi91_test_frame();
         // Corrupt one payload byte
         frame[10] ^= 0xFF;
 
@@ -399,10 +1004,218 @@ mod tests {
         assert_eq!(packets.len(), 0, "Corrupted frame should be rejected");
     }
 
-    /// Helper: convert hex string to bytes
-    fn hex_to_bytes(hex: &str) -> Vec<u8> {
-        hex.split_whitespace()
-            .map(|s| u8::from_str_radix(s, 16).unwrap())
-            .collect()
+    /// Test the encoder reproduces the reference HI91 payload (bar main_status)
+    #[test]
+    fn test_hi91_encode_round_trip() {
+        let frame = hi91_test_frame();
+        let packets = HipnucDecoder::new().input_bytes(&frame);
+        let Some(HipnucPacket::Hi91(d)) = packets.first() else {
+            panic!("expected HI91, got {:?}", packets);
+        };
+
+        let payload = encode_hi91(d);
+        assert_eq!(payload.len(), HI91_SIZE);
+        assert_eq!(payload[0], FRAME_TAG_HI91);
+        assert_eq!(payload[3..], frame[CH_HDR_SIZE + 3..]);
+
+        let mut original = frame[CH_HDR_SIZE..].to_vec();
+        original[1..3].copy_from_slice(&[0, 0]);
+        let reencoded = encode_frame(&original);
+        assert_eq!(reencoded[0..4], frame[0..4]);
+        let again = HipnucDecoder::new().input_bytes(&reencoded);
+        assert_eq!(again[0].imu_sample().unwrap().quat, d.quat);
+    }
+
+    /// Test a frame carrying several sub-packets yields all of them, in order
+    #[test]
+    fn test_multiple_subpackets_in_one_frame() {
+        let hi91_payload = hi91_test_frame()[CH_HDR_SIZE..].to_vec();
+
+        let mut payload = vec![FRAME_TAG_USER_ID, 0x07];
+        payload.extend_from_slice(&hi91_payload);
+        payload.push(FRAME_TAG_QUAT);
+        for q in [1.0f32, 0.0, 0.0, 0.0] {
+            payload.extend_from_slice(&q.to_le_bytes());
+        }
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&payload));
+
+        assert_eq!(packets.len(), 3);
+        assert!(matches!(packets[0], HipnucPacket::UserId(7)));
+        assert_eq!(packets[1].imu_sample().unwrap().system_time, 1840392);
+        match &packets[2] {
+            HipnucPacket::Quaternion(q) => assert_eq!(*q, [1.0, 0.0, 0.0, 0.0]),
+            other => panic!("expected quaternion, got {:?}", other),
+        }
+    }
+
+    /// Test that an unknown tag stops the walk but keeps earlier packets
+    #[test]
+    fn test_unknown_tag_keeps_previous_packets() {
+        let mut payload = vec![FRAME_TAG_USER_ID, 0x01, 0xEE, 0x00, 0x00];
+        payload.extend_from_slice(&[FRAME_TAG_USER_ID, 0x02]);
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&payload));
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(packets[0], HipnucPacket::UserId(1)));
+    }
+
+    #[test]
+    fn test_parse_hi92() {
+        let mut p = vec![0u8; HI92_SIZE];
+        p[0] = FRAME_TAG_HI92;
+        p[3] = 30; // temperature
+        p[6..10].copy_from_slice(&5000u32.to_le_bytes());
+        p[14..16].copy_from_slice(&2008i16.to_le_bytes()); // acc_z ≈ 9.805 m/s^2
+        p[16..18].copy_from_slice(&1000i16.to_le_bytes()); // gyr_x = 1 rad/s
+        p[28..32].copy_from_slice(&(-45_000i32).to_le_bytes()); // roll = -45 deg
+        p[40..42].copy_from_slice(&10000i16.to_le_bytes()); // qw = 1
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&p));
+        assert_eq!(packets.len(), 1);
+        let HipnucPacket::Hi92(d) = &packets[0] else {
+            panic!("expected HI92");
+        };
+        assert_eq!(d.temperature, 30);
+        assert_eq!(d.system_time, 5000);
+        assert!((d.acc[2] - 9.805).abs() < 0.01, "acc_z {}", d.acc[2]);
+        assert!((d.gyr[0] - 57.2958).abs() < 0.001, "gyr_x {}", d.gyr[0]);
+        assert!((d.roll + 45.0).abs() < 1e-9);
+        assert!((d.quat[0] - 1.0).abs() < 1e-9);
+
+        // HI92 is also exposed as a Hi91-shaped IMU sample
+        let sample = packets[0].imu_sample().unwrap();
+        assert_eq!(sample.system_time, 5000);
+        assert!(sample.air_pressure.is_nan(), "HI92 has no pressure");
+    }
+
+    #[test]
+    fn test_parse_hi81() {
+        let mut p = vec![0u8; HI81_SIZE];
+        p[0] = FRAME_TAG_HI81;
+        p[3] = 2; // ins_status
+        p[4..6].copy_from_slice(&2300u16.to_le_bytes());
+        p[6..10].copy_from_slice(&123_456u32.to_le_bytes());
+        p[36] = (-5i8) as u8;
+        p[48..50].copy_from_slice(&27000u16.to_le_bytes()); // yaw = 270 deg
+        p[58..62].copy_from_slice(&1_213_456_789i32.to_le_bytes());
+        p[62..66].copy_from_slice(&312_345_678i32.to_le_bytes());
+        p[66..70].copy_from_slice(&15_250i32.to_le_bytes());
+        p[80..82].copy_from_slice(&150i16.to_le_bytes()); // vel_e = 1.5 m/s
+        p[100..104].copy_from_slice(&(-2_000i32).to_le_bytes());
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&p));
+        assert_eq!(packets.len(), 1);
+        let HipnucPacket::Hi81(d) = &packets[0] else {
+            panic!("expected HI81");
+        };
+        assert_eq!(d.ins_status, 2);
+        assert_eq!(d.gpst_wn, 2300);
+        assert_eq!(d.gpst_tow, 123_456);
+        assert_eq!(d.temperature, -5);
+        assert!((d.yaw - 270.0).abs() < 1e-9);
+        assert!((d.ins_lon - 121.3456789).abs() < 1e-9);
+        assert!((d.ins_lat - 31.2345678).abs() < 1e-9);
+        assert!((d.ins_msl - 15.25).abs() < 1e-9);
+        assert!((d.vel_enu[0] - 1.5).abs() < 1e-9);
+        assert!((d.gnss_msl + 2.0).abs() < 1e-9);
+        assert!(packets[0].imu_sample().is_none());
+    }
+
+    #[test]
+    fn test_parse_hi83_bitmap() {
+        let bitmap = HI83_BMAP_GYR | HI83_BMAP_SYSTEM_TIME | HI83_BMAP_TEMPERATURE;
+        let mut p = vec![FRAME_TAG_HI83, 0x00, 0x00];
+        p.extend_from_slice(&bitmap.to_le_bytes());
+        for g in [1.5f32, -2.0, 0.25] {
+            p.extend_from_slice(&g.to_le_bytes());
+        }
+        p.extend_from_slice(&777u32.to_le_bytes());
+        p.extend_from_slice(&36.5f32.to_le_bytes());
+        assert_eq!(Some(p.len()), hi83_size(bitmap));
+
+        // Follow with a user ID to check the walk advances past HI83 correctly
+        p.extend_from_slice(&[FRAME_TAG_USER_ID, 0x09]);
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&p));
+        assert_eq!(packets.len(), 2);
+        let HipnucPacket::Hi83(d) = &packets[0] else {
+            panic!("expected HI83");
+        };
+        assert_eq!(d.gyr, Some([1.5, -2.0, 0.25]));
+        assert_eq!(d.system_time, Some(777));
+        assert_eq!(d.temperature, Some(36.5));
+        assert!(d.acc.is_none());
+        assert!(d.quat.is_none());
+        assert!(matches!(packets[1], HipnucPacket::UserId(9)));
+    }
+
+    #[test]
+    fn test_hi83_unknown_bitmap_bit_rejected() {
+        assert_eq!(hi83_size(1 << 20), None);
+        assert_eq!(hi83_size(0), Some(HI83_HDR_SIZE));
+    }
+
+    /// Test a sub-packet cut short by the frame length is not decoded
+    #[test]
+    fn test_truncated_subpacket() {
+        let hi91_payload = hi91_test_frame()[CH_HDR_SIZE..].to_vec();
+        let mut payload = vec![FRAME_TAG_USER_ID, 0x03];
+        payload.extend_from_slice(&hi91_payload[..40]);
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&encode_frame(&payload));
+        assert_eq!(packets.len(), 1);
+        assert!(matches!(packets[0], HipnucPacket::UserId(3)));
+    }
+
+    /// Test that text around frames is handed back for the AT layer
+    #[test]
+    fn test_unframed_bytes_captured() {
+        let mut data = b"OK\r\n".to_vec();
+        data.extend_from_slice(&hi91_test_frame());
+        data.extend_from_slice(b"ZZ\r\n");
+
+        let mut decoder = HipnucDecoder::new();
+        decoder.set_capture_unframed(true);
+        let packets = decoder.input_bytes(&data);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(decoder.take_unframed(), b"OK\r\nZZ\r\n".to_vec());
+        assert!(decoder.take_unframed().is_empty());
+    }
+
+    /// Test every failure path lands in its own counter
+    #[test]
+    fn test_decoder_stats() {
+        let good = hi91_test_frame();
+        let mut bad_crc = good.clone();
+        bad_crc[10] ^= 0xFF;
+
+        let mut data = vec![0x00, 0x11, 0x5A, 0x00]; // 4 resync bytes
+        data.extend_from_slice(&[0x5A, 0x5A]); // first 5A skipped, second starts the next frame
+        data.extend_from_slice(&good[1..]);
+        data.extend_from_slice(&bad_crc);
+        data.extend_from_slice(&[CHSYNC1, CHSYNC2, 0x00, 0x00, 0x00, 0x00]); // zero length
+        data.extend_from_slice(&encode_frame(&[FRAME_TAG_USER_ID, 0x01, 0xEE]));
+        data.extend_from_slice(&encode_frame(&[FRAME_TAG_HI91, 0x00]));
+
+        let mut decoder = HipnucDecoder::new();
+        let packets = decoder.input_bytes(&data);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].system_time(), Some(1840392));
+
+        let stats = decoder.stats();
+        assert_eq!(stats.frames_ok, 3);
+        assert_eq!(stats.crc_failures, 1);
+        assert_eq!(stats.resync_bytes, 5);
+        assert_eq!(stats.oversize_lengths, 1);
+        assert_eq!(stats.unknown_tags, 1);
+        assert_eq!(stats.truncated_packets, 1);
+        assert_eq!(stats.packets, 2);
     }
 }
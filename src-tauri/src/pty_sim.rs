@@ -0,0 +1,306 @@
+//! Pseudo-terminal IMU simulator for end-to-end serial tests
+//!
+//! Opens a pty pair and plays a HiPNUC device on the master end: valid frames
+//! from a `VirtualImu`, plus garbage, corrupted and truncated frames on demand.
+//! The slave end is an ordinary tty, so tests open it by name through
+//! `serial::open_port` and go through the real read thread, decoder, link
+//! statistics and reconnect logic.
+
+use std::io::Write;
+
+use serialport::{SerialPort, TTYPort};
+
+use crate::protocol::{encode_frame, encode_hi91, Hi91Data};
+use crate::virtual_imu::{VirtualImu, VirtualImuConfig};
+
+pub struct PtyImu {
+    /// Device side; dropping it hangs up the line
+    master: Option<TTYPort>,
+    /// Held so the slave stays valid until the app has opened it
+    slave: Option<TTYPort>,
+    port_name: String,
+    imu: VirtualImu,
+}
+
+impl PtyImu {
+    pub fn new(config: VirtualImuConfig) -> Result<Self, String> {
+        let (master, slave) = TTYPort::pair().map_err(|e| format!("Failed to open pty pair: {}", e))?;
+        let port_name = slave.name().ok_or("Pty slave has no device name")?;
+        Ok(Self {
+            master: Some(master),
+            slave: Some(slave),
+            port_name,
+            imu: VirtualImu::new(config)?,
+        })
+    }
+
+    /// Device path of the slave end, for `open_port`
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let master = self.master.as_mut().ok_or("Pty already hung up")?;
+        master.write_all(bytes).map_err(|e| e.to_string())?;
+        master.flush().map_err(|e| e.to_string())
+    }
+
+    fn next_frame(&mut self) -> (Hi91Data, Vec<u8>) {
+        let sample = self.imu.next_sample();
+        let frame = encode_frame(&encode_hi91(&sample));
+        (sample, frame)
+    }
+
+    /// Send `count` valid frames; returns the samples in them
+    pub fn send_samples(&mut self, count: usize) -> Result<Vec<Hi91Data>, String> {
+        let mut sent = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (sample, frame) = self.next_frame();
+            self.write_raw(&frame)?;
+            sent.push(sample);
+        }
+        Ok(sent)
+    }
+
+    /// Line noise that never contains the 0x5A sync byte
+    pub fn send_garbage(&mut self, len: usize) -> Result<(), String> {
+        let bytes: Vec<u8> = (0..len)
+            .map(|i| match (i * 37 % 251) as u8 {
+                0x5A => 0x00,
+                b => b,
+            })
+            .collect();
+        self.write_raw(&bytes)
+    }
+
+    /// A full frame with one payload byte flipped, so its CRC fails
+    pub fn send_corrupted(&mut self) -> Result<Hi91Data, String> {
+        let (sample, mut frame) = self.next_frame();
+        let i = frame.len() - 10;
+        frame[i] ^= 0xFF;
+        self.write_raw(&frame)?;
+        Ok(sample)
+    }
+
+    /// The first half of a frame, as when the device resets mid-write. The
+    /// decoder completes it with the start of the next frame, so that one is
+    /// lost too.
+    pub fn send_truncated(&mut self) -> Result<Hi91Data, String> {
+        let (sample, frame) = self.next_frame();
+        self.write_raw(&frame[..frame.len() / 2])?;
+        Ok(sample)
+    }
+
+    /// Close both ends, as if the USB adapter was unplugged
+    pub fn hang_up(&mut self) {
+        self.master = None;
+        self.slave = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::{Duration, Instant};
+
+    use serde_json::Value;
+    use tauri::test::{mock_app, MockRuntime};
+    use tauri::{App, Listener, Manager};
+
+    use crate::capture::CaptureWriter;
+    use crate::serial;
+    use crate::state::AppState;
+
+    const WAIT: Duration = Duration::from_secs(3);
+
+    fn quiet_imu() -> PtyImu {
+        PtyImu::new(VirtualImuConfig {
+            gyr_noise: 0.0,
+            acc_noise: 0.0,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn app_with_state() -> App<MockRuntime> {
+        let app = mock_app();
+        app.manage(AppState::new());
+        app
+    }
+
+    fn listen(app: &App<MockRuntime>, event: &str) -> Receiver<Value> {
+        let (tx, rx) = channel();
+        app.listen_any(event, move |e| {
+            if let Ok(v) = serde_json::from_str(e.payload()) {
+                let _ = tx.send(v);
+            }
+        });
+        rx
+    }
+
+    /// Wait for the first payload that satisfies `pred`
+    fn wait_for(rx: &Receiver<Value>, pred: impl Fn(&Value) -> bool) -> Option<Value> {
+        let deadline = Instant::now() + WAIT;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(left) {
+                Ok(v) if pred(&v) => return Some(v),
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+
+    fn collect(rx: &Receiver<Value>, count: usize) -> Vec<Value> {
+        let deadline = Instant::now() + WAIT;
+        let mut out = Vec::new();
+        while out.len() < count {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            match rx.recv_timeout(left) {
+                Ok(v) => out.push(v),
+                Err(_) => break,
+            }
+        }
+        out
+    }
+
+    fn open(app: &App<MockRuntime>, imu: &PtyImu) {
+        serial::open_port(
+            app.handle().clone(),
+            app.state::<AppState>(),
+            imu.port_name().to_string(),
+            115200,
+            Some("pty".to_string()),
+        )
+        .unwrap();
+    }
+
+    fn close(app: &App<MockRuntime>) {
+        serial::close_port(app.handle().clone(), app.state::<AppState>(), Some("pty".to_string())).unwrap();
+    }
+
+    #[test]
+    fn test_open_port_streams_samples() {
+        let app = app_with_state();
+        let data = listen(&app, "imu-data");
+        let mut imu = quiet_imu();
+        open(&app, &imu);
+
+        let sent = imu.send_samples(20).unwrap();
+        let got = collect(&data, 20);
+        assert_eq!(got.len(), 20);
+        for (s, v) in sent.iter().zip(&got) {
+            assert_eq!(v["session_id"], "pty");
+            assert_eq!(v["system_time"], s.system_time);
+            assert!((v["acc"][2].as_f64().unwrap() - s.acc[2]).abs() < 1e-4);
+        }
+        close(&app);
+    }
+
+    #[test]
+    fn test_corrupt_frames_are_counted_and_stream_recovers() {
+        let app = app_with_state();
+        let data = listen(&app, "imu-data");
+        let stats = listen(&app, "imu-link-stats");
+        let mut imu = quiet_imu();
+        open(&app, &imu);
+
+        let mut expected: Vec<u32> = Vec::new();
+        let times = |v: Vec<Hi91Data>| v.into_iter().map(|s| s.system_time).collect::<Vec<_>>();
+        expected.extend(times(imu.send_samples(5).unwrap()));
+        imu.send_garbage(32).unwrap();
+        imu.send_corrupted().unwrap();
+        expected.extend(times(imu.send_samples(5).unwrap()));
+        imu.send_truncated().unwrap();
+        // The first frame after the truncated one completes it and is lost
+        let after = times(imu.send_samples(5).unwrap());
+        expected.extend(&after[1..]);
+
+        let got: Vec<u64> = collect(&data, expected.len())
+            .iter()
+            .map(|v| v["system_time"].as_u64().unwrap())
+            .collect();
+        assert_eq!(got, expected.iter().map(|&t| t as u64).collect::<Vec<_>>());
+
+        let stats = wait_for(&stats, |v| v["decoder"]["crc_failures"].as_u64() == Some(2))
+            .expect("link stats with both CRC failures");
+        assert_eq!(stats["decoder"]["frames_ok"].as_u64(), Some(expected.len() as u64));
+        assert!(stats["decoder"]["resync_bytes"].as_u64().unwrap() >= 32);
+        close(&app);
+    }
+
+    #[test]
+    fn test_hang_up_reports_reconnecting() {
+        let app = app_with_state();
+        let connection = listen(&app, "serial-connection");
+        let errors = listen(&app, "serial-error");
+        let mut imu = quiet_imu();
+        open(&app, &imu);
+        assert!(wait_for(&connection, |v| v["state"] == "connected").is_some());
+
+        imu.send_samples(3).unwrap();
+        imu.hang_up();
+        assert!(wait_for(&errors, |v| v["message"].is_string()).is_some());
+        let status = wait_for(&connection, |v| v["state"] == "reconnecting").expect("reconnecting status");
+        assert_eq!(status["session_id"], "pty");
+
+        close(&app);
+        // The reader has exited by the time close_port returns, so Closed is the last word
+        let session = app.state::<AppState>().imu.get(Some("pty")).unwrap();
+        assert!(session.source_thread.lock().unwrap().is_none());
+        assert!(wait_for(&connection, |v| v["state"] == "closed").is_some());
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(connection.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_reopen_leaves_a_single_reader() {
+        let app = app_with_state();
+        let data = listen(&app, "imu-data");
+        let mut imu = quiet_imu();
+        open(&app, &imu);
+        open(&app, &imu);
+
+        let sent = imu.send_samples(20).unwrap();
+        let got: Vec<u64> = collect(&data, 20).iter().map(|v| v["system_time"].as_u64().unwrap()).collect();
+        assert_eq!(got, sent.iter().map(|s| s.system_time as u64).collect::<Vec<_>>());
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(data.try_recv().is_err());
+        close(&app);
+    }
+
+    #[test]
+    fn test_replay_restart_right_after_stop() {
+        let path = std::env::temp_dir().join(format!("hecate_replay_restart_{}.cap", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut writer = CaptureWriter::create(&path, 115200).unwrap();
+        for _ in 0..5 {
+            writer.write_chunk(&[0u8; 16]);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        writer.finish().unwrap();
+
+        let app = app_with_state();
+        let finished = listen(&app, "imu-replay-finished");
+        let session = || Some("replay".to_string());
+        let replay = || serial::replay_capture(app.handle().clone(), app.state::<AppState>(), path.clone(), Some(0.01), session());
+
+        replay().unwrap();
+        serial::stop_replay(app.state::<AppState>(), session()).unwrap();
+        replay().unwrap();
+        // The first run has already ended; only the new one is feeding the session
+        let first = finished.try_recv().expect("first run finished before the restart");
+        assert_eq!(first["completed"], false);
+        assert!(app.state::<AppState>().imu.get(Some("replay")).unwrap().replay_running.load(Ordering::SeqCst));
+
+        serial::stop_replay(app.state::<AppState>(), session()).unwrap();
+        assert!(wait_for(&finished, |_| true).is_some());
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(finished.try_recv().is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
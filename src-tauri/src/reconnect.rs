@@ -0,0 +1,191 @@
+//! Serial reconnect support
+//!
+//! USB serial adapters often come back under a different name after a replug
+//! (ttyUSB0 -> ttyUSB1, COM5 -> COM7), so the port is remembered by its USB
+//! VID/PID/serial number and looked up again in the port list. Ports without
+//! USB information or without a serial number can only be matched by name.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use serialport::{SerialPortInfo, SerialPortType};
+
+/// What identifies "the same device" across an unplug
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortIdentity {
+    pub name: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+}
+
+impl PortIdentity {
+    /// Identity of `port_name`, enriched with USB details when it is in `ports`
+    pub fn resolve(port_name: &str, ports: &[SerialPortInfo]) -> Self {
+        let usb = ports
+            .iter()
+            .find(|p| p.port_name == port_name)
+            .and_then(|p| match &p.port_type {
+                SerialPortType::UsbPort(info) => Some(info),
+                _ => None,
+            });
+        Self {
+            name: port_name.to_string(),
+            vid: usb.map(|u| u.vid),
+            pid: usb.map(|u| u.pid),
+            serial_number: usb.and_then(|u| u.serial_number.clone()),
+        }
+    }
+
+    /// Find the port name this device currently has, if it is plugged in
+    pub fn find(&self, ports: &[SerialPortInfo]) -> Option<String> {
+        let (Some(vid), Some(pid)) = (self.vid, self.pid) else {
+            return ports
+                .iter()
+                .find(|p| p.port_name == self.name)
+                .map(|p| p.port_name.clone());
+        };
+
+        let candidates: Vec<&SerialPortInfo> = ports
+            .iter()
+            .filter(|p| match &p.port_type {
+                SerialPortType::UsbPort(u) => {
+                    u.vid == vid
+                        && u.pid == pid
+                        && (self.serial_number.is_none() || u.serial_number == self.serial_number)
+                }
+                _ => false,
+            })
+            .collect();
+
+        // Adapters without a serial number cannot be told apart: only trust the old name
+        match candidates.as_slice() {
+            [only] if self.serial_number.is_some() => Some(only.port_name.clone()),
+            many => many
+                .iter()
+                .find(|p| p.port_name == self.name)
+                .map(|p| p.port_name.clone()),
+        }
+    }
+}
+
+/// Exponential retry delay
+pub struct Backoff {
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self { max, next: initial }
+    }
+
+    /// Delay before the next attempt; doubles up to `max`
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Closed,
+    Connected,
+    /// Port lost; retrying in the background
+    Reconnecting,
+}
+
+/// Payload of the "serial-connection" event
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStatus {
+    pub state: ConnectionState,
+    pub port_name: Option<String>,
+    pub baud_rate: u32,
+    /// Reconnect attempts since the port was lost (0 while connected)
+    pub attempt: u32,
+    /// Successful reconnects since the port was opened
+    pub reconnects: u32,
+    pub last_error: Option<String>,
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        Self {
+            state: ConnectionState::Closed,
+            port_name: None,
+            baud_rate: 0,
+            attempt: 0,
+            reconnects: 0,
+            last_error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::UsbPortInfo;
+
+    fn usb(name: &str, vid: u16, pid: u16, serial: Option<&str>) -> SerialPortInfo {
+        SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid,
+                pid,
+                serial_number: serial.map(str::to_string),
+                manufacturer: None,
+                product: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_port_found_under_new_name() {
+        let before = vec![usb("/dev/ttyUSB0", 0x1A86, 0x7523, Some("A1"))];
+        let id = PortIdentity::resolve("/dev/ttyUSB0", &before);
+        assert_eq!(id.vid, Some(0x1A86));
+
+        assert_eq!(id.find(&[]), None, "unplugged");
+        let after = vec![
+            usb("/dev/ttyUSB0", 0x1A86, 0x7523, Some("B2")),
+            usb("/dev/ttyUSB1", 0x1A86, 0x7523, Some("A1")),
+        ];
+        assert_eq!(id.find(&after).as_deref(), Some("/dev/ttyUSB1"));
+    }
+
+    #[test]
+    fn test_ambiguous_adapters_keep_old_name() {
+        let id = PortIdentity::resolve("COM5", &[usb("COM5", 0x10C4, 0xEA60, None)]);
+        let two = vec![
+            usb("COM5", 0x10C4, 0xEA60, None),
+            usb("COM7", 0x10C4, 0xEA60, None),
+        ];
+        assert_eq!(id.find(&two).as_deref(), Some("COM5"));
+        let moved = vec![
+            usb("COM6", 0x10C4, 0xEA60, None),
+            usb("COM7", 0x10C4, 0xEA60, None),
+        ];
+        assert_eq!(id.find(&moved), None);
+        // A lone look-alike under another name may be a different device
+        assert_eq!(id.find(&[usb("COM6", 0x10C4, 0xEA60, None)]), None);
+    }
+
+    #[test]
+    fn test_non_usb_port_matched_by_name() {
+        let id = PortIdentity::resolve("/dev/ttyS0", &[]);
+        let ports = vec![SerialPortInfo {
+            port_name: "/dev/ttyS0".to_string(),
+            port_type: SerialPortType::Unknown,
+        }];
+        assert_eq!(id.find(&ports).as_deref(), Some("/dev/ttyS0"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_to_max() {
+        let mut b = Backoff::new(Duration::from_millis(250), Duration::from_secs(1));
+        let delays: Vec<u128> = (0..4).map(|_| b.next_delay().as_millis()).collect();
+        assert_eq!(delays, vec![250, 500, 1000, 1000]);
+    }
+}
@@ -0,0 +1,146 @@
+//! CSV recorder for decoded HI91 packets
+//!
+//! One row per packet: host wall-clock timestamp (ms since UNIX epoch), the
+//! device `system_time` (ms), then the decoded fields in `Hi91Data` order.
+//! Rows that fail to write are counted as dropped instead of aborting the
+//! recording, so a full disk shows up in the stats rather than as a silent stop.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use serde::Serialize;
+
+use crate::protocol::Hi91Data;
+
+pub const CSV_HEADER: &str = "timestamp_ms,system_time,acc_x,acc_y,acc_z,gyr_x,gyr_y,gyr_z,mag_x,mag_y,mag_z,roll,pitch,yaw,qw,qx,qy,qz,temperature,air_pressure";
+
+/// Recording progress reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStats {
+    pub file_path: String,
+    pub rows_written: u64,
+    pub rows_dropped: u64,
+    pub duration_ms: u64,
+    pub last_error: Option<String>,
+}
+
+pub struct CsvRecorder {
+    writer: BufWriter<File>,
+    file_path: String,
+    rows_written: u64,
+    rows_dropped: u64,
+    started_ms: u64,
+    last_error: Option<String>,
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl CsvRecorder {
+    /// Create the file and write the CSV header
+    pub fn create(file_path: &str) -> Result<Self, String> {
+        let file = File::create(file_path).map_err(|e| format!("Failed to create file: {}", e))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", CSV_HEADER).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            writer,
+            file_path: file_path.to_string(),
+            rows_written: 0,
+            rows_dropped: 0,
+            started_ms: now_ms(),
+            last_error: None,
+        })
+    }
+
+    /// Append one packet, stamped with the given host time
+    pub fn write_row(&mut self, host_time_ms: u64, p: &Hi91Data) {
+        // Empty cell rather than a made-up value when the packet had no pressure
+        let air_pressure = if p.air_pressure.is_nan() { String::new() } else { p.air_pressure.to_string() };
+        let result = writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            host_time_ms,
+            p.system_time,
+            p.acc[0], p.acc[1], p.acc[2],
+            p.gyr[0], p.gyr[1], p.gyr[2],
+            p.mag[0], p.mag[1], p.mag[2],
+            p.roll, p.pitch, p.yaw,
+            p.quat[0], p.quat[1], p.quat[2], p.quat[3],
+            p.temperature,
+            air_pressure,
+        );
+
+        match result {
+            Ok(()) => self.rows_written += 1,
+            Err(e) => {
+                if self.last_error.is_none() {
+                    log::error!("CSV write failed ({}): {}", self.file_path, e);
+                }
+                self.rows_dropped += 1;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn stats(&self) -> RecordingStats {
+        RecordingStats {
+            file_path: self.file_path.clone(),
+            rows_written: self.rows_written,
+            rows_dropped: self.rows_dropped,
+            duration_ms: now_ms().saturating_sub(self.started_ms),
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// Flush buffered rows to disk and return the final stats
+    pub fn finish(mut self) -> Result<RecordingStats, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush {}: {}", self.file_path, e))?;
+        Ok(self.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_written_and_flushed() {
+        let path = std::env::temp_dir().join(format!("hecate_rec_{}.csv", std::process::id()));
+        let path_str = path.to_string_lossy().to_string();
+
+        let mut rec = CsvRecorder::create(&path_str).unwrap();
+        let packet = Hi91Data {
+            system_time: 1234,
+            temperature: 35,
+            ..Default::default()
+        };
+        rec.write_row(1_000, &packet);
+        rec.write_row(1_005, &Hi91Data { air_pressure: f64::NAN, ..packet.clone() });
+
+        let stats = rec.finish().unwrap();
+        assert_eq!(stats.rows_written, 2);
+        assert_eq!(stats.rows_dropped, 0);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3, "header + 2 rows");
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("1000,1234,"));
+        assert_eq!(
+            lines[1].split(',').count(),
+            CSV_HEADER.split(',').count(),
+            "row must have one value per header column"
+        );
+        assert!(lines[1].ends_with(",0"));
+        assert!(lines[2].ends_with(",35,"), "no pressure: {}", lines[2]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
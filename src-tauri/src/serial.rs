@@ -1,18 +1,67 @@
 use std::io::{Read, Write};
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Manager, Runtime};
 
-use crate::protocol::HipnucDecoder;
+use crate::ahrs::{Ahrs, AhrsConfig};
+use crate::allan::{NoiseAnalysis, NoiseCollector, NoiseProgress};
+use crate::baud_detect::{self, BaudDetection, ProbeDecoder};
+use crate::calibration::{
+    self, AccelCalibResult, AccelFaceResult, CalibSample, GyroCalibResult, ImuCalibration,
+    MagCalibResult, SampleCollector,
+};
+use crate::capture::{CaptureReader, CaptureStats, CaptureWriter};
+use crate::imu_at::{self, AtExpect, AtResponse, ImuDeviceInfo};
+use crate::imu_session::{ImuSession, SessionInfo};
+use crate::link_stats::{LinkMonitor, LinkStats};
+use crate::output_profile::OutputProfile;
+use crate::protocol::{Hi91Data, HipnucDecoder, HipnucPacket};
+use crate::reconnect::{Backoff, ConnectionState, ConnectionStatus, PortIdentity};
+use crate::recorder::{self, CsvRecorder, RecordingStats};
 use crate::state::AppState;
+use crate::virtual_imu::{VirtualImu, VirtualImuConfig};
+
+/// Interval between periodic "imu-link-stats" / "imu-recording-stats" events
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default time to wait for an AT command reply
+const AT_DEFAULT_TIMEOUT_MS: u64 = 1000;
+/// INFO replies have no terminator; consider them complete after this much silence
+const AT_TEXT_IDLE: Duration = Duration::from_millis(150);
+
+/// Default listening time per candidate rate during baud detection
+const BAUD_DETECT_SAMPLE_MS: u64 = 600;
+
+/// Reconnect backoff after the port is lost
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5);
+/// How often a sleeping reconnect loop checks for close_port
+const RECONNECT_POLL: Duration = Duration::from_millis(50);
+
+/// Default collection time per calibration step
+const CALIB_GYRO_MS: u64 = 5_000;
+const CALIB_ACCEL_FACE_MS: u64 = 2_000;
+const CALIB_MAG_MS: u64 = 30_000;
+const CALIB_POLL: Duration = Duration::from_millis(20);
+
+/// Longest single sleep in the replay thread, so stop requests are seen promptly
+const REPLAY_MAX_SLEEP: Duration = Duration::from_millis(50);
+/// Virtual IMU output period: samples due in each tick go out as one chunk,
+/// like a USB serial adapter batching frames
+const VIRTUAL_TICK: Duration = Duration::from_millis(5);
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PortInfo {
     pub name: String,
     pub port_type: String,
+    /// USB identity (None for non-USB ports); used to find the device again after a replug
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
 }
 
 /// List available serial ports
@@ -38,7 +87,14 @@ pub fn list_ports() -> Result<Vec<PortInfo>, String> {
                 serialport::SerialPortType::PciPort => "PCI".to_string(),
                 serialport::SerialPortType::Unknown => "Unknown".to_string(),
             };
+            let usb = match &p.port_type {
+                serialport::SerialPortType::UsbPort(info) => Some(info),
+                _ => None,
+            };
             PortInfo {
+                vid: usb.map(|u| u.vid),
+                pid: usb.map(|u| u.pid),
+                serial_number: usb.and_then(|u| u.serial_number.clone()),
                 name: p.port_name,
                 port_type,
             }
@@ -46,76 +102,145 @@ pub fn list_ports() -> Result<Vec<PortInfo>, String> {
         .collect())
 }
 
-/// Open serial port and start reading data in a background thread
+/// Open serial port and start reading data in a background thread.
+/// If the port is lost later (USB unplug), the read thread keeps trying to
+/// reopen the same device and reports progress as "serial-connection" events.
+/// `session_id` names the IMU (default session when omitted); reopening a
+/// session replaces its port.
 #[tauri::command]
-pub fn open_port(
-    app: AppHandle,
+pub fn open_port<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     port_name: String,
     baud_rate: u32,
+    session_id: Option<String>,
 ) -> Result<(), String> {
-    // Close existing port if any
-    {
-        state.read_running.store(false, Ordering::SeqCst);
-        let mut port_lock = state.port.lock().map_err(|e| e.to_string())?;
-        *port_lock = None;
-        // Brief pause to let old read thread exit
-        std::thread::sleep(Duration::from_millis(100));
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    if let Some(owner) = state.imu.port_owner(&port_name, &session.id) {
+        return Err(format!("{} is already open in IMU session '{}'", port_name, owner));
     }
 
-    // Open new port
-    let port = serialport::new(&port_name, baud_rate)
-        .data_bits(serialport::DataBits::Eight)
-        .parity(serialport::Parity::None)
-        .stop_bits(serialport::StopBits::One)
-        .flow_control(serialport::FlowControl::None)
-        .timeout(Duration::from_millis(100))
-        .open()
-        .map_err(|e| format!("Failed to open {}: {}", port_name, e))?;
+    // Close existing port if any; a live port replaces any running replay or virtual IMU
+    let mut source = session.source_thread.lock().map_err(|e| e.to_string())?;
+    session.read_running.store(false, Ordering::SeqCst);
+    session.replay_running.store(false, Ordering::SeqCst);
+    session.virtual_running.store(false, Ordering::SeqCst);
+    *session.port.lock().map_err(|e| e.to_string())? = None;
+    // The old reader may hold the device open and touches `port` while reconnecting
+    join_source_thread(&mut source);
+
+    let port = open_serial(&port_name, baud_rate)?;
 
     // Clone port for the read thread
     let read_port = port.try_clone().map_err(|e| e.to_string())?;
 
     // Store the port
     {
-        let mut port_lock = state.port.lock().map_err(|e| e.to_string())?;
+        let mut port_lock = session.port.lock().map_err(|e| e.to_string())?;
         *port_lock = Some(port);
     }
 
-    // Reset decoder
-    {
-        let mut decoder = state.decoder.lock().map_err(|e| e.to_string())?;
-        *decoder = HipnucDecoder::new();
-    }
+    // Remember the USB identity so the device can be found again after a replug
+    let ports = serialport::available_ports().unwrap_or_default();
+    let identity = PortIdentity::resolve(&port_name, &ports);
 
     // Start read thread
-    let running = Arc::clone(&state.read_running);
-    running.store(true, Ordering::SeqCst);
-
-    std::thread::spawn(move || {
-        read_thread(read_port, running, app);
+    claim_source(&session.read_running, "Serial port is already open")?;
+    if let Ok(mut at) = session.at_lines.lock() {
+        at.cancel();
+    }
+    if let Ok(mut ls) = session.link_stats.lock() {
+        *ls = None;
+    }
+    session.set_connection(&app, |c| {
+        *c = ConnectionStatus {
+            state: ConnectionState::Connected,
+            port_name: Some(port_name.clone()),
+            baud_rate,
+            ..Default::default()
+        };
     });
 
-    log::info!("Opened port {} at {} baud", port_name, baud_rate);
+    let thread_session = Arc::clone(&session);
+    *source = Some(std::thread::spawn(move || {
+        read_thread(read_port, identity, baud_rate, thread_session, app);
+    }));
+    drop(source);
+
+    log::info!(
+        "Opened port {} at {} baud (session '{}')",
+        port_name,
+        baud_rate,
+        session.id
+    );
     Ok(())
 }
 
+/// Wait for a stopped source thread to finish
+fn join_source_thread(source: &mut Option<JoinHandle<()>>) {
+    if let Some(thread) = source.take() {
+        if thread.join().is_err() {
+            log::error!("IMU source thread panicked");
+        }
+    }
+}
+
+/// Set a source's running flag, failing if it is already set
+fn claim_source(flag: &AtomicBool, busy: &str) -> Result<(), String> {
+    flag.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .map(|_| ())
+        .map_err(|_| busy.to_string())
+}
+
+fn open_serial(port_name: &str, baud_rate: u32) -> Result<Box<dyn serialport::SerialPort>, String> {
+    serialport::new(port_name, baud_rate)
+        .data_bits(serialport::DataBits::Eight)
+        .parity(serialport::Parity::None)
+        .stop_bits(serialport::StopBits::One)
+        .flow_control(serialport::FlowControl::None)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .map_err(|e| format!("Failed to open {}: {}", port_name, e))
+}
+
 /// Background thread that reads serial data and emits parsed IMU events
-fn read_thread(
+fn read_thread<R: Runtime>(
     mut port: Box<dyn serialport::SerialPort>,
-    running: Arc<std::sync::atomic::AtomicBool>,
-    app: AppHandle,
+    mut identity: PortIdentity,
+    baud_rate: u32,
+    session: Arc<ImuSession>,
+    app: AppHandle<R>,
 ) {
     let mut buf = [0u8; 256];
     let mut decoder = HipnucDecoder::new();
+    decoder.set_capture_unframed(true);
+    let mut link = LinkMonitor::new(Instant::now());
+    let mut last_stats_emit = Instant::now();
 
-    while running.load(Ordering::SeqCst) {
+    while session.read_running.load(Ordering::SeqCst) {
         match port.read(&mut buf) {
             Ok(n) if n > 0 => {
-                let packets = decoder.input_bytes(&buf[..n]);
-                for packet in packets {
-                    if let Err(e) = app.emit("imu-data", &packet) {
-                        log::error!("Failed to emit imu-data event: {}", e);
+                // Raw bytes first, so the capture holds exactly what the decoder saw
+                if let Ok(mut cap) = session.capture.lock() {
+                    if let Some(cap) = cap.as_mut() {
+                        cap.write_chunk(&buf[..n]);
+                    }
+                }
+
+                let mut packets = decoder.input_bytes(&buf[..n]);
+                link.on_bytes(n);
+                track_link(&mut link, &packets);
+                publish_packets(&app, &session, &mut packets);
+
+                // Text between frames: AT command replies
+                let text = decoder.take_unframed();
+                if !text.is_empty() {
+                    let lines = match session.at_lines.lock() {
+                        Ok(mut at) => at.push_bytes(&text, Instant::now()),
+                        Err(_) => Vec::new(),
+                    };
+                    for line in lines {
+                        session.emit(&app, "serial-response", &serde_json::json!({ "line": line }));
                     }
                 }
             }
@@ -127,36 +252,375 @@ fn read_thread(
             }
             Err(e) => {
                 log::error!("Serial read error: {}", e);
-                let _ = app.emit("serial-error", e.to_string());
-                break;
+                session.emit(&app, "serial-error", &serde_json::json!({ "message": e.to_string() }));
+
+                match reconnect(&mut identity, baud_rate, &session, &e.to_string(), &app) {
+                    Some(new_port) => {
+                        // Fresh stream: partial frames and stats from before the drop are stale
+                        port = new_port;
+                        decoder = HipnucDecoder::new();
+                        decoder.set_capture_unframed(true);
+                        link = LinkMonitor::new(Instant::now());
+                    }
+                    None => break,
+                }
             }
         }
+
+        if last_stats_emit.elapsed() >= STATS_INTERVAL {
+            last_stats_emit = Instant::now();
+            let stats = link.snapshot(last_stats_emit, decoder.stats());
+            session.emit(&app, "imu-link-stats", &stats);
+            if let Ok(mut ls) = session.link_stats.lock() {
+                *ls = Some(stats);
+            }
+            if let Ok(rec) = session.recorder.lock() {
+                if let Some(rec) = rec.as_ref() {
+                    session.emit(&app, "imu-recording-stats", &rec.stats());
+                }
+            }
+        }
+    }
+
+    log::info!("Read thread exiting (session '{}')", session.id);
+}
+
+/// Drop the dead handle and retry opening the device with backoff until it is
+/// back or the port is closed. Returns the handle for the read thread.
+fn reconnect<R: Runtime>(
+    identity: &mut PortIdentity,
+    baud_rate: u32,
+    session: &ImuSession,
+    error: &str,
+    app: &AppHandle<R>,
+) -> Option<Box<dyn serialport::SerialPort>> {
+    if let Ok(mut port_lock) = session.port.lock() {
+        *port_lock = None;
     }
+    if let Ok(mut at) = session.at_lines.lock() {
+        at.cancel();
+    }
+
+    let mut backoff = Backoff::new(RECONNECT_INITIAL_DELAY, RECONNECT_MAX_DELAY);
+    let mut attempt = 0;
+    let mut last_error = error.to_string();
+
+    while session.read_running.load(Ordering::SeqCst) {
+        attempt += 1;
+        session.set_connection(app, |c| {
+            c.state = ConnectionState::Reconnecting;
+            c.attempt = attempt;
+            c.last_error = Some(last_error.clone());
+        });
 
-    log::info!("Read thread exiting");
+        // Sleep in short slices so close_port is not held up by a long backoff
+        let wake = Instant::now() + backoff.next_delay();
+        while Instant::now() < wake {
+            if !session.read_running.load(Ordering::SeqCst) {
+                return None;
+            }
+            std::thread::sleep(RECONNECT_POLL);
+        }
+
+        // Never take over a port another session is reading
+        let imu = &app.state::<AppState>().imu;
+        let mut ports = serialport::available_ports().unwrap_or_default();
+        ports.retain(|p| imu.port_owner(&p.port_name, &session.id).is_none());
+        let Some(name) = identity.find(&ports) else {
+            last_error = format!("{} not present", identity.name);
+            continue;
+        };
+
+        let opened = open_serial(&name, baud_rate)
+            .and_then(|p| p.try_clone().map(|r| (p, r)).map_err(|e| e.to_string()));
+        let (port, read_port) = match opened {
+            Ok(pair) => pair,
+            Err(e) => {
+                last_error = e;
+                continue;
+            }
+        };
+
+        let Ok(mut port_lock) = session.port.lock() else {
+            return None;
+        };
+        // close_port may have run while we were opening
+        if !session.read_running.load(Ordering::SeqCst) {
+            return None;
+        }
+        *port_lock = Some(port);
+        drop(port_lock);
+
+        if name != identity.name {
+            log::info!("Device reappeared as {} (was {})", name, identity.name);
+            identity.name = name.clone();
+        }
+        session.set_connection(app, |c| {
+            c.state = ConnectionState::Connected;
+            c.port_name = Some(name.clone());
+            c.attempt = 0;
+            c.reconnects += 1;
+        });
+        log::info!("Reconnected to {} after {} attempt(s)", name, attempt);
+        return Some(read_port);
+    }
+    None
+}
+
+fn track_link(link: &mut LinkMonitor, packets: &[HipnucPacket]) {
+    for t in packets.iter().filter_map(|p| p.system_time()) {
+        link.on_sample(t);
+    }
+}
+
+/// Record and emit decoded packets (shared by the live read thread and replay)
+fn publish_packets<R: Runtime>(app: &AppHandle<R>, session: &ImuSession, packets: &mut [HipnucPacket]) {
+    apply_calibration(session, packets);
+    let profile = session
+        .output_profile
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    for packet in packets.iter_mut() {
+        if let Some(sensors) = packet.sensors_mut() {
+            profile.apply_mounting(sensors);
+        }
+    }
+
+    // The AHRS and noise analysis work in m/s^2 and deg/s, so they run before unit conversion
+    let mut samples: Vec<Hi91Data> = packets.iter().filter_map(|p| p.imu_sample()).collect();
+    run_ahrs(app, session, &samples);
+    if let Ok(mut noise) = session.noise.lock() {
+        if let Some(noise) = noise.as_mut() {
+            samples.iter().for_each(|s| noise.push(s));
+        }
+    }
+    for sample in &mut samples {
+        profile.finish_sample(sample);
+    }
+    for packet in packets.iter_mut() {
+        if let Some(sensors) = packet.sensors_mut() {
+            profile.convert_units(sensors);
+        }
+    }
+
+    if !samples.is_empty() {
+        record_packets(&session.recorder, &samples);
+    }
+    // Every decoded sub-packet, tagged by type
+    for packet in packets.iter() {
+        session.emit(app, "imu-packet", packet);
+    }
+    // HI91/HI92 IMU samples in the flat shape the dashboard expects (plus session_id)
+    for sample in &samples {
+        session.emit(app, "imu-data", sample);
+    }
+}
+
+/// Hand raw samples to a running calibration procedure, then correct the
+/// packets with the session's active calibration
+fn apply_calibration(session: &ImuSession, packets: &mut [HipnucPacket]) {
+    let Ok(mut cal) = session.calibration.lock() else {
+        return;
+    };
+    if let Some(collector) = cal.collector.as_mut() {
+        for sample in packets.iter().filter_map(|p| p.imu_sample()) {
+            collector.push(&sample);
+        }
+    }
+    if cal.active.is_identity() {
+        return;
+    }
+    for packet in packets.iter_mut() {
+        if let Some(sensors) = packet.sensors_mut() {
+            cal.active.apply(sensors);
+        }
+    }
+}
+
+/// Feed samples to the session's host-side filter and emit "imu-ahrs"
+fn run_ahrs<R: Runtime>(app: &AppHandle<R>, session: &ImuSession, samples: &[Hi91Data]) {
+    let Ok(mut ahrs) = session.ahrs.lock() else {
+        return;
+    };
+    let Some(ahrs) = ahrs.as_mut() else {
+        return;
+    };
+    for sample in samples {
+        if let Some(out) = ahrs.update(sample) {
+            session.emit(app, "imu-ahrs", &out);
+        }
+    }
+}
+
+/// Append decoded packets to the active recording, if any
+fn record_packets(recorder: &std::sync::Mutex<Option<CsvRecorder>>, packets: &[Hi91Data]) {
+    let Ok(mut rec) = recorder.lock() else {
+        return;
+    };
+    if let Some(rec) = rec.as_mut() {
+        let host_time_ms = recorder::now_ms();
+        for packet in packets {
+            rec.write_row(host_time_ms, packet);
+        }
+    }
+}
+
+/// Try each candidate baud rate on `port_name` and report which one yields
+/// valid HiPNUC frames. The port must not be open in this app.
+#[tauri::command(async)]
+pub fn imu_detect_baud(
+    state: tauri::State<'_, AppState>,
+    port_name: String,
+    baud_rates: Option<Vec<u32>>,
+    sample_ms: Option<u64>,
+) -> Result<BaudDetection, String> {
+    if let Some(owner) = state.imu.port_owner(&port_name, "") {
+        return Err(format!(
+            "Close {} (IMU session '{}') before detecting its baud rate",
+            port_name, owner
+        ));
+    }
+
+    let candidates = baud_rates.unwrap_or_else(|| baud_detect::DEFAULT_CANDIDATES.to_vec());
+    if candidates.is_empty() {
+        return Err("No candidate baud rates".to_string());
+    }
+    let sample = Duration::from_millis(sample_ms.unwrap_or(BAUD_DETECT_SAMPLE_MS));
+
+    let mut probes = Vec::with_capacity(candidates.len());
+    for baud_rate in candidates {
+        let probe = probe_baud(&port_name, baud_rate, sample);
+        log::info!(
+            "Baud probe {} @ {}: {} bytes, {} frames ok, {} CRC failures",
+            port_name,
+            baud_rate,
+            probe.bytes,
+            probe.frames_ok,
+            probe.crc_failures
+        );
+        let conclusive = probe.frames_ok >= baud_detect::MIN_VALID_FRAMES;
+        probes.push(probe);
+        if conclusive {
+            break;
+        }
+    }
+
+    let best = baud_detect::pick_baud(&probes);
+    Ok(BaudDetection {
+        port_name,
+        baud_rate: best.map(|p| p.baud_rate),
+        packet_types: best.map(|p| p.packet_types.clone()).unwrap_or_default(),
+        probes,
+    })
+}
+
+/// Listen on the port at one rate for up to `sample`
+fn probe_baud(port_name: &str, baud_rate: u32, sample: Duration) -> baud_detect::BaudProbe {
+    let mut probe = ProbeDecoder::new(baud_rate);
+    let mut port = match open_serial(port_name, baud_rate) {
+        Ok(p) => p,
+        Err(e) => return probe.finish(Some(e)),
+    };
+    // Drop whatever was buffered before the rate changed
+    let _ = port.clear(serialport::ClearBuffer::Input);
+
+    let mut buf = [0u8; 256];
+    let deadline = Instant::now() + sample;
+    while Instant::now() < deadline && !probe.is_conclusive() {
+        match port.read(&mut buf) {
+            Ok(n) => probe.feed(&buf[..n]),
+            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return probe.finish(Some(e.to_string())),
+        }
+    }
+    probe.finish(None)
 }
 
 /// Close serial port
 #[tauri::command]
-pub fn close_port(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    state.read_running.store(false, Ordering::SeqCst);
-    // Brief pause to let read thread exit
-    std::thread::sleep(Duration::from_millis(150));
+pub fn close_port<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    stop_session_port(&app, &session)?;
+
+    log::info!("Port closed (session '{}')", session.id);
+    Ok(())
+}
+
+fn stop_session_port<R: Runtime>(app: &AppHandle<R>, session: &ImuSession) -> Result<(), String> {
+    let mut source = session.source_thread.lock().map_err(|e| e.to_string())?;
+    session.read_running.store(false, Ordering::SeqCst);
+    // The reader holds a clone of the port and may still report Reconnecting;
+    // a replay or virtual IMU in the slot keeps running
+    if !session.replay_running.load(Ordering::SeqCst) && !session.virtual_running.load(Ordering::SeqCst) {
+        join_source_thread(&mut source);
+    }
+    drop(source);
 
-    let mut port_lock = state.port.lock().map_err(|e| e.to_string())?;
+    let mut port_lock = session.port.lock().map_err(|e| e.to_string())?;
     *port_lock = None;
+    drop(port_lock);
 
-    log::info!("Port closed");
+    session.set_connection(app, |c| {
+        c.state = ConnectionState::Closed;
+        c.attempt = 0;
+    });
     Ok(())
 }
 
-/// Send a command string to the device (appends \r\n)
+/// Current connection state of an IMU session (Closed if it does not exist)
 #[tauri::command]
-pub fn send_command(
+pub fn serial_connection_status(
     state: tauri::State<'_, AppState>,
-    command: String,
+    session_id: Option<String>,
+) -> Result<ConnectionStatus, String> {
+    let Ok(session) = state.imu.get(session_id.as_deref()) else {
+        return Ok(ConnectionStatus::default());
+    };
+    let c = session.connection.lock().map_err(|e| e.to_string())?;
+    Ok(c.clone())
+}
+
+/// Every IMU session with its port, connection state and activity
+#[tauri::command]
+pub fn imu_list_sessions(state: tauri::State<'_, AppState>) -> Result<Vec<SessionInfo>, String> {
+    Ok(state.imu.list().iter().map(|s| s.info()).collect())
+}
+
+/// Close a session's port, finish its recording and capture, and forget it
+#[tauri::command]
+pub fn imu_remove_session<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    session_id: String,
 ) -> Result<(), String> {
-    let mut port_lock = state.port.lock().map_err(|e| e.to_string())?;
+    let session = state.imu.remove(Some(&session_id))?;
+    session.replay_running.store(false, Ordering::SeqCst);
+    session.virtual_running.store(false, Ordering::SeqCst);
+    stop_session_port(&app, &session)?;
+
+    if let Some(rec) = session.recorder.lock().map_err(|e| e.to_string())?.take() {
+        if let Err(e) = rec.finish() {
+            log::warn!("Recording of session '{}' did not flush cleanly: {}", session.id, e);
+        }
+    }
+    if let Some(cap) = session.capture.lock().map_err(|e| e.to_string())?.take() {
+        if let Err(e) = cap.finish() {
+            log::warn!("Capture of session '{}' did not flush cleanly: {}", session.id, e);
+        }
+    }
+
+    log::info!("IMU session '{}' removed", session.id);
+    Ok(())
+}
+
+/// Write one command line to the open port (appends \r\n)
+fn write_command(session: &ImuSession, command: &str) -> Result<(), String> {
+    let mut port_lock = session.port.lock().map_err(|e| e.to_string())?;
     let port = port_lock
         .as_mut()
         .ok_or("Port not open")?;
@@ -171,40 +635,781 @@ pub fn send_command(
     Ok(())
 }
 
-/// Start recording data to CSV
+/// Send a command string to the device (appends \r\n)
+#[tauri::command]
+pub fn send_command(
+    state: tauri::State<'_, AppState>,
+    command: String,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    write_command(&session, &command)
+}
+
+/// Send a command and block until its reply is complete or the timeout expires.
+/// The read thread keeps decoding binary frames meanwhile and feeds the text
+/// between them into the session's `at_lines`.
+fn at_transaction(
+    session: &ImuSession,
+    command: &str,
+    expect: AtExpect,
+    timeout: Duration,
+) -> Result<AtResponse, String> {
+    if !session.read_running.load(Ordering::SeqCst) {
+        return Err("Port not open".to_string());
+    }
+
+    session
+        .at_lines
+        .lock()
+        .map_err(|e| e.to_string())?
+        .begin(command, expect, Instant::now())?;
+
+    if let Err(e) = write_command(session, command) {
+        if let Ok(mut at) = session.at_lines.lock() {
+            at.cancel();
+        }
+        return Err(e);
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        std::thread::sleep(Duration::from_millis(10));
+        let mut at = session.at_lines.lock().map_err(|e| e.to_string())?;
+        if let Some(resp) = at.poll(Instant::now()) {
+            log::info!("AT '{}' -> ok={} ({} ms)", command, resp.ok, resp.elapsed_ms);
+            return Ok(resp);
+        }
+        if Instant::now() >= deadline {
+            at.cancel();
+            return Err(format!(
+                "No reply to '{}' within {} ms",
+                command,
+                timeout.as_millis()
+            ));
+        }
+    }
+}
+
+fn at_timeout(timeout_ms: Option<u64>) -> Duration {
+    Duration::from_millis(timeout_ms.unwrap_or(AT_DEFAULT_TIMEOUT_MS))
+}
+
+/// Send an arbitrary command and wait for its reply.
+/// `expect_text`: the command prints text with no OK/ERR terminator (e.g. INFO).
+#[tauri::command(async)]
+pub fn imu_at_command(
+    state: tauri::State<'_, AppState>,
+    command: String,
+    expect_text: Option<bool>,
+    timeout_ms: Option<u64>,
+    session_id: Option<String>,
+) -> Result<AtResponse, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let expect = if expect_text.unwrap_or(false) {
+        AtExpect::Text { idle: AT_TEXT_IDLE }
+    } else {
+        AtExpect::Ack
+    };
+    at_transaction(&session, command.trim(), expect, at_timeout(timeout_ms))
+}
+
+/// Set the output rate of one packet (default HI91): `LOG <packet> ONTIME <1/rate>`
+#[tauri::command(async)]
+pub fn imu_set_output_rate(
+    state: tauri::State<'_, AppState>,
+    rate_hz: f64,
+    packet: Option<String>,
+    session_id: Option<String>,
+) -> Result<AtResponse, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let cmd = imu_at::cmd_log_ontime(packet.as_deref().unwrap_or("HI91"), rate_hz)?;
+    at_transaction(&session, &cmd, AtExpect::Ack, at_timeout(None))
+}
+
+/// Replace the output packet selection: UNLOG, then schedule each packet at `rate_hz`.
+/// Stops at the first rejected command; the returned list shows how far it got.
+#[tauri::command(async)]
+pub fn imu_set_output_packets(
+    state: tauri::State<'_, AppState>,
+    packets: Vec<String>,
+    rate_hz: f64,
+    session_id: Option<String>,
+) -> Result<Vec<AtResponse>, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    if packets.is_empty() {
+        return Err("Select at least one output packet".to_string());
+    }
+    // Validate everything before touching the device
+    let log_cmds = packets
+        .iter()
+        .map(|p| imu_at::cmd_log_ontime(p, rate_hz))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut responses = Vec::with_capacity(log_cmds.len() + 1);
+    for cmd in std::iter::once(imu_at::cmd_unlog()).chain(log_cmds) {
+        let resp = at_transaction(&session, &cmd, AtExpect::Ack, at_timeout(None))?;
+        let ok = resp.ok;
+        responses.push(resp);
+        if !ok {
+            break;
+        }
+    }
+    Ok(responses)
+}
+
+/// Change the device baud rate. The device switches after replying, so the
+/// port must be reopened at the new rate afterwards.
+#[tauri::command(async)]
+pub fn imu_set_baud_rate(
+    state: tauri::State<'_, AppState>,
+    baud_rate: u32,
+    session_id: Option<String>,
+) -> Result<AtResponse, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let cmd = imu_at::cmd_serial_config(baud_rate)?;
+    at_transaction(&session, &cmd, AtExpect::Ack, at_timeout(None))
+}
+
+/// Persist the current configuration to device flash
+#[tauri::command(async)]
+pub fn imu_save_config(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<AtResponse, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    at_transaction(&session, &imu_at::cmd_save_config(), AtExpect::Ack, at_timeout(None))
+}
+
+/// Reboot the device
+#[tauri::command(async)]
+pub fn imu_reboot(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<AtResponse, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    at_transaction(&session, &imu_at::cmd_reboot(), AtExpect::Ack, at_timeout(None))
+}
+
+/// Query INFO and parse model / serial number / firmware
+#[tauri::command(async)]
+pub fn imu_query_info(
+    state: tauri::State<'_, AppState>,
+    timeout_ms: Option<u64>,
+    session_id: Option<String>,
+) -> Result<ImuDeviceInfo, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let resp = at_transaction(
+        &session,
+        &imu_at::cmd_info(),
+        AtExpect::Text { idle: AT_TEXT_IDLE },
+        at_timeout(timeout_ms),
+    )?;
+    if let Some(err) = resp.error {
+        return Err(format!("INFO rejected: {}", err));
+    }
+    Ok(imu_at::parse_info(&resp.lines))
+}
+
+/// Latest link health snapshot of the open port (None before the first one)
+#[tauri::command]
+pub fn imu_link_stats(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<Option<LinkStats>, String> {
+    let Ok(session) = state.imu.get(session_id.as_deref()) else {
+        return Ok(None);
+    };
+    let ls = session.link_stats.lock().map_err(|e| e.to_string())?;
+    Ok(ls.clone())
+}
+
+/// Start the host-side attitude filter; "imu-ahrs" is emitted per sample
+/// next to the device attitude. Restarting re-initialises from acc/mag.
+#[tauri::command]
+pub fn imu_ahrs_start(
+    state: tauri::State<'_, AppState>,
+    config: Option<AhrsConfig>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let config = config.unwrap_or_default();
+    log::info!("AHRS started (session '{}'): {:?}", session.id, config);
+    *session.ahrs.lock().map_err(|e| e.to_string())? = Some(Ahrs::new(config));
+    Ok(())
+}
+
+/// Change algorithm or gains of the running filter, keeping its attitude
+#[tauri::command]
+pub fn imu_ahrs_configure(
+    state: tauri::State<'_, AppState>,
+    config: AhrsConfig,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut ahrs = session.ahrs.lock().map_err(|e| e.to_string())?;
+    ahrs.as_mut().ok_or("AHRS not running")?.set_config(config);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn imu_ahrs_stop(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    session.ahrs.lock().map_err(|e| e.to_string())?.take();
+    Ok(())
+}
+
+/// Start a static noise run (keep the IMU still); samples accumulate until
+/// `imu_noise_stop`. Longer runs resolve longer averaging times: an hour or
+/// more for bias instability.
+#[tauri::command]
+pub fn imu_noise_start(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut noise = session.noise.lock().map_err(|e| e.to_string())?;
+    if noise.is_some() {
+        return Err("Noise run already in progress".to_string());
+    }
+    *noise = Some(NoiseCollector::new());
+    log::info!("Noise run started (session '{}')", session.id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn imu_noise_status(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<Option<NoiseProgress>, String> {
+    let Ok(session) = state.imu.get(session_id.as_deref()) else {
+        return Ok(None);
+    };
+    let noise = session.noise.lock().map_err(|e| e.to_string())?;
+    Ok(noise.as_ref().map(NoiseCollector::progress))
+}
+
+/// End the noise run and compute statistics and Allan deviation curves
+#[tauri::command(async)]
+pub fn imu_noise_stop(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<NoiseAnalysis, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let collector = session
+        .noise
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take()
+        .ok_or("No noise run in progress")?;
+    let analysis = collector.analyse()?;
+    log::info!(
+        "Noise run analysed (session '{}'): {} samples over {:.0} s",
+        session.id,
+        analysis.samples,
+        analysis.duration_s
+    );
+    Ok(analysis)
+}
+
+/// Set units, mounting rotation and derived fields for the session's data
+#[tauri::command]
+pub fn imu_set_output_profile(
+    state: tauri::State<'_, AppState>,
+    profile: OutputProfile,
+    session_id: Option<String>,
+) -> Result<OutputProfile, String> {
+    let profile = profile.validated()?;
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    *session.output_profile.lock().map_err(|e| e.to_string())? = profile.clone();
+    log::info!("Output profile (session '{}'): {:?}", session.id, profile);
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn imu_get_output_profile(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<OutputProfile, String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let profile = session.output_profile.lock().map_err(|e| e.to_string())?;
+    Ok(profile.clone())
+}
+
+/// Collect raw samples from the live (or replayed) stream for `duration`
+fn collect_calibration_samples(
+    session: &ImuSession,
+    duration: Duration,
+) -> Result<Vec<CalibSample>, String> {
+    let streaming = || {
+        session.read_running.load(Ordering::SeqCst)
+            || session.replay_running.load(Ordering::SeqCst)
+            || session.virtual_running.load(Ordering::SeqCst)
+    };
+    if !streaming() {
+        return Err("Port not open".to_string());
+    }
+    {
+        let mut cal = session.calibration.lock().map_err(|e| e.to_string())?;
+        if cal.collector.is_some() {
+            return Err("A calibration collection is already running".to_string());
+        }
+        cal.collector = Some(SampleCollector::new());
+    }
+
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline && streaming() {
+        std::thread::sleep(CALIB_POLL);
+    }
+    let collector = session
+        .calibration
+        .lock()
+        .map_err(|e| e.to_string())?
+        .collector
+        .take();
+    if !streaming() {
+        return Err("Stream stopped during calibration".to_string());
+    }
+    Ok(collector.map(SampleCollector::into_samples).unwrap_or_default())
+}
+
+fn calib_duration(duration_ms: Option<u64>, default_ms: u64) -> Duration {
+    Duration::from_millis(duration_ms.unwrap_or(default_ms))
+}
+
+/// Static gyro bias: keep the IMU still for the whole collection.
+/// The bias is applied to subsequent packets.
+#[tauri::command(async)]
+pub fn imu_calib_gyro(
+    state: tauri::State<'_, AppState>,
+    duration_ms: Option<u64>,
+    session_id: Option<String>,
+) -> Result<GyroCalibResult, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let samples = collect_calibration_samples(&session, calib_duration(duration_ms, CALIB_GYRO_MS))?;
+    let result = calibration::solve_gyro_bias(&samples)?;
+
+    session.calibration.lock().map_err(|e| e.to_string())?.active.gyro_bias = result.bias;
+    log::info!("Gyro bias calibrated (session '{}'): {:?}", session.id, result.bias);
+    Ok(result)
+}
+
+/// Collect one face of the six-face accelerometer calibration: the IMU rests
+/// with one axis pointing straight up or down. Faces may be taken in any order.
+#[tauri::command(async)]
+pub fn imu_calib_accel_face(
+    state: tauri::State<'_, AppState>,
+    duration_ms: Option<u64>,
+    session_id: Option<String>,
+) -> Result<AccelFaceResult, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let samples = collect_calibration_samples(&session, calib_duration(duration_ms, CALIB_ACCEL_FACE_MS))?;
+    let mut cal = session.calibration.lock().map_err(|e| e.to_string())?;
+    calibration::add_accel_face(&mut cal.accel_faces, &samples)
+}
+
+/// Compute and apply the accelerometer calibration from the six collected faces
+#[tauri::command]
+pub fn imu_calib_accel_solve(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<AccelCalibResult, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut cal = session.calibration.lock().map_err(|e| e.to_string())?;
+    let result = calibration::solve_accel(&cal.accel_faces)?;
+
+    cal.active.acc_offset = result.offset;
+    cal.active.acc_scale = result.scale;
+    cal.accel_faces = [None; 6];
+    log::info!(
+        "Accelerometer calibrated (session '{}'): residual {:.4} m/s^2",
+        session.id,
+        result.residual_rms
+    );
+    Ok(result)
+}
+
+/// Magnetometer hard/soft-iron fit: rotate the IMU slowly through as many
+/// orientations as possible during the collection. Applied on success.
+#[tauri::command(async)]
+pub fn imu_calib_mag(
+    state: tauri::State<'_, AppState>,
+    duration_ms: Option<u64>,
+    session_id: Option<String>,
+) -> Result<MagCalibResult, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let samples = collect_calibration_samples(&session, calib_duration(duration_ms, CALIB_MAG_MS))?;
+    let result = calibration::solve_mag(&samples)?;
+
+    let mut cal = session.calibration.lock().map_err(|e| e.to_string())?;
+    cal.active.mag_offset = result.offset;
+    cal.active.mag_matrix = result.matrix;
+    log::info!(
+        "Magnetometer calibrated (session '{}'): field {:.1} uT, residual {:.2}%, coverage {:.0}%",
+        session.id,
+        result.field_strength,
+        result.residual_rms * 100.0,
+        result.coverage * 100.0
+    );
+    Ok(result)
+}
+
+/// Calibration currently applied to the session's packets
+#[tauri::command]
+pub fn imu_calib_get(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<ImuCalibration, String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let cal = session.calibration.lock().map_err(|e| e.to_string())?;
+    Ok(cal.active.clone())
+}
+
+/// Load a saved calibration (omitted fields are identity)
+#[tauri::command]
+pub fn imu_calib_set(
+    state: tauri::State<'_, AppState>,
+    calibration: ImuCalibration,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    session.calibration.lock().map_err(|e| e.to_string())?.active = calibration;
+    Ok(())
+}
+
+/// Drop the active calibration and any collected accelerometer faces
+#[tauri::command]
+pub fn imu_calib_reset(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut cal = session.calibration.lock().map_err(|e| e.to_string())?;
+    cal.active = ImuCalibration::default();
+    cal.accel_faces = [None; 6];
+    Ok(())
+}
+
+/// Start recording decoded packets to CSV (replaces any active recording)
 #[tauri::command]
 pub fn start_recording(
     state: tauri::State<'_, AppState>,
     file_path: String,
+    session_id: Option<String>,
 ) -> Result<(), String> {
-    let file = std::fs::File::create(&file_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
-    let mut writer = std::io::BufWriter::new(file);
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let new_recorder = CsvRecorder::create(&file_path)?;
+
+    let mut rec = session.recorder.lock().map_err(|e| e.to_string())?;
+    if let Some(old) = rec.take() {
+        if let Err(e) = old.finish() {
+            log::warn!("Previous recording did not flush cleanly: {}", e);
+        }
+    }
+    *rec = Some(new_recorder);
+
+    log::info!("Recording started: {} (session '{}')", file_path, session.id);
+    Ok(())
+}
+
+/// Stop recording, flush the file and return the final row counts
+#[tauri::command]
+pub fn stop_recording(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<RecordingStats, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut rec = session.recorder.lock().map_err(|e| e.to_string())?;
+    let recorder = rec.take().ok_or("Not recording")?;
+    let stats = recorder.finish()?;
+
+    log::info!(
+        "Recording stopped: {} rows written, {} dropped",
+        stats.rows_written,
+        stats.rows_dropped
+    );
+    Ok(stats)
+}
 
-    // Write CSV header
-    writeln!(
-        writer,
-        "timestamp_ms,acc_x,acc_y,acc_z,gyr_x,gyr_y,gyr_z,mag_x,mag_y,mag_z,roll,pitch,yaw,qw,qx,qy,qz,temperature,air_pressure"
-    )
-    .map_err(|e| e.to_string())?;
+/// Current recording progress (None if not recording)
+#[tauri::command]
+pub fn recording_status(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<Option<RecordingStats>, String> {
+    let Ok(session) = state.imu.get(session_id.as_deref()) else {
+        return Ok(None);
+    };
+    let rec = session.recorder.lock().map_err(|e| e.to_string())?;
+    Ok(rec.as_ref().map(|r| r.stats()))
+}
 
-    let mut csv = state.csv_writer.lock().map_err(|e| e.to_string())?;
-    *csv = Some(writer);
+/// Start teeing the raw serial byte stream to a capture file (replaces any active capture)
+#[tauri::command]
+pub fn start_capture(
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let baud_rate = {
+        let port_lock = session.port.lock().map_err(|e| e.to_string())?;
+        let port = port_lock.as_ref().ok_or("Port not open")?;
+        port.baud_rate().unwrap_or(0)
+    };
+    let new_capture = CaptureWriter::create(&file_path, baud_rate)?;
 
-    let mut rec = state.recording.lock().map_err(|e| e.to_string())?;
-    *rec = true;
+    let mut cap = session.capture.lock().map_err(|e| e.to_string())?;
+    if let Some(old) = cap.take() {
+        if let Err(e) = old.finish() {
+            log::warn!("Previous capture did not flush cleanly: {}", e);
+        }
+    }
+    *cap = Some(new_capture);
 
+    log::info!("Raw capture started: {} (session '{}')", file_path, session.id);
+    Ok(())
+}
+
+/// Stop the raw capture, flush the file and return its size
+#[tauri::command]
+pub fn stop_capture(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<CaptureStats, String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    let mut cap = session.capture.lock().map_err(|e| e.to_string())?;
+    let capture = cap.take().ok_or("Not capturing")?;
+    let stats = capture.finish()?;
+
+    log::info!("Raw capture stopped: {} chunks, {} bytes", stats.chunks, stats.bytes);
+    Ok(stats)
+}
+
+/// Summary emitted as "imu-replay-finished" when a replay ends
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayStats {
+    pub file_path: String,
+    pub chunks: u64,
+    pub bytes: u64,
+    pub packets: u64,
+    /// False when stopped early or aborted by a read error
+    pub completed: bool,
+    pub error: Option<String>,
+}
+
+/// Replay a raw capture through the decoder, emitting the same events as a live port.
+/// `speed` scales the original timing (2.0 = twice as fast); 0 replays as fast as possible.
+/// Events carry the given session id, which must not have a live port open.
+#[tauri::command]
+pub fn replay_capture<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+    speed: Option<f64>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let mut source = session.source_thread.lock().map_err(|e| e.to_string())?;
+    if session.read_running.load(Ordering::SeqCst) {
+        return Err("Close the serial port before replaying a capture".to_string());
+    }
+    if session.replay_running.load(Ordering::SeqCst) {
+        return Err("Replay already running".to_string());
+    }
+    if session.virtual_running.load(Ordering::SeqCst) {
+        return Err("Stop the virtual IMU before replaying a capture".to_string());
+    }
+    let speed = speed.unwrap_or(1.0);
+    if !speed.is_finite() || speed < 0.0 {
+        return Err(format!("Invalid replay speed {}", speed));
+    }
+
+    let reader = CaptureReader::open(&file_path)?;
+    log::info!(
+        "Replaying {} (captured at {} baud) at {}x",
+        file_path,
+        reader.header().baud_rate,
+        speed
+    );
+
+    // A replay that was just stopped may still be winding down
+    join_source_thread(&mut source);
+    claim_source(&session.replay_running, "Replay already running")?;
+    let thread_session = Arc::clone(&session);
+    *source = Some(std::thread::spawn(move || {
+        replay_thread(reader, file_path, speed, thread_session, app);
+    }));
+    Ok(())
+}
+
+/// Stop a running replay
+#[tauri::command]
+pub fn stop_replay(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    session.replay_running.store(false, Ordering::SeqCst);
+    log::info!("Replay stop requested");
     Ok(())
 }
 
-/// Stop recording
+/// Background thread that feeds capture chunks to a fresh decoder at the recorded pace
+fn replay_thread<T: Read, R: Runtime>(
+    mut reader: CaptureReader<T>,
+    file_path: String,
+    speed: f64,
+    session: Arc<ImuSession>,
+    app: AppHandle<R>,
+) {
+    let running = &session.replay_running;
+    let mut decoder = HipnucDecoder::new();
+    let started = Instant::now();
+    let mut link = LinkMonitor::new(started);
+    let mut last_stats_emit = started;
+    let mut stats = ReplayStats {
+        file_path,
+        chunks: 0,
+        bytes: 0,
+        packets: 0,
+        completed: false,
+        error: None,
+    };
+
+    while running.load(Ordering::SeqCst) {
+        let chunk = match reader.next_chunk() {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => {
+                stats.completed = true;
+                break;
+            }
+            Err(e) => {
+                log::error!("Replay read error: {}", e);
+                stats.error = Some(e);
+                break;
+            }
+        };
+
+        if speed > 0.0 {
+            let due = Duration::from_secs_f64(chunk.t_us as f64 / 1e6 / speed);
+            loop {
+                let elapsed = started.elapsed();
+                if elapsed >= due || !running.load(Ordering::SeqCst) {
+                    break;
+                }
+                std::thread::sleep((due - elapsed).min(REPLAY_MAX_SLEEP));
+            }
+        }
+
+        let mut packets = decoder.input_bytes(&chunk.data);
+        link.on_bytes(chunk.data.len());
+        track_link(&mut link, &packets);
+        publish_packets(&app, &session, &mut packets);
+        stats.chunks += 1;
+        stats.bytes += chunk.data.len() as u64;
+        stats.packets += packets.len() as u64;
+
+        if last_stats_emit.elapsed() >= STATS_INTERVAL {
+            last_stats_emit = Instant::now();
+            session.emit(&app, "imu-link-stats", &link.snapshot(last_stats_emit, decoder.stats()));
+        }
+    }
+
+    running.store(false, Ordering::SeqCst);
+    log::info!(
+        "Replay finished: {} chunks, {} packets, completed={}",
+        stats.chunks,
+        stats.packets,
+        stats.completed
+    );
+    session.emit(&app, "imu-replay-finished", &stats);
+}
+
+/// Start a virtual IMU on the session: synthetic HI91 frames for a motion
+/// profile, fed through the decoder and the same events as a live port
 #[tauri::command]
-pub fn stop_recording(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut rec = state.recording.lock().map_err(|e| e.to_string())?;
-    *rec = false;
+pub fn imu_virtual_start<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    config: Option<VirtualImuConfig>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get_or_create(session_id.as_deref())?;
+    let mut source = session.source_thread.lock().map_err(|e| e.to_string())?;
+    if session.read_running.load(Ordering::SeqCst) {
+        return Err("Close the serial port before starting a virtual IMU".to_string());
+    }
+    if session.replay_running.load(Ordering::SeqCst) {
+        return Err("Stop the replay before starting a virtual IMU".to_string());
+    }
+    if session.virtual_running.load(Ordering::SeqCst) {
+        return Err("Virtual IMU already running".to_string());
+    }
+    let config = config.unwrap_or_default();
+    let imu = VirtualImu::new(config.clone())?;
+    log::info!("Virtual IMU started (session '{}'): {:?}", session.id, config);
 
-    let mut csv = state.csv_writer.lock().map_err(|e| e.to_string())?;
-    *csv = None;
+    join_source_thread(&mut source);
+    claim_source(&session.virtual_running, "Virtual IMU already running")?;
+    let thread_session = Arc::clone(&session);
+    *source = Some(std::thread::spawn(move || {
+        virtual_thread(imu, thread_session, app);
+    }));
+    Ok(())
+}
 
+#[tauri::command]
+pub fn imu_virtual_stop(
+    state: tauri::State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    let session = state.imu.get(session_id.as_deref())?;
+    session.virtual_running.store(false, Ordering::SeqCst);
+    log::info!("Virtual IMU stop requested");
     Ok(())
 }
+
+/// Background thread that encodes the virtual IMU's samples in real time and
+/// handles them like bytes read from a port (capture tee included)
+fn virtual_thread<R: Runtime>(mut imu: VirtualImu, session: Arc<ImuSession>, app: AppHandle<R>) {
+    let running = &session.virtual_running;
+    let mut decoder = HipnucDecoder::new();
+    let started = Instant::now();
+    let mut link = LinkMonitor::new(started);
+    let mut last_stats_emit = started;
+    let mut sent: u64 = 0;
+
+    while running.load(Ordering::SeqCst) {
+        let due = (started.elapsed().as_secs_f64() * imu.rate_hz()) as u64 + 1;
+        let mut chunk = Vec::new();
+        while sent < due {
+            chunk.extend_from_slice(&imu.next_frame());
+            sent += 1;
+        }
+
+        if !chunk.is_empty() {
+            if let Ok(mut cap) = session.capture.lock() {
+                if let Some(cap) = cap.as_mut() {
+                    cap.write_chunk(&chunk);
+                }
+            }
+            let mut packets = decoder.input_bytes(&chunk);
+            link.on_bytes(chunk.len());
+            track_link(&mut link, &packets);
+            publish_packets(&app, &session, &mut packets);
+        }
+
+        if last_stats_emit.elapsed() >= STATS_INTERVAL {
+            last_stats_emit = Instant::now();
+            session.emit(&app, "imu-link-stats", &link.snapshot(last_stats_emit, decoder.stats()));
+        }
+        std::thread::sleep(VIRTUAL_TICK);
+    }
+
+    running.store(false, Ordering::SeqCst);
+    log::info!("Virtual IMU stopped after {} samples (session '{}')", sent, session.id);
+}
@@ -1,83 +1,113 @@
-use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 
-use crate::protocol::HipnucDecoder;
+use crate::can_transport::CanTransport;
+use crate::canopen::CanopenState;
+use crate::imu_session::ImuSessions;
+use crate::param_staging::ParamStaging;
+use crate::pending::PendingRequests;
 use crate::udp::UdpConfig;
 
-/// MIT high-frequency loop parameters (shared between command handler and loop thread)
-#[derive(Debug, Clone)]
-pub struct MitLoopConfig {
-    pub motor_id: u8,
+/// Impedance setpoint shared by both high-rate control loops
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImpedanceTarget {
     pub position: f32,
     pub velocity: f32,
     pub kp: f32,
     pub kd: f32,
     pub torque: f32,
+}
+
+/// MIT loop parameters (shared between command handler and loop thread)
+#[derive(Debug, Clone)]
+pub struct MitLoopConfig {
+    pub motor_id: u8,
+    pub target: ImpedanceTarget,
     pub freq_hz: u32,
 }
 
 impl Default for MitLoopConfig {
     fn default() -> Self {
-        Self {
-            motor_id: 127,
-            position: 0.0,
-            velocity: 0.0,
-            kp: 0.0,
-            kd: 0.0,
-            torque: 0.0,
-            freq_hz: 200,
-        }
+        Self { motor_id: 127, target: ImpedanceTarget::default(), freq_hz: 200 }
+    }
+}
+
+/// Private protocol type 1 loop parameters
+#[derive(Debug, Clone)]
+pub struct PrivLoopConfig {
+    pub motor_id: u8,
+    /// Host ID for the type 4 stop sent when the loop exits
+    pub master_id: u8,
+    pub target: ImpedanceTarget,
+    pub freq_hz: u32,
+}
+
+impl Default for PrivLoopConfig {
+    fn default() -> Self {
+        Self { motor_id: 127, master_id: 0xFD, target: ImpedanceTarget::default(), freq_hz: 200 }
     }
 }
 
 /// Shared application state accessible from Tauri commands
 pub struct AppState {
     // ── Serial / IMU ──
-    /// Currently open serial port (None if disconnected)
-    pub port: Mutex<Option<Box<dyn serialport::SerialPort>>>,
-    /// Flag to signal the serial read thread to stop
-    pub read_running: Arc<AtomicBool>,
-    /// Protocol decoder instance
-    pub decoder: Mutex<HipnucDecoder>,
-    /// Whether currently recording data
-    pub recording: Mutex<bool>,
-    /// CSV writer for recording
-    pub csv_writer: Mutex<Option<std::io::BufWriter<std::fs::File>>>,
+    /// IMU sessions keyed by id, each with its own port, read thread and recording
+    pub imu: ImuSessions,
 
     // ── UDP / Motor ──
-    /// UDP socket for CAN-ETH gateway
-    pub udp_socket: Mutex<Option<UdpSocket>>,
-    /// Flag to signal the UDP recv thread to stop
+    /// Connected CAN transport (Waveshare UDP gateway or SocketCAN)
+    pub can_transport: Mutex<Option<Arc<dyn CanTransport>>>,
+    /// Flag to signal the CAN recv thread to stop
     pub udp_running: Arc<AtomicBool>,
+    /// CAN recv thread, joined before the transport is replaced
+    pub can_recv_thread: Mutex<Option<JoinHandle<()>>>,
     /// Current UDP connection config
     pub udp_config: Mutex<UdpConfig>,
     /// Flag indicating MIT scan is in progress (recv thread emits scan results for MIT feedback)
     pub mit_scanning: Arc<AtomicBool>,
+    /// Private protocol requests waiting for their reply from the recv thread
+    pub pending: Arc<PendingRequests>,
+    /// Parameter writes not yet saved to flash, per motor
+    pub param_staging: ParamStaging,
 
     // ── MIT high-frequency loop ──
     /// Flag to signal the MIT loop thread to stop
     pub mit_loop_running: Arc<AtomicBool>,
     /// Shared MIT loop parameters (updated from frontend sliders)
     pub mit_loop_params: Arc<Mutex<MitLoopConfig>>,
+
+    // ── Private protocol motion-control loop ──
+    /// Flag to signal the private protocol loop thread to stop
+    pub priv_loop_running: Arc<AtomicBool>,
+    /// Shared private protocol loop parameters
+    pub priv_loop_params: Arc<Mutex<PrivLoopConfig>>,
+
+    // ── CANopen ──
+    /// SDO transfer and heartbeat state for motors in CANopen mode
+    pub canopen: Arc<CanopenState>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            port: Mutex::new(None),
-            read_running: Arc::new(AtomicBool::new(false)),
-            decoder: Mutex::new(HipnucDecoder::new()),
-            recording: Mutex::new(false),
-            csv_writer: Mutex::new(None),
+            imu: ImuSessions::new(),
 
-            udp_socket: Mutex::new(None),
+            can_transport: Mutex::new(None),
             udp_running: Arc::new(AtomicBool::new(false)),
+            can_recv_thread: Mutex::new(None),
             udp_config: Mutex::new(UdpConfig::default()),
             mit_scanning: Arc::new(AtomicBool::new(false)),
+            pending: Arc::new(PendingRequests::new()),
+            param_staging: ParamStaging::new(),
 
             mit_loop_running: Arc::new(AtomicBool::new(false)),
             mit_loop_params: Arc::new(Mutex::new(MitLoopConfig::default())),
+
+            priv_loop_running: Arc::new(AtomicBool::new(false)),
+            priv_loop_params: Arc::new(Mutex::new(PrivLoopConfig::default())),
+
+            canopen: Arc::new(CanopenState::new()),
         }
     }
 }
@@ -1,13 +1,17 @@
-use std::net::UdpSocket;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Runtime};
 
+use crate::can_transport::{CanFrame, CanTransport, Slcan, WaveshareTcp, WaveshareUdp};
+use crate::canopen::{self, CanopenState, Cia402Status, Cia402Step, CobKind, SdoStep, SdoTransfer, SdoWaiter};
 use crate::motor_protocol::{self, CAN_FRAME_SIZE};
-use crate::state::AppState;
+use crate::param_profile::{self, ParamDiff, ParamProfile, ProfileEntry};
+use crate::param_staging::StagedParam;
+use crate::pending::{PendingRequests, PrivResponse, ResponseKey};
+use crate::state::{AppState, ImpedanceTarget};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UdpConfig {
@@ -47,75 +51,197 @@ fn now_ms() -> u64 {
         .as_millis() as u64
 }
 
+/// Linux SocketCAN connection (USB-CAN adapter or vcan)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocketCanConfig {
+    /// Interface name, e.g. "can0" or "vcan0"
+    pub interface: String,
+    pub motor_id: u8,
+    pub master_id: u8,
+}
+
+/// Waveshare gateway in TCP mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpConfig {
+    /// Gateway address in TCP server mode
+    pub gateway_ip: String,
+    pub gateway_port: u16,
+    /// Gateway in TCP client mode: listen on this port instead of connecting
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+    pub motor_id: u8,
+    pub master_id: u8,
+}
+
+/// USB-CAN dongle speaking SLCAN on a serial port
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlcanConfig {
+    pub port_name: String,
+    /// Serial baud rate (ignored by USB CDC adapters)
+    #[serde(default = "default_slcan_baud")]
+    pub baud_rate: u32,
+    /// CAN bitrate (bit/s)
+    #[serde(default = "default_can_bitrate")]
+    pub bitrate: u32,
+    pub motor_id: u8,
+    pub master_id: u8,
+}
+
+fn default_slcan_baud() -> u32 {
+    115200
+}
+
+/// RobStride motors ship at 1 Mbit/s
+fn default_can_bitrate() -> u32 {
+    1_000_000
+}
+
 /// Connect to CAN-ETH gateway via UDP
 #[tauri::command]
-pub fn udp_connect(
-    app: AppHandle,
+pub fn udp_connect<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     config: UdpConfig,
 ) -> Result<(), String> {
-    // Close existing connection
+    close_transport(&state)?;
+
+    let remote_addr = format!("{}:{}", config.gateway_ip, config.gateway_port);
+    let transport = WaveshareUdp::connect(config.local_port, &remote_addr)?;
     {
-        state.udp_running.store(false, Ordering::SeqCst);
-        let mut sock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-        *sock = None;
-        std::thread::sleep(Duration::from_millis(50));
+        let mut cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
+        *cfg = config.clone();
     }
+    start_transport(app, &state, Arc::new(transport), config.master_id)?;
 
-    // Bind local socket
-    let bind_addr = format!("0.0.0.0:{}", config.local_port);
-    let socket = UdpSocket::bind(&bind_addr)
-        .map_err(|e| format!("Failed to bind UDP socket on {}: {}", bind_addr, e))?;
+    log::info!("UDP connected to {}", remote_addr);
+    Ok(())
+}
 
-    socket
-        .set_read_timeout(Some(Duration::from_millis(100)))
-        .map_err(|e| e.to_string())?;
+/// Connect to the CAN-ETH gateway over TCP, for networks where UDP is
+/// filtered. The link is re-established automatically if it drops.
+#[tauri::command]
+pub fn tcp_connect<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    config: TcpConfig,
+) -> Result<(), String> {
+    close_transport(&state)?;
 
-    let remote_addr = format!("{}:{}", config.gateway_ip, config.gateway_port);
+    let transport = match config.listen_port {
+        Some(port) => WaveshareTcp::listen(port)?,
+        None => WaveshareTcp::connect(&format!("{}:{}", config.gateway_ip, config.gateway_port))?,
+    };
+    let endpoint = transport.describe();
+    set_motor_ids(&state, config.motor_id, config.master_id)?;
+    start_transport(app, &state, Arc::new(transport), config.master_id)?;
+
+    log::info!("{}", endpoint);
+    Ok(())
+}
 
-    socket
-        .connect(&remote_addr)
-        .map_err(|e| format!("Failed to connect to {}: {}", remote_addr, e))?;
+/// Connect through an SLCAN (Lawicel) USB-CAN adapter
+#[tauri::command]
+pub fn slcan_connect<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    config: SlcanConfig,
+) -> Result<(), String> {
+    close_transport(&state)?;
 
-    let recv_socket = socket
-        .try_clone()
-        .map_err(|e| format!("Failed to clone socket: {}", e))?;
+    let transport = Slcan::open(&config.port_name, config.baud_rate, config.bitrate)?;
+    let endpoint = transport.describe();
+    set_motor_ids(&state, config.motor_id, config.master_id)?;
+    start_transport(app, &state, Arc::new(transport), config.master_id)?;
+
+    log::info!("{} connected", endpoint);
+    Ok(())
+}
 
+/// Connect to a SocketCAN interface instead of the gateway (Linux only).
+/// All motor, CANopen and scan commands then go through it.
+#[tauri::command]
+pub fn socketcan_connect<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    config: SocketCanConfig,
+) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
     {
-        let mut sock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-        *sock = Some(socket);
+        close_transport(&state)?;
+        let transport = crate::can_transport::SocketCan::open(&config.interface)?;
+        set_motor_ids(&state, config.motor_id, config.master_id)?;
+        start_transport(app, &state, Arc::new(transport), config.master_id)?;
+        log::info!("SocketCAN connected on {}", config.interface);
+        Ok(())
     }
+    #[cfg(not(target_os = "linux"))]
     {
-        let mut cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
-        *cfg = config.clone();
+        let _ = (app, state);
+        Err(format!("SocketCAN ({}) is only available on Linux", config.interface))
+    }
+}
+
+/// Keep the gateway fields of the UDP config, take the motor / master ids
+fn set_motor_ids(state: &AppState, motor_id: u8, master_id: u8) -> Result<(), String> {
+    let mut cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
+    cfg.motor_id = motor_id;
+    cfg.master_id = master_id;
+    Ok(())
+}
+
+/// Stop the recv thread and drop the current transport
+fn close_transport(state: &AppState) -> Result<(), String> {
+    state.udp_running.store(false, Ordering::SeqCst);
+    // recv returns within RECV_TIMEOUT, so the join is short
+    let thread = state.can_recv_thread.lock().map_err(|e| e.to_string())?.take();
+    if let Some(thread) = thread {
+        if thread.join().is_err() {
+            log::error!("CAN recv thread panicked");
+        }
+    }
+    let mut transport = state.can_transport.lock().map_err(|e| e.to_string())?;
+    *transport = None;
+    Ok(())
+}
+
+/// Install `transport` and start its recv thread
+fn start_transport<R: Runtime>(
+    app: AppHandle<R>,
+    state: &AppState,
+    transport: Arc<dyn CanTransport>,
+    master_id: u8,
+) -> Result<(), String> {
+    {
+        let mut current = state.can_transport.lock().map_err(|e| e.to_string())?;
+        *current = Some(Arc::clone(&transport));
     }
 
     let running = Arc::clone(&state.udp_running);
     running.store(true, Ordering::SeqCst);
 
-    let master_id = config.master_id;
     let mit_scanning = Arc::clone(&state.mit_scanning);
+    let canopen = Arc::clone(&state.canopen);
+    let pending = Arc::clone(&state.pending);
 
-    std::thread::spawn(move || {
-        udp_recv_thread(recv_socket, running, app, master_id, mit_scanning);
+    let thread = std::thread::spawn(move || {
+        can_recv_thread(transport, running, app, master_id, mit_scanning, canopen, pending);
     });
-
-    log::info!("UDP connected to {}", remote_addr);
+    *state.can_recv_thread.lock().map_err(|e| e.to_string())? = Some(thread);
     Ok(())
 }
 
-/// Background thread that receives UDP packets and parses CAN frames
-fn udp_recv_thread(
-    socket: UdpSocket,
+/// Background thread that receives CAN frames from the transport and parses them
+fn can_recv_thread<R: Runtime>(
+    transport: Arc<dyn CanTransport>,
     running: Arc<std::sync::atomic::AtomicBool>,
-    app: AppHandle,
+    app: AppHandle<R>,
     master_id: u8,
     mit_scanning: Arc<std::sync::atomic::AtomicBool>,
+    canopen: Arc<CanopenState>,
+    pending: Arc<PendingRequests>,
 ) {
     use std::time::Instant;
 
-    let mut buf = [0u8; 1024];
-
     // Throttle can-frame-log: max 50 emits per 100ms window
     const LOG_WINDOW_MS: u128 = 100;
     const LOG_MAX_PER_WINDOW: u32 = 50;
@@ -123,16 +249,10 @@ fn udp_recv_thread(
     let mut log_count: u32 = 0;
 
     while running.load(Ordering::SeqCst) {
-        match socket.recv(&mut buf) {
-            Ok(n) if n >= CAN_FRAME_SIZE => {
-                let frame_count = n / CAN_FRAME_SIZE;
-                for i in 0..frame_count {
-                    let offset = i * CAN_FRAME_SIZE;
-                    let mut frame_bytes = [0u8; CAN_FRAME_SIZE];
-                    frame_bytes.copy_from_slice(&buf[offset..offset + CAN_FRAME_SIZE]);
-
-                    let (frame_info, can_id, data) = motor_protocol::parse_can_frame(&frame_bytes);
-                    let is_extended = motor_protocol::is_extended_data_frame(frame_info);
+        match transport.recv() {
+            Ok(frames) => {
+                for frame in frames {
+                    let CanFrame { id: can_id, extended: is_extended, data, .. } = frame;
 
                     // Throttled CAN frame log — business parsing always runs regardless
                     let elapsed = log_window_start.elapsed().as_millis();
@@ -152,7 +272,7 @@ fn udp_recv_thread(
                         log_count += 1;
                     }
 
-                    if motor_protocol::is_standard_data_frame(frame_info) {
+                    if !is_extended {
                         // MIT protocol standard frame response
                         let mode = (can_id >> 8) & 0x07;
                         let id_field = (can_id & 0xFF) as u8;
@@ -165,8 +285,10 @@ fn udp_recv_thread(
                                 let _ = app.emit("motor-mit-scan-result", feedback.motor_id);
                             }
                             let _ = app.emit("motor-feedback", &feedback);
+                        } else {
+                            handle_canopen_frame(&app, &canopen, can_id as u16, &data, frame.len as usize);
                         }
-                    } else if is_extended {
+                    } else {
                         // Private protocol extended frame response
                         let (comm_type, data_area2, _target_id) =
                             motor_protocol::parse_ext_can_id(can_id);
@@ -187,6 +309,10 @@ fn udp_recv_thread(
                                 });
                                 let _ = app.emit("motor-device-info", &device_info);
                                 let _ = app.emit("motor-scan-result", responding_id);
+                                pending.complete(
+                                    ResponseKey::DeviceId { motor_id: responding_id },
+                                    &PrivResponse::DeviceId(device_id_hex),
+                                );
                             }
                             2 => {
                                 // Type 2: Private protocol feedback
@@ -200,6 +326,10 @@ fn udp_recv_thread(
                                         "version": version_str,
                                     });
                                     let _ = app.emit("motor-version-info", &version_info);
+                                    pending.complete(
+                                        ResponseKey::Version { motor_id: motor_id_resp },
+                                        &PrivResponse::Version(version_str),
+                                    );
                                 } else {
                                     let fb = motor_protocol::decode_private_feedback(data_area2, &data);
                                     let _ = app.emit("motor-feedback", &motor_protocol::MotorFeedback {
@@ -216,6 +346,11 @@ fn udp_recv_thread(
                                 // Type 17: Parameter read response
                                 let resp = motor_protocol::decode_param_read_response(data_area2, &data);
                                 let _ = app.emit("motor-param-read", &resp);
+                                let key = ResponseKey::ParamRead {
+                                    motor_id: (data_area2 & 0xFF) as u8,
+                                    index: resp.index,
+                                };
+                                pending.complete(key, &PrivResponse::ParamRead(resp));
                             }
                             0x04 => {
                                 // Type 4: Stop response (no special handling needed)
@@ -226,6 +361,10 @@ fn udp_recv_thread(
                                 let fault_word = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
                                 let fault_status = motor_protocol::decode_faults(fault_word);
                                 let _ = app.emit("motor-fault-status", &fault_status);
+                                pending.complete(
+                                    ResponseKey::Fault { motor_id: (data_area2 & 0xFF) as u8 },
+                                    &PrivResponse::Fault(fault_status),
+                                );
                             }
                             0x18 => {
                                 // Type 24 response: same as type 2 feedback format
@@ -239,7 +378,6 @@ fn udp_recv_thread(
                     }
                 }
             }
-            Ok(_) => {}
             Err(ref e)
                 if e.kind() == std::io::ErrorKind::TimedOut
                     || e.kind() == std::io::ErrorKind::WouldBlock =>
@@ -252,27 +390,75 @@ fn udp_recv_thread(
                 log::warn!("UDP ICMP port unreachable (10054) — gateway may not be listening on the configured port");
                 let _ = app.emit("udp-warning", "ICMP port unreachable (10054): gateway may not be listening. Check gateway IP/port and UDP mode.".to_string());
             }
+            Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                // Link lost on a reconnecting transport (TCP gateway); it keeps trying
+                let _ = app.emit("udp-warning", format!("{}; reconnecting", e));
+            }
             Err(e) => {
-                log::error!("UDP recv error: {}", e);
+                log::error!("CAN recv error: {}", e);
                 let _ = app.emit("udp-error", e.to_string());
                 break;
             }
         }
+
+        if let Ok(mut hb) = canopen.heartbeat.lock() {
+            for lost in hb.check(Instant::now()) {
+                log::warn!("CANopen node {} heartbeat lost", lost.node_id);
+                let _ = app.emit("canopen-heartbeat", &lost);
+            }
+        }
     }
 
-    log::info!("UDP recv thread exiting");
+    log::info!("CAN recv thread exiting");
+}
+
+/// Route a standard frame from a CANopen node: SDO responses go to the
+/// waiting transfer, heartbeats to the monitor, EMCY and TPDOs to the frontend
+fn handle_canopen_frame<R: Runtime>(app: &AppHandle<R>, canopen: &CanopenState, can_id: u16, data: &[u8; 8], len: usize) {
+    let Some((kind, node_id)) = canopen::classify_cob_id(can_id) else {
+        return;
+    };
+    match kind {
+        CobKind::SdoResponse => {
+            if let Ok(waiter) = canopen.sdo_waiter.lock() {
+                if let Some(w) = waiter.as_ref().filter(|w| w.node_id == node_id) {
+                    let _ = w.tx.send(*data);
+                }
+            }
+        }
+        CobKind::Heartbeat if len >= 1 => {
+            let changed = canopen
+                .heartbeat
+                .lock()
+                .ok()
+                .and_then(|mut hb| hb.record(node_id, data[0], std::time::Instant::now()));
+            if let Some(status) = changed {
+                let _ = app.emit("canopen-heartbeat", &status);
+            }
+        }
+        CobKind::Heartbeat => {}
+        CobKind::Emcy if len == 8 => {
+            let _ = app.emit("canopen-emcy", &canopen::decode_emcy(node_id, data));
+        }
+        CobKind::Emcy => {}
+        CobKind::Tpdo(number) => {
+            let pdo = serde_json::json!({
+                "node_id": node_id,
+                "pdo": number,
+                "cob_id": can_id,
+                "data": &data[..len],
+            });
+            let _ = app.emit("canopen-pdo", &pdo);
+        }
+    }
 }
 
 /// Disconnect UDP
 #[tauri::command]
 pub fn udp_disconnect(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    state.udp_running.store(false, Ordering::SeqCst);
-    std::thread::sleep(Duration::from_millis(100));
+    close_transport(&state)?;
 
-    let mut sock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    *sock = None;
-
-    log::info!("UDP disconnected");
+    log::info!("CAN transport disconnected");
     Ok(())
 }
 
@@ -292,97 +478,91 @@ pub fn udp_update_motor_ids(
 
 // ── Internal send helpers ───────────────────────────────────────────
 
-/// Send a standard CAN frame (11-bit ID)
-fn send_std_frame(
-    state: &AppState,
-    app: &AppHandle,
-    can_id: u16,
-    data: &[u8; 8],
-) -> Result<(), String> {
-    let frame = motor_protocol::build_can_frame(can_id, data);
-
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
-    socket
-        .send(&frame)
-        .map_err(|e| format!("UDP send failed: {}", e))?;
+/// The connected transport, cloned out of the lock so sends do not hold it
+fn transport(state: &AppState) -> Result<Arc<dyn CanTransport>, String> {
+    let transport = state.can_transport.lock().map_err(|e| e.to_string())?;
+    transport.as_ref().map(Arc::clone).ok_or_else(|| "CAN transport not connected".to_string())
+}
 
+fn emit_tx_log<R: Runtime>(app: &AppHandle<R>, frame: &CanFrame) {
     let log_entry = CanFrameLog {
         direction: "tx".to_string(),
-        can_id: can_id as u32,
-        is_extended: false,
-        data: data.to_vec(),
+        can_id: frame.id,
+        is_extended: frame.extended,
+        data: frame.payload().to_vec(),
         timestamp_ms: now_ms(),
     };
     let _ = app.emit("can-frame-log", &log_entry);
+}
 
+/// Send frames through the transport and log them
+fn send_frames<R: Runtime>(state: &AppState, app: &AppHandle<R>, frames: &[CanFrame]) -> Result<(), String> {
+    transport(state)?
+        .send(frames)
+        .map_err(|e| format!("CAN send failed: {}", e))?;
+    for frame in frames {
+        emit_tx_log(app, frame);
+    }
     Ok(())
 }
 
+/// Send a standard CAN frame (11-bit ID)
+fn send_std_frame<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    can_id: u16,
+    data: &[u8; 8],
+) -> Result<(), String> {
+    send_frames(state, app, &[CanFrame::standard(can_id, data)])
+}
+
 /// Send an extended CAN frame (29-bit ID)
-fn send_ext_frame(
+fn send_ext_frame<R: Runtime>(
     state: &AppState,
-    app: &AppHandle,
+    app: &AppHandle<R>,
     ext_can_id: u32,
     data: &[u8; 8],
 ) -> Result<(), String> {
-    let frame = motor_protocol::build_ext_can_frame(ext_can_id, data);
-
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
-    socket
-        .send(&frame)
-        .map_err(|e| format!("UDP send failed: {}", e))?;
-
-    let log_entry = CanFrameLog {
-        direction: "tx".to_string(),
-        can_id: ext_can_id,
-        is_extended: true,
-        data: data.to_vec(),
-        timestamp_ms: now_ms(),
-    };
-    let _ = app.emit("can-frame-log", &log_entry);
+    send_frames(state, app, &[CanFrame::extended(ext_can_id, data)])
+}
 
-    Ok(())
+/// Send a standard CAN frame with DLC = data.len() (CANopen NMT, SYNC, PDO)
+fn send_can_frame<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    can_id: u16,
+    data: &[u8],
+) -> Result<(), String> {
+    send_frames(state, app, &[CanFrame::standard_dlc(can_id, data)?])
 }
 
-/// Send multiple CAN frames in a single UDP packet (batch mode)
+/// Send multiple CAN frames at once (one UDP packet on the Waveshare gateway)
 #[tauri::command]
-pub fn udp_send_batch(
-    app: AppHandle,
+pub fn udp_send_batch<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     frames: Vec<(u16, Vec<u8>)>,
 ) -> Result<(), String> {
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
-
-    let mut packet = Vec::with_capacity(frames.len() * CAN_FRAME_SIZE);
+    let mut batch = Vec::with_capacity(frames.len());
     for (can_id, data_vec) in &frames {
         if data_vec.len() != 8 {
             return Err("Each CAN frame must have exactly 8 data bytes".to_string());
         }
         let mut data = [0u8; 8];
         data.copy_from_slice(data_vec);
-        let frame = motor_protocol::build_can_frame(*can_id, &data);
-        packet.extend_from_slice(&frame);
-
-        let log_entry = CanFrameLog {
-            direction: "tx".to_string(),
-            can_id: *can_id as u32,
-            is_extended: false,
-            data: data_vec.clone(),
-            timestamp_ms: now_ms(),
-        };
-        let _ = app.emit("can-frame-log", &log_entry);
+        batch.push(CanFrame::standard(*can_id, &data));
     }
 
-    if packet.len() > 650 {
+    if batch.len() * CAN_FRAME_SIZE > 650 {
         return Err("Batch too large (max 650 bytes / 50 frames)".to_string());
     }
 
-    socket
-        .send(&packet)
-        .map_err(|e| format!("UDP batch send failed: {}", e))?;
+    transport(&state)?
+        .send(&batch)
+        .map_err(|e| format!("CAN batch send failed: {}", e))?;
+    for frame in &batch {
+        emit_tx_log(&app, frame);
+    }
 
     Ok(())
 }
@@ -390,8 +570,8 @@ pub fn udp_send_batch(
 // ── MIT protocol commands (standard frame) ──────────────────────────
 
 #[tauri::command]
-pub fn motor_enable(
-    app: AppHandle,
+pub fn motor_enable<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -401,8 +581,8 @@ pub fn motor_enable(
 }
 
 #[tauri::command]
-pub fn motor_stop(
-    app: AppHandle,
+pub fn motor_stop<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -412,8 +592,8 @@ pub fn motor_stop(
 }
 
 #[tauri::command]
-pub fn motor_set_zero(
-    app: AppHandle,
+pub fn motor_set_zero<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -423,8 +603,8 @@ pub fn motor_set_zero(
 }
 
 #[tauri::command]
-pub fn motor_set_mode(
-    app: AppHandle,
+pub fn motor_set_mode<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     mode: u8,
@@ -435,8 +615,8 @@ pub fn motor_set_mode(
 }
 
 #[tauri::command]
-pub fn motor_clear_fault(
-    app: AppHandle,
+pub fn motor_clear_fault<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -446,8 +626,8 @@ pub fn motor_clear_fault(
 }
 
 #[tauri::command]
-pub fn motor_read_fault(
-    app: AppHandle,
+pub fn motor_read_fault<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -457,8 +637,8 @@ pub fn motor_read_fault(
 }
 
 #[tauri::command]
-pub fn motor_mit_control(
-    app: AppHandle,
+pub fn motor_mit_control<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     position: f32,
@@ -473,8 +653,8 @@ pub fn motor_mit_control(
 }
 
 #[tauri::command]
-pub fn motor_position_control(
-    app: AppHandle,
+pub fn motor_position_control<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     target_position: f32,
@@ -486,8 +666,8 @@ pub fn motor_position_control(
 }
 
 #[tauri::command]
-pub fn motor_speed_control(
-    app: AppHandle,
+pub fn motor_speed_control<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     target_speed: f32,
@@ -499,8 +679,8 @@ pub fn motor_speed_control(
 }
 
 #[tauri::command]
-pub fn motor_change_id(
-    app: AppHandle,
+pub fn motor_change_id<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     new_id: u8,
@@ -511,8 +691,8 @@ pub fn motor_change_id(
 }
 
 #[tauri::command]
-pub fn motor_change_master_id(
-    app: AppHandle,
+pub fn motor_change_master_id<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     new_master_id: u8,
@@ -523,8 +703,8 @@ pub fn motor_change_master_id(
 }
 
 #[tauri::command]
-pub fn motor_change_protocol(
-    app: AppHandle,
+pub fn motor_change_protocol<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     protocol: u8,
@@ -537,8 +717,8 @@ pub fn motor_change_protocol(
 // ── Private protocol commands (extended frame) ──────────────────────
 
 #[tauri::command]
-pub fn priv_get_device_id(
-    app: AppHandle,
+pub fn priv_get_device_id<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -549,8 +729,8 @@ pub fn priv_get_device_id(
 }
 
 #[tauri::command]
-pub fn priv_enable(
-    app: AppHandle,
+pub fn priv_enable<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -561,8 +741,8 @@ pub fn priv_enable(
 }
 
 #[tauri::command]
-pub fn priv_stop(
-    app: AppHandle,
+pub fn priv_stop<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     clear_fault: bool,
@@ -574,8 +754,8 @@ pub fn priv_stop(
 }
 
 #[tauri::command]
-pub fn priv_set_zero(
-    app: AppHandle,
+pub fn priv_set_zero<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -586,8 +766,8 @@ pub fn priv_set_zero(
 }
 
 #[tauri::command]
-pub fn priv_set_can_id(
-    app: AppHandle,
+pub fn priv_set_can_id<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     new_id: u8,
@@ -595,12 +775,15 @@ pub fn priv_set_can_id(
     let cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
     let (ext_id, data) = motor_protocol::priv_cmd_set_can_id(cfg.master_id, motor_id, new_id);
     drop(cfg);
-    send_ext_frame(&state, &app, ext_id, &data)
+    send_ext_frame(&state, &app, ext_id, &data)?;
+    // The motor answers on the new ID from now on; its unsaved writes go with it
+    state.param_staging.move_motor(motor_id, new_id);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn priv_param_read(
-    app: AppHandle,
+pub fn priv_param_read<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     index: u16,
@@ -611,15 +794,23 @@ pub fn priv_param_read(
     send_ext_frame(&state, &app, ext_id, &data)
 }
 
-#[tauri::command]
-pub fn priv_param_write(
-    app: AppHandle,
+/// Raw type 18 write. Table parameters go through the same range check,
+/// confirmation and read-back as `priv_write_param`.
+#[tauri::command(async)]
+pub fn priv_param_write<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     index: u16,
     param_type: String,
     value_f64: f64,
+    confirm: Option<bool>,
 ) -> Result<(), String> {
+    if let Some(def) = motor_protocol::find_param_by_index(index) {
+        let value = motor_protocol::ParamValue::from_f64(&def.param_type, value_f64)?;
+        return write_param_verified(&state, &app, motor_id, def, value, confirm.unwrap_or(false)).map(|_| ());
+    }
+
     let cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
     let master_id = cfg.master_id;
     drop(cfg);
@@ -639,21 +830,20 @@ pub fn priv_param_write(
     send_ext_frame(&state, &app, ext_id, &data)
 }
 
-#[tauri::command]
-pub fn priv_save_params(
-    app: AppHandle,
+/// Save RAM parameters to flash (type 22) once every pending write reads
+/// back as written. Returns the writes that were persisted.
+#[tauri::command(async)]
+pub fn priv_save_params<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
-) -> Result<(), String> {
-    let cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
-    let (ext_id, data) = motor_protocol::priv_cmd_save_params(cfg.master_id, motor_id);
-    drop(cfg);
-    send_ext_frame(&state, &app, ext_id, &data)
+) -> Result<Vec<StagedParam>, String> {
+    persist_params(&state, &app, motor_id)
 }
 
 #[tauri::command]
-pub fn priv_change_baud(
-    app: AppHandle,
+pub fn priv_change_baud<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     baud_code: u8,
@@ -665,8 +855,8 @@ pub fn priv_change_baud(
 }
 
 #[tauri::command]
-pub fn priv_active_report(
-    app: AppHandle,
+pub fn priv_active_report<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     enable: u8,
@@ -678,8 +868,8 @@ pub fn priv_active_report(
 }
 
 #[tauri::command]
-pub fn priv_change_protocol(
-    app: AppHandle,
+pub fn priv_change_protocol<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
     protocol: u8,
@@ -691,8 +881,8 @@ pub fn priv_change_protocol(
 }
 
 #[tauri::command]
-pub fn priv_read_version(
-    app: AppHandle,
+pub fn priv_read_version<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -703,8 +893,8 @@ pub fn priv_read_version(
 }
 
 #[tauri::command]
-pub fn priv_fault_feedback(
-    app: AppHandle,
+pub fn priv_fault_feedback<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_id: u8,
 ) -> Result<(), String> {
@@ -714,11 +904,495 @@ pub fn priv_fault_feedback(
     send_ext_frame(&state, &app, ext_id, &data)
 }
 
+/// Private protocol type 1: one motion-control (impedance) frame.
+/// The motor answers with a type 2 feedback frame.
+#[tauri::command]
+pub fn priv_motion_control<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    position: f32,
+    velocity: f32,
+    kp: f32,
+    kd: f32,
+    torque: f32,
+) -> Result<(), String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_motion_control(motor_id, position, velocity, kp, kd, torque);
+    send_ext_frame(&state, &app, ext_id, &data)
+}
+
+// ── Awaitable private protocol requests ─────────────────────────────
+//
+// Same frames as the commands above, but the command waits for the matching
+// reply (see pending.rs) and returns it, resending on timeout.
+
+/// Per-attempt reply timeout and resends after the first attempt
+const REQUEST_TIMEOUT_MS: u64 = 200;
+const REQUEST_RETRIES: u32 = 2;
+
+/// Send an extended frame and wait for the reply matching `key`
+fn priv_request<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    key: ResponseKey,
+    ext_id: u32,
+    data: &[u8; 8],
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<PrivResponse, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(REQUEST_TIMEOUT_MS).max(1));
+    let attempts = retries.unwrap_or(REQUEST_RETRIES) + 1;
+    let reply = state.pending.register(key);
+    for attempt in 1..=attempts {
+        send_ext_frame(state, app, ext_id, data)?;
+        match reply.rx.recv_timeout(timeout) {
+            Ok(response) => return Ok(response),
+            Err(_) if attempt < attempts => log::warn!("No reply to {:?} (attempt {}), resending", key, attempt),
+            Err(_) => {}
+        }
+    }
+    Err(format!(
+        "No reply to {:?} after {} attempt(s) of {} ms",
+        key,
+        attempts,
+        timeout.as_millis()
+    ))
+}
+
+fn master_id(state: &AppState) -> Result<u8, String> {
+    Ok(state.udp_config.lock().map_err(|e| e.to_string())?.master_id)
+}
+
+/// Read one parameter and return its value (type 17)
+#[tauri::command(async)]
+pub fn priv_param_read_await<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    index: u16,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<motor_protocol::ParamReadResponse, String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_param_read(master_id(&state)?, motor_id, index);
+    let key = ResponseKey::ParamRead { motor_id, index };
+    match priv_request(&state, &app, key, ext_id, &data, timeout_ms, retries)? {
+        PrivResponse::ParamRead(resp) if resp.success => Ok(resp),
+        PrivResponse::ParamRead(_) => Err(format!("Motor {} rejected read of 0x{:04X}", motor_id, index)),
+        other => Err(format!("Unexpected reply {:?}", other)),
+    }
+}
+
+/// Read the firmware version string (type 4 special command, type 2 reply)
+#[tauri::command(async)]
+pub fn priv_read_version_await<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<String, String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_read_version(master_id(&state)?, motor_id);
+    match priv_request(&state, &app, ResponseKey::Version { motor_id }, ext_id, &data, timeout_ms, retries)? {
+        PrivResponse::Version(version) => Ok(version),
+        other => Err(format!("Unexpected reply {:?}", other)),
+    }
+}
+
+/// Request and return the fault status (type 21)
+#[tauri::command(async)]
+pub fn priv_fault_feedback_await<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<motor_protocol::FaultStatus, String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_fault_feedback(master_id(&state)?, motor_id);
+    match priv_request(&state, &app, ResponseKey::Fault { motor_id }, ext_id, &data, timeout_ms, retries)? {
+        PrivResponse::Fault(status) => Ok(status),
+        other => Err(format!("Unexpected reply {:?}", other)),
+    }
+}
+
+/// Request and return the 64-bit MCU identifier as hex (type 0)
+#[tauri::command(async)]
+pub fn priv_get_device_id_await<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<String, String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_get_device_id(master_id(&state)?, motor_id);
+    match priv_request(&state, &app, ResponseKey::DeviceId { motor_id }, ext_id, &data, timeout_ms, retries)? {
+        PrivResponse::DeviceId(id) => Ok(id),
+        other => Err(format!("Unexpected reply {:?}", other)),
+    }
+}
+
+/// Read a table parameter by name or index and return its typed value
+#[tauri::command(async)]
+pub fn priv_read_param<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    param: String,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<motor_protocol::TypedParam, String> {
+    let def = motor_protocol::find_param(&param)?;
+    motor_protocol::check_param_access(def, false)?;
+    let value = read_param_value(&state, &app, motor_id, def, timeout_ms, retries)?;
+    Ok(motor_protocol::TypedParam { index: def.index, name: def.name, value })
+}
+
+/// Refuse a write to a `confirm` parameter unless the caller confirmed it
+fn check_param_confirm(def: &motor_protocol::ParamDef, confirm: bool) -> Result<(), String> {
+    if def.confirm && !confirm {
+        return Err(format!(
+            "Writing {} (0x{:04X}) changes protocol behaviour; resend with confirm",
+            def.name, def.index
+        ));
+    }
+    Ok(())
+}
+
+/// Read a table parameter (type 17) and remember it as the last read value
+fn read_param_value<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    motor_id: u8,
+    def: &motor_protocol::ParamDef,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+) -> Result<motor_protocol::ParamValue, String> {
+    let (ext_id, data) = motor_protocol::priv_cmd_param_read(master_id(state)?, motor_id, def.index);
+    let key = ResponseKey::ParamRead { motor_id, index: def.index };
+    let value = match priv_request(state, app, key, ext_id, &data, timeout_ms, retries)? {
+        PrivResponse::ParamRead(resp) => resp.typed(def)?.value,
+        other => return Err(format!("Unexpected reply {:?}", other)),
+    };
+    state.param_staging.record_read(motor_id, def.index, value);
+    Ok(value)
+}
+
+/// Validate, write (type 18), then read the parameter back (type 17) and
+/// fail if the motor did not take the value. The write is staged as pending
+/// until saved; the first write of a parameter reads its current value first
+/// so it can be reverted. Returns the read-back value.
+fn write_param_verified<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    motor_id: u8,
+    def: &motor_protocol::ParamDef,
+    value: motor_protocol::ParamValue,
+    confirm: bool,
+) -> Result<motor_protocol::ParamValue, String> {
+    check_param_confirm(def, confirm)?;
+    motor_protocol::validate_param_value(def, value)?;
+    let readable = def.access != motor_protocol::ParamAccess::W;
+    if readable && state.param_staging.baseline(motor_id, def.index).is_none() {
+        if let Err(e) = read_param_value(state, app, motor_id, def, None, None) {
+            log::warn!("No baseline for {} on motor {}: {}", def.name, motor_id, e);
+        }
+    }
+    let read_back = write_and_read_back(state, app, motor_id, def, value)?;
+    // Stage what the motor holds even on a mismatch, so it can be reverted or caught on save
+    state.param_staging.record_write(motor_id, def, read_back);
+    check_read_back(motor_id, def, value, read_back)?;
+    Ok(read_back)
+}
+
+/// Type 18 write followed by a type 17 read-back; returns the value read
+/// back (the written value for write-only parameters)
+fn write_and_read_back<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    motor_id: u8,
+    def: &motor_protocol::ParamDef,
+    value: motor_protocol::ParamValue,
+) -> Result<motor_protocol::ParamValue, String> {
+    let master_id = master_id(state)?;
+    let (ext_id, data) = motor_protocol::priv_cmd_param_write_value(master_id, motor_id, def, value)?;
+    send_ext_frame(state, app, ext_id, &data)?;
+    if def.access == motor_protocol::ParamAccess::W {
+        return Ok(value);
+    }
+
+    let (ext_id, data) = motor_protocol::priv_cmd_param_read(master_id, motor_id, def.index);
+    let key = ResponseKey::ParamRead { motor_id, index: def.index };
+    let read_back = match priv_request(state, app, key, ext_id, &data, None, None)? {
+        PrivResponse::ParamRead(resp) => resp.typed(def)?.value,
+        other => return Err(format!("Unexpected reply {:?}", other)),
+    };
+    Ok(read_back)
+}
+
+/// Fail if the read-back value is not the one written
+fn check_read_back(
+    motor_id: u8,
+    def: &motor_protocol::ParamDef,
+    written: motor_protocol::ParamValue,
+    read_back: motor_protocol::ParamValue,
+) -> Result<(), String> {
+    if !param_profile::values_equal(read_back, written) {
+        return Err(format!(
+            "Motor {} did not accept {}: wrote {}, read back {}",
+            motor_id,
+            def.name,
+            written.as_f64(),
+            read_back.as_f64()
+        ));
+    }
+    Ok(())
+}
+
+/// Write a table parameter by name or index (volatile until saved).
+/// The value is converted to the parameter's type and range-checked;
+/// parameters marked `confirm` need `confirm: true`. Returns the value
+/// read back from the motor.
+#[tauri::command(async)]
+pub fn priv_write_param<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    param: String,
+    value: f64,
+    confirm: Option<bool>,
+) -> Result<motor_protocol::ParamValue, String> {
+    let def = motor_protocol::find_param(&param)?;
+    let value = motor_protocol::ParamValue::from_f64(&def.param_type, value)?;
+    write_param_verified(&state, &app, motor_id, def, value, confirm.unwrap_or(false))
+}
+
+// ── Flash staging ───────────────────────────────────────────────────
+//
+// Writes through `write_param_verified` are pending in `param_staging` until
+// a verified save; they can be listed or reverted to their original values.
+
+/// Read back every pending write, then save (type 22) if all match
+fn persist_params<R: Runtime>(state: &AppState, app: &AppHandle<R>, motor_id: u8) -> Result<Vec<StagedParam>, String> {
+    let pending = state.param_staging.dirty(motor_id);
+    let mut mismatches = Vec::new();
+    for staged in &pending {
+        let def = motor_protocol::find_param_by_index(staged.index)
+            .ok_or_else(|| format!("Unknown parameter 0x{:04X}", staged.index))?;
+        if def.access == motor_protocol::ParamAccess::W {
+            continue;
+        }
+        match read_param_value(state, app, motor_id, def, None, None) {
+            Ok(now) if param_profile::values_equal(now, staged.value) => {}
+            Ok(now) => mismatches.push(format!("{} is {}, staged {}", def.name, now.as_f64(), staged.value.as_f64())),
+            Err(e) => mismatches.push(format!("{}: {}", def.name, e)),
+        }
+    }
+    if !mismatches.is_empty() {
+        return Err(format!("Not saving motor {}: {}", motor_id, mismatches.join("; ")));
+    }
+
+    let (ext_id, data) = motor_protocol::priv_cmd_save_params(master_id(state)?, motor_id);
+    send_ext_frame(state, app, ext_id, &data)?;
+    state.param_staging.mark_persisted(motor_id, &pending);
+    log::info!("Motor {} saved to flash with {} pending write(s)", motor_id, pending.len());
+    Ok(pending)
+}
+
+/// Parameters written this session and not yet saved
+#[tauri::command]
+pub fn priv_param_dirty(state: tauri::State<'_, AppState>, motor_id: u8) -> Vec<StagedParam> {
+    state.param_staging.dirty(motor_id)
+}
+
+/// Write pending parameters back to their original values (all when
+/// `params` is None). Returns the reverted entries.
+#[tauri::command(async)]
+pub fn priv_param_revert<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    params: Option<Vec<String>>,
+) -> Result<Vec<StagedParam>, String> {
+    let selected = match params {
+        Some(keys) => keys
+            .iter()
+            .map(|k| motor_protocol::find_param(k).map(|d| d.index))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => state.param_staging.dirty(motor_id).iter().map(|s| s.index).collect(),
+    };
+
+    let mut reverted = Vec::new();
+    for staged in state.param_staging.dirty(motor_id) {
+        if !selected.contains(&staged.index) {
+            continue;
+        }
+        let def = motor_protocol::find_param_by_index(staged.index)
+            .ok_or_else(|| format!("Unknown parameter 0x{:04X}", staged.index))?;
+        let original = staged
+            .original
+            .ok_or_else(|| format!("No original value recorded for {}", def.name))?;
+        let read_back = write_and_read_back(&state, &app, motor_id, def, original)?;
+        if let Err(e) = check_read_back(motor_id, def, original, read_back) {
+            state.param_staging.record_write(motor_id, def, read_back);
+            return Err(e);
+        }
+        state.param_staging.discard(motor_id, def.index, original);
+        reverted.push(staged);
+    }
+    Ok(reverted)
+}
+
+// ── Parameter profiles ──────────────────────────────────────────────
+
+/// Gap between parameter writes during a restore
+const RESTORE_WRITE_GAP: Duration = Duration::from_millis(5);
+
+/// Read `params` from a motor; failed reads are kept with their error
+fn read_profile<'a, R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    motor_id: u8,
+    params: impl Iterator<Item = &'a motor_protocol::ParamDef>,
+) -> Result<ParamProfile, String> {
+    // Type 0 presence check, so an offline motor fails once instead of once per parameter
+    let (ext_id, data) = motor_protocol::priv_cmd_get_device_id(master_id(state)?, motor_id);
+    let device_id = match priv_request(state, app, ResponseKey::DeviceId { motor_id }, ext_id, &data, None, None) {
+        Ok(PrivResponse::DeviceId(id)) => id,
+        Ok(other) => return Err(format!("Unexpected reply {:?}", other)),
+        Err(e) => return Err(format!("Motor {} is not answering: {}", motor_id, e)),
+    };
+    let mut entries = Vec::new();
+    for def in params {
+        let (value, error) = match read_param_value(state, app, motor_id, def, None, None) {
+            Ok(v) => (Some(v), None),
+            Err(e) => (None, Some(e)),
+        };
+        entries.push(ProfileEntry {
+            index: def.index,
+            name: def.name.to_string(),
+            value,
+            error,
+        });
+    }
+    if !entries.is_empty() && entries.iter().all(|e| e.value.is_none()) {
+        let first = entries[0].error.as_deref().unwrap_or("");
+        return Err(format!("Motor {} did not return any parameter ({})", motor_id, first));
+    }
+    Ok(ParamProfile {
+        version: param_profile::PROFILE_VERSION,
+        motor_id,
+        firmware: None,
+        device_id: Some(device_id),
+        created_ms: now_ms(),
+        params: entries,
+    })
+}
+
+/// Snapshot every table parameter plus firmware version and device id.
+/// Saved to `file_path` (.json or .toml) when given.
+#[tauri::command(async)]
+pub fn priv_param_dump<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    file_path: Option<String>,
+) -> Result<ParamProfile, String> {
+    let mut profile = read_profile(&state, &app, motor_id, motor_protocol::all_params())?;
+    let master_id = master_id(&state)?;
+
+    let (ext_id, data) = motor_protocol::priv_cmd_read_version(master_id, motor_id);
+    let key = ResponseKey::Version { motor_id };
+    if let Ok(PrivResponse::Version(v)) = priv_request(&state, &app, key, ext_id, &data, None, None) {
+        profile.firmware = Some(v);
+    }
+    if let Some(path) = file_path {
+        profile.save(&path)?;
+        log::info!("Motor {} parameters saved to {}", motor_id, path);
+    }
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn param_profile_load(file_path: String) -> Result<ParamProfile, String> {
+    ParamProfile::load(&file_path)
+}
+
+#[tauri::command]
+pub fn param_profile_save(profile: ParamProfile, file_path: String) -> Result<(), String> {
+    profile.save(&file_path)
+}
+
+/// Differences between two profiles, or between a profile and the table defaults
+#[tauri::command]
+pub fn param_profile_diff(
+    left: ParamProfile,
+    right: Option<ParamProfile>,
+) -> Vec<ParamDiff> {
+    let right = right.unwrap_or_else(|| ParamProfile::defaults(left.motor_id));
+    param_profile::diff(&left, &right)
+}
+
+/// Result of `priv_param_restore`
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    /// left = value on the motor before, right = value written
+    pub written: Vec<ParamDiff>,
+    pub saved: bool,
+}
+
+/// Write every writable parameter where `profile` differs from the motor,
+/// then save to flash (type 22). Every write is range-checked and read back;
+/// nothing is written if a value is out of range, or touches a `confirm`
+/// parameter without `confirm: true`. Refused while the motor has unsaved
+/// writes, since the save would flash them too.
+#[tauri::command(async)]
+pub fn priv_param_restore<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    profile: ParamProfile,
+    confirm: Option<bool>,
+) -> Result<RestoreReport, String> {
+    let pending = state.param_staging.dirty(motor_id);
+    if !pending.is_empty() {
+        return Err(format!(
+            "Motor {} has {} unsaved write(s); save or revert them before restoring",
+            motor_id,
+            pending.len()
+        ));
+    }
+    let confirm = confirm.unwrap_or(false);
+    let current = read_profile(&state, &app, motor_id, motor_protocol::WRITABLE_PARAMS.iter())?;
+    let writes = param_profile::restore_writes(&profile, &current);
+    for &(def, value) in &writes {
+        check_param_confirm(def, confirm)?;
+        motor_protocol::validate_param_value(def, value)?;
+    }
+
+    let mut written = Vec::new();
+    for (def, value) in writes {
+        write_param_verified(&state, &app, motor_id, def, value, confirm)?;
+        written.push(ParamDiff {
+            index: def.index,
+            name: def.name.to_string(),
+            left: current.value(def.index),
+            right: Some(value),
+        });
+        std::thread::sleep(RESTORE_WRITE_GAP);
+    }
+
+    let saved = !written.is_empty();
+    if saved {
+        persist_params(&state, &app, motor_id)?;
+    }
+    log::info!("Motor {} restore: {} parameter(s) written", motor_id, written.len());
+    Ok(RestoreReport { written, saved })
+}
+
 /// Get device ID: sends private protocol type 0 (get_device_id) command
 /// Response is parsed by recv thread and emitted as "motor-device-id" event
 #[tauri::command]
-pub fn udp_diagnose(
-    app: AppHandle,
+pub fn udp_diagnose<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     let cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
@@ -729,15 +1403,11 @@ pub fn udp_diagnose(
 
     send_ext_frame(&state, &app, ext_id, &data)?;
 
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
-    let local_addr = socket.local_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".to_string());
-    let peer_addr = socket.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".to_string());
-    drop(sock_lock);
+    let endpoint = transport(&state)?.describe();
 
     Ok(format!(
-        "Sent Get Device ID (type 0) to motor ID={}\nLocal: {} → Remote: {}\nResponse will appear in Device Info section",
-        motor_id, local_addr, peer_addr
+        "Sent Get Device ID (type 0) to motor ID={}\n{}\nResponse will appear in Device Info section",
+        motor_id, endpoint
     ))
 }
 
@@ -745,31 +1415,22 @@ pub fn udp_diagnose(
 /// then MIT enable commands to catch motors in MIT mode.
 /// Responses are emitted as "motor-scan-result" (private) and "motor-mit-scan-result" (MIT) events.
 #[tauri::command]
-pub fn udp_scan_motors(
-    app: AppHandle,
+pub fn udp_scan_motors<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
     let cfg = state.udp_config.lock().map_err(|e| e.to_string())?;
     let master_id = cfg.master_id;
     drop(cfg);
 
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
+    let transport = transport(&state)?;
 
     // Phase 1: Private protocol scan (extended frame, type 0)
     for motor_id in 0..=127u8 {
         let (ext_id, data) = motor_protocol::priv_cmd_get_device_id(master_id, motor_id);
-        let frame = motor_protocol::build_ext_can_frame(ext_id, &data);
-        let _ = socket.send(&frame);
-
-        let log_entry = CanFrameLog {
-            direction: "tx".to_string(),
-            can_id: ext_id,
-            is_extended: true,
-            data: data.to_vec(),
-            timestamp_ms: now_ms(),
-        };
-        let _ = app.emit("can-frame-log", &log_entry);
+        let frame = CanFrame::extended(ext_id, &data);
+        let _ = transport.send(&[frame]);
+        emit_tx_log(&app, &frame);
 
         std::thread::sleep(Duration::from_millis(5));
     }
@@ -782,18 +1443,9 @@ pub fn udp_scan_motors(
 
     for motor_id in 0..=127u8 {
         let can_id = motor_protocol::make_can_id(0, motor_id);
-        let data = motor_protocol::cmd_enable();
-        let frame = motor_protocol::build_can_frame(can_id, &data);
-        let _ = socket.send(&frame);
-
-        let log_entry = CanFrameLog {
-            direction: "tx".to_string(),
-            can_id: can_id as u32,
-            is_extended: false,
-            data: data.to_vec(),
-            timestamp_ms: now_ms(),
-        };
-        let _ = app.emit("can-frame-log", &log_entry);
+        let frame = CanFrame::standard(can_id, &motor_protocol::cmd_enable());
+        let _ = transport.send(&[frame]);
+        emit_tx_log(&app, &frame);
 
         std::thread::sleep(Duration::from_millis(5));
     }
@@ -805,9 +1457,7 @@ pub fn udp_scan_motors(
     // Phase 3: Stop all motors that were enabled during MIT scan
     for motor_id in 0..=127u8 {
         let can_id = motor_protocol::make_can_id(0, motor_id);
-        let data = motor_protocol::cmd_stop();
-        let frame = motor_protocol::build_can_frame(can_id, &data);
-        let _ = socket.send(&frame);
+        let _ = transport.send(&[CanFrame::standard(can_id, &motor_protocol::cmd_stop())]);
         std::thread::sleep(Duration::from_millis(2));
     }
 
@@ -816,8 +1466,8 @@ pub fn udp_scan_motors(
 
 /// Send a raw 13-byte frame for manual testing
 #[tauri::command]
-pub fn udp_send_raw(
-    app: AppHandle,
+pub fn udp_send_raw<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     hex_string: String,
 ) -> Result<String, String> {
@@ -831,29 +1481,15 @@ pub fn udp_send_raw(
         return Err(format!("Need exactly 13 bytes, got {}", bytes.len()));
     }
 
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?;
-
-    match socket.send(&bytes) {
-        Ok(n) => {
-            let frame_info = bytes[0];
-            let can_id = ((bytes[1] as u32) << 24)
-                | ((bytes[2] as u32) << 16)
-                | ((bytes[3] as u32) << 8)
-                | (bytes[4] as u32);
-            let is_ext = frame_info & 0x80 != 0;
-
-            let log_entry = CanFrameLog {
-                direction: "tx".to_string(),
-                can_id,
-                is_extended: is_ext,
-                data: bytes[5..13].to_vec(),
-                timestamp_ms: now_ms(),
-            };
-            let _ = app.emit("can-frame-log", &log_entry);
+    let mut raw = [0u8; CAN_FRAME_SIZE];
+    raw.copy_from_slice(&bytes);
+    let frame = CanFrame::from_waveshare(&raw).ok_or("Only data frames can be sent (frame_info bit6 must be 0)")?;
 
+    match transport(&state)?.send(&[frame]) {
+        Ok(()) => {
+            emit_tx_log(&app, &frame);
             let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
-            Ok(format!("Sent {} bytes: [{}]", n, hex.join(" ")))
+            Ok(format!("Sent {} bytes: [{}]", bytes.len(), hex.join(" ")))
         }
         Err(e) => Err(format!("Send failed: {}", e)),
     }
@@ -863,7 +1499,7 @@ pub fn udp_send_raw(
 #[tauri::command]
 pub fn get_param_table() -> Vec<serde_json::Value> {
     let mut result = Vec::new();
-    for p in motor_protocol::WRITABLE_PARAMS.iter().chain(motor_protocol::READONLY_PARAMS.iter()) {
+    for p in motor_protocol::all_params() {
         result.push(serde_json::json!({
             "index": p.index,
             "name": p.name,
@@ -871,6 +1507,8 @@ pub fn get_param_table() -> Vec<serde_json::Value> {
             "paramType": format!("{:?}", p.param_type),
             "access": format!("{:?}", p.access),
             "defaultStr": p.default_str,
+            "range": p.range,
+            "confirm": p.confirm,
         }));
     }
     result
@@ -879,8 +1517,8 @@ pub fn get_param_table() -> Vec<serde_json::Value> {
 // ── Batch commands ───────────────────────────────────────────────────
 
 #[tauri::command]
-pub fn motor_enable_all(
-    app: AppHandle,
+pub fn motor_enable_all<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_ids: Vec<u8>,
 ) -> Result<(), String> {
@@ -893,8 +1531,8 @@ pub fn motor_enable_all(
 }
 
 #[tauri::command]
-pub fn motor_stop_all(
-    app: AppHandle,
+pub fn motor_stop_all<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_ids: Vec<u8>,
 ) -> Result<(), String> {
@@ -907,8 +1545,8 @@ pub fn motor_stop_all(
 }
 
 #[tauri::command]
-pub fn motor_set_zero_all(
-    app: AppHandle,
+pub fn motor_set_zero_all<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_ids: Vec<u8>,
 ) -> Result<(), String> {
@@ -921,8 +1559,8 @@ pub fn motor_set_zero_all(
 }
 
 #[tauri::command]
-pub fn motor_set_mode_all(
-    app: AppHandle,
+pub fn motor_set_mode_all<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_ids: Vec<u8>,
     mode: u8,
@@ -936,8 +1574,8 @@ pub fn motor_set_mode_all(
 }
 
 #[tauri::command]
-pub fn motor_clear_fault_all(
-    app: AppHandle,
+pub fn motor_clear_fault_all<R: Runtime>(
+    app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     motor_ids: Vec<u8>,
 ) -> Result<(), String> {
@@ -949,42 +1587,170 @@ pub fn motor_clear_fault_all(
     Ok(())
 }
 
-// ── MIT high-frequency loop ─────────────────────────────────────────
+// ── High-frequency control loops ────────────────────────────────────
+//
+// The MIT loop sends command 3 standard frames; the private protocol loop
+// sends the same impedance control as type 1 extended frames so the motor can
+// stay in private protocol. Both run on `control_loop_thread`; only one loop
+// runs at a time.
+
+/// Parameters of a high-rate loop: what to send each period and on exit
+trait ControlLoop: Send + 'static {
+    /// Name for logs and errors
+    const NAME: &'static str;
+    fn init(&mut self, motor_id: u8, freq_hz: u32, master_id: u8);
+    fn freq_hz(&self) -> u32;
+    /// Frame sent every period from the current setpoint
+    fn frame(&self) -> CanFrame;
+    /// Frame sent once when the loop exits
+    fn stop_frame(&self) -> CanFrame;
+}
 
-/// Start MIT high-frequency control loop thread
-#[tauri::command]
-pub fn udp_mit_loop_start(
-    app: AppHandle,
-    state: tauri::State<'_, AppState>,
+impl ControlLoop for crate::state::MitLoopConfig {
+    const NAME: &'static str = "MIT loop";
+
+    fn init(&mut self, motor_id: u8, freq_hz: u32, _master_id: u8) {
+        self.motor_id = motor_id;
+        self.freq_hz = freq_hz;
+    }
+
+    fn freq_hz(&self) -> u32 {
+        self.freq_hz
+    }
+
+    fn frame(&self) -> CanFrame {
+        let t = &self.target;
+        let data = motor_protocol::cmd_mit_params(t.position, t.velocity, t.kp, t.kd, t.torque);
+        CanFrame::standard(motor_protocol::make_can_id(0, self.motor_id), &data)
+    }
+
+    fn stop_frame(&self) -> CanFrame {
+        CanFrame::standard(motor_protocol::make_can_id(0, self.motor_id), &motor_protocol::cmd_stop())
+    }
+}
+
+impl ControlLoop for crate::state::PrivLoopConfig {
+    const NAME: &'static str = "Private protocol loop";
+
+    fn init(&mut self, motor_id: u8, freq_hz: u32, master_id: u8) {
+        self.motor_id = motor_id;
+        self.freq_hz = freq_hz;
+        self.master_id = master_id;
+    }
+
+    fn freq_hz(&self) -> u32 {
+        self.freq_hz
+    }
+
+    fn frame(&self) -> CanFrame {
+        let t = &self.target;
+        let (ext_id, data) =
+            motor_protocol::priv_cmd_motion_control(self.motor_id, t.position, t.velocity, t.kp, t.kd, t.torque);
+        CanFrame::extended(ext_id, &data)
+    }
+
+    fn stop_frame(&self) -> CanFrame {
+        let (ext_id, data) = motor_protocol::priv_cmd_stop(self.master_id, self.motor_id, false);
+        CanFrame::extended(ext_id, &data)
+    }
+}
+
+/// Claim `running` and spawn the loop thread, unless either loop is running
+fn start_control_loop<R: Runtime, P: ControlLoop>(
+    state: &AppState,
+    app: AppHandle<R>,
+    running: &Arc<AtomicBool>,
+    other_running: &AtomicBool,
+    params: &Arc<Mutex<P>>,
     motor_id: u8,
     frequency: u32,
 ) -> Result<(), String> {
-    if state.mit_loop_running.load(Ordering::SeqCst) {
-        return Err("MIT loop already running".to_string());
+    if other_running.load(Ordering::SeqCst) {
+        return Err("Another control loop is running; stop it first".to_string());
+    }
+    let transport = transport(state)?;
+    let master_id = master_id(state)?;
+    if running.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(format!("{} already running", P::NAME));
+    }
+    match params.lock() {
+        Ok(mut p) => p.init(motor_id, frequency.clamp(1, 2000), master_id),
+        Err(e) => {
+            running.store(false, Ordering::SeqCst);
+            return Err(e.to_string());
+        }
     }
 
-    let sock_lock = state.udp_socket.lock().map_err(|e| e.to_string())?;
-    let socket = sock_lock.as_ref().ok_or("UDP not connected")?.try_clone()
-        .map_err(|e| format!("Failed to clone socket: {}", e))?;
-    drop(sock_lock);
+    let running = Arc::clone(running);
+    let params = Arc::clone(params);
+    std::thread::spawn(move || control_loop_thread(transport, running, params, app));
 
-    // Initialize params
-    {
-        let mut params = state.mit_loop_params.lock().map_err(|e| e.to_string())?;
-        params.motor_id = motor_id;
-        params.freq_hz = frequency.max(1).min(2000);
+    log::info!("{} started: motor_id={}, freq={}Hz", P::NAME, motor_id, frequency);
+    Ok(())
+}
+
+/// Send `P::frame` at the configured rate until `running` clears, then `P::stop_frame`
+fn control_loop_thread<R: Runtime, P: ControlLoop>(
+    transport: Arc<dyn CanTransport>,
+    running: Arc<AtomicBool>,
+    params: Arc<Mutex<P>>,
+    app: AppHandle<R>,
+) {
+    use std::time::Instant;
+
+    log::info!("{} thread started", P::NAME);
+
+    while running.load(Ordering::SeqCst) {
+        let start = Instant::now();
+
+        let (frame, freq_hz) = match params.lock() {
+            Ok(p) => (p.frame(), p.freq_hz()),
+            Err(_) => break,
+        };
+
+        if let Err(e) = transport.send(&[frame]) {
+            log::error!("{} send error: {}", P::NAME, e);
+            // WSAECONNRESET after an ICMP unreachable is transient; anything else ends the loop
+            if e.raw_os_error() != Some(10054) {
+                let _ = app.emit("udp-error", format!("{} send error: {}", P::NAME, e));
+                break;
+            }
+        }
+
+        // The recv thread handles RX logging for the feedback frames
+
+        let interval = Duration::from_micros(1_000_000 / freq_hz.max(1) as u64);
+        let elapsed = start.elapsed();
+        if elapsed < interval {
+            std::thread::sleep(interval - elapsed);
+        }
     }
 
-    let running = Arc::clone(&state.mit_loop_running);
-    running.store(true, Ordering::SeqCst);
-    let params = Arc::clone(&state.mit_loop_params);
+    if let Ok(p) = params.lock() {
+        let _ = transport.send(&[p.stop_frame()]);
+    }
 
-    std::thread::spawn(move || {
-        mit_loop_thread(socket, running, params, app);
-    });
+    running.store(false, Ordering::SeqCst);
+    log::info!("{} thread exited", P::NAME);
+}
 
-    log::info!("MIT loop started: motor_id={}, freq={}Hz", motor_id, frequency);
-    Ok(())
+/// Start MIT high-frequency control loop thread
+#[tauri::command]
+pub fn udp_mit_loop_start<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    frequency: u32,
+) -> Result<(), String> {
+    start_control_loop(
+        &state,
+        app,
+        &state.mit_loop_running,
+        &state.priv_loop_running,
+        &state.mit_loop_params,
+        motor_id,
+        frequency,
+    )
 }
 
 /// Update MIT loop parameters (called from frontend sliders)
@@ -998,11 +1764,7 @@ pub fn udp_mit_loop_update(
     torque: f32,
 ) -> Result<(), String> {
     let mut params = state.mit_loop_params.lock().map_err(|e| e.to_string())?;
-    params.position = position;
-    params.velocity = velocity;
-    params.kp = kp;
-    params.kd = kd;
-    params.torque = torque;
+    params.target = ImpedanceTarget { position, velocity, kp, kd, torque };
     Ok(())
 }
 
@@ -1016,57 +1778,273 @@ pub fn udp_mit_loop_stop(
     Ok(())
 }
 
-/// MIT high-frequency loop thread
-fn mit_loop_thread(
-    socket: UdpSocket,
-    running: Arc<std::sync::atomic::AtomicBool>,
-    params: Arc<std::sync::Mutex<crate::state::MitLoopConfig>>,
-    app: AppHandle,
-) {
-    use std::time::Instant;
+/// Start private protocol type 1 high-frequency control loop thread
+#[tauri::command]
+pub fn udp_priv_loop_start<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    motor_id: u8,
+    frequency: u32,
+) -> Result<(), String> {
+    start_control_loop(
+        &state,
+        app,
+        &state.priv_loop_running,
+        &state.mit_loop_running,
+        &state.priv_loop_params,
+        motor_id,
+        frequency,
+    )
+}
 
-    log::info!("MIT loop thread started");
+/// Update private protocol loop parameters (called from frontend sliders)
+#[tauri::command]
+pub fn udp_priv_loop_update(
+    state: tauri::State<'_, AppState>,
+    position: f32,
+    velocity: f32,
+    kp: f32,
+    kd: f32,
+    torque: f32,
+) -> Result<(), String> {
+    let mut params = state.priv_loop_params.lock().map_err(|e| e.to_string())?;
+    params.target = ImpedanceTarget { position, velocity, kp, kd, torque };
+    Ok(())
+}
 
-    while running.load(Ordering::SeqCst) {
-        let start = Instant::now();
+/// Stop private protocol control loop
+#[tauri::command]
+pub fn udp_priv_loop_stop(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    state.priv_loop_running.store(false, Ordering::SeqCst);
+    log::info!("Private protocol loop stop requested");
+    Ok(())
+}
 
-        let (motor_id, position, velocity, kp, kd, torque, freq_hz) = {
-            let p = params.lock().unwrap();
-            (p.motor_id, p.position, p.velocity, p.kp, p.kd, p.torque, p.freq_hz)
-        };
+// ── CANopen (CiA 301 / CiA 402) ─────────────────────────────────────
+//
+// For motors switched to protocol 1. SDO responses are picked up by the recv
+// thread (handle_canopen_frame) and handed to the transfer waiting here.
 
-        let can_id = motor_protocol::make_can_id(0, motor_id);
-        let data = motor_protocol::cmd_mit_params(position, velocity, kp, kd, torque);
-        let frame = motor_protocol::build_can_frame(can_id, &data);
+/// Time to wait for each SDO response
+const SDO_TIMEOUT: Duration = Duration::from_millis(500);
+/// Statusword polls / controlword writes per CiA 402 transition request
+const CIA402_MAX_STEPS: usize = 16;
+const CIA402_POLL: Duration = Duration::from_millis(20);
 
-        if let Err(e) = socket.send(&frame) {
-            log::error!("MIT loop send error: {}", e);
-            // Don't log every failed frame to avoid flooding
-            if e.raw_os_error() != Some(10054) {
-                let _ = app.emit("udp-error", format!("MIT loop send error: {}", e));
-                break;
+/// Run one SDO transfer to completion, returning the uploaded bytes
+fn sdo_transfer<R: Runtime>(
+    state: &AppState,
+    app: &AppHandle<R>,
+    node_id: u8,
+    mut transfer: SdoTransfer,
+) -> Result<Vec<u8>, String> {
+    canopen::check_node_id(node_id)?;
+    let _guard = state.canopen.sdo_lock.lock().map_err(|e| e.to_string())?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    *state.canopen.sdo_waiter.lock().map_err(|e| e.to_string())? = Some(SdoWaiter { node_id, tx });
+
+    let result = (|| {
+        let mut request = transfer.initiate();
+        loop {
+            send_std_frame(state, app, canopen::COB_SDO_RX + node_id as u16, &request)?;
+            let Ok(resp) = rx.recv_timeout(SDO_TIMEOUT) else {
+                let (index, subindex) = transfer.object();
+                let abort = canopen::sdo_abort_frame(index, subindex, canopen::SDO_ABORT_TIMEOUT);
+                let _ = send_std_frame(state, app, canopen::COB_SDO_RX + node_id as u16, &abort);
+                return Err(format!(
+                    "SDO timeout: node {} did not answer for 0x{:04X}:{:02X}",
+                    node_id, index, subindex
+                ));
+            };
+            match transfer.on_response(&resp)? {
+                SdoStep::Send(next) => request = next,
+                SdoStep::Done(data) => return Ok(data),
             }
         }
+    })();
 
-        // Log TX frame (throttled: only log every 10th frame to avoid flooding CAN log)
-        // The recv thread handles RX logging for the feedback frames
+    if let Ok(mut waiter) = state.canopen.sdo_waiter.lock() {
+        *waiter = None;
+    }
+    result
+}
 
-        let interval = Duration::from_micros(1_000_000 / freq_hz.max(1) as u64);
-        let elapsed = start.elapsed();
-        if elapsed < interval {
-            std::thread::sleep(interval - elapsed);
-        }
+fn sdo_read_u16<R: Runtime>(state: &AppState, app: &AppHandle<R>, node_id: u8, index: u16, subindex: u8) -> Result<u16, String> {
+    let data = sdo_transfer(state, app, node_id, SdoTransfer::upload(index, subindex))?;
+    match data[..] {
+        [lo, hi, ..] => Ok(u16::from_le_bytes([lo, hi])),
+        _ => Err(format!("0x{:04X}:{:02X} returned {} bytes, expected 2", index, subindex, data.len())),
     }
+}
 
-    // Send stop command when loop exits
-    let can_id = {
-        let p = params.lock().unwrap();
-        motor_protocol::make_can_id(0, p.motor_id)
-    };
-    let stop_data = motor_protocol::cmd_stop();
-    let stop_frame = motor_protocol::build_can_frame(can_id, &stop_data);
-    let _ = socket.send(&stop_frame);
+/// NMT command to one node, or every node with node_id 0
+#[tauri::command]
+pub fn canopen_nmt<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    command: canopen::NmtCommand,
+) -> Result<(), String> {
+    if node_id != 0 {
+        canopen::check_node_id(node_id)?;
+    }
+    send_can_frame(&state, &app, canopen::COB_NMT, &canopen::nmt_frame(command, node_id))
+}
 
-    running.store(false, Ordering::SeqCst);
-    log::info!("MIT loop thread exited");
+#[tauri::command]
+pub fn canopen_sync<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    send_can_frame(&state, &app, canopen::COB_SYNC, &[])
+}
+
+/// SDO upload (read) of any object; expedited or segmented as the node chooses
+#[tauri::command(async)]
+pub fn canopen_sdo_upload<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    index: u16,
+    subindex: u8,
+) -> Result<Vec<u8>, String> {
+    sdo_transfer(&state, &app, node_id, SdoTransfer::upload(index, subindex))
+}
+
+/// SDO download (write); data up to 4 bytes is sent expedited, longer data segmented
+#[tauri::command(async)]
+pub fn canopen_sdo_download<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    index: u16,
+    subindex: u8,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("SDO download needs at least one byte".to_string());
+    }
+    sdo_transfer(&state, &app, node_id, SdoTransfer::download(index, subindex, data)).map(|_| ())
+}
+
+/// Remap one RPDO/TPDO through SDO. The node should be pre-operational.
+#[tauri::command(async)]
+pub fn canopen_pdo_configure<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    config: canopen::PdoConfig,
+) -> Result<(), String> {
+    canopen::check_node_id(node_id)?;
+    for (index, subindex, data) in canopen::pdo_mapping_writes(&config, node_id)? {
+        sdo_transfer(&state, &app, node_id, SdoTransfer::download(index, subindex, data))?;
+    }
+    log::info!("CANopen node {} {:?}{} mapped ({} entries)", node_id, config.kind, config.number, config.entries.len());
+    Ok(())
+}
+
+/// Send RPDO 1~4 to a node using the predefined connection set COB-ID
+#[tauri::command]
+pub fn canopen_rpdo_send<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    number: u8,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    canopen::check_node_id(node_id)?;
+    if !(1..=4).contains(&number) {
+        return Err(format!("Invalid PDO number {} (1~4)", number));
+    }
+    let cob_id = canopen::pdo_default_cob_id(canopen::PdoKind::Rpdo, number, node_id);
+    send_can_frame(&state, &app, cob_id, &data)
+}
+
+/// Set the node's producer heartbeat time (0x1017) and watch for it
+#[tauri::command(async)]
+pub fn canopen_heartbeat_configure<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    period_ms: u16,
+) -> Result<(), String> {
+    let data = period_ms.to_le_bytes().to_vec();
+    sdo_transfer(&state, &app, node_id, SdoTransfer::download(canopen::OD_PRODUCER_HEARTBEAT, 0, data))?;
+    let mut hb = state.canopen.heartbeat.lock().map_err(|e| e.to_string())?;
+    hb.set_expected(node_id, period_ms, std::time::Instant::now());
+    Ok(())
+}
+
+/// NMT state and liveness of every node that sent a heartbeat
+#[tauri::command]
+pub fn canopen_heartbeat_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<canopen::NodeHeartbeat>, String> {
+    let hb = state.canopen.heartbeat.lock().map_err(|e| e.to_string())?;
+    Ok(hb.status(std::time::Instant::now()))
+}
+
+fn cia402_status<R: Runtime>(state: &AppState, app: &AppHandle<R>, node_id: u8) -> Result<Cia402Status, String> {
+    let statusword = sdo_read_u16(state, app, node_id, canopen::OD_STATUSWORD, 0)?;
+    let mode_display = sdo_transfer(state, app, node_id, SdoTransfer::upload(canopen::OD_MODES_DISPLAY, 0))
+        .ok()
+        .and_then(|d| d.first().map(|&b| b as i8));
+    Ok(Cia402Status {
+        node_id,
+        statusword,
+        state: canopen::decode_statusword(statusword),
+        mode_display,
+    })
+}
+
+/// Statusword, decoded CiA 402 state and mode of operation display
+#[tauri::command(async)]
+pub fn canopen_cia402_status<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+) -> Result<Cia402Status, String> {
+    cia402_status(&state, &app, node_id)
+}
+
+/// Drive the CiA 402 state machine to `target`, one controlword at a time
+#[tauri::command(async)]
+pub fn canopen_cia402_transition<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    target: canopen::Cia402State,
+) -> Result<Cia402Status, String> {
+    let mut last = None;
+    for _ in 0..CIA402_MAX_STEPS {
+        let statusword = sdo_read_u16(&state, &app, node_id, canopen::OD_STATUSWORD, 0)?;
+        let current = canopen::decode_statusword(statusword)
+            .ok_or_else(|| format!("Node {} statusword 0x{:04X} is not a CiA 402 state", node_id, statusword))?;
+        last = Some(current);
+        match canopen::cia402_next_step(current, target)? {
+            Cia402Step::Done => return cia402_status(&state, &app, node_id),
+            Cia402Step::Wait => std::thread::sleep(CIA402_POLL),
+            Cia402Step::Write(cw) => {
+                let data = cw.to_le_bytes().to_vec();
+                sdo_transfer(&state, &app, node_id, SdoTransfer::download(canopen::OD_CONTROLWORD, 0, data))?;
+                std::thread::sleep(CIA402_POLL);
+            }
+        }
+    }
+    Err(format!("Node {} did not reach {:?} (last state {:?})", node_id, target, last))
+}
+
+/// Write modes of operation (0x6060): 1=PP, 3=PV, 4=PT, 6=Homing, 8=CSP, 9=CSV, 10=CST
+#[tauri::command(async)]
+pub fn canopen_cia402_set_mode<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    node_id: u8,
+    mode: i8,
+) -> Result<(), String> {
+    let data = vec![mode as u8];
+    sdo_transfer(&state, &app, node_id, SdoTransfer::download(canopen::OD_MODES_OF_OPERATION, 0, data)).map(|_| ())
 }
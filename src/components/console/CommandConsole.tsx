@@ -2,6 +2,7 @@ import { useState, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { useImuStore } from "../../stores/imu-store";
+import { DEFAULT_SESSION } from "../../types/imu";
 
 export function CommandConsole() {
   const [input, setInput] = useState("");
@@ -11,8 +12,9 @@ export function CommandConsole() {
   const addConsoleLine = useImuStore((s) => s.addConsoleLine);
 
   useEffect(() => {
-    const unlisten = listen<string>("serial-response", (event) => {
-      addConsoleLine(`< ${event.payload}`);
+    const unlisten = listen<{ session_id: string; line: string }>("serial-response", (event) => {
+      if (event.payload.session_id !== DEFAULT_SESSION) return;
+      addConsoleLine(`< ${event.payload.line}`);
     });
     return () => { unlisten.then((fn) => fn()); };
   }, [addConsoleLine]);
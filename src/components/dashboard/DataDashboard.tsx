@@ -105,7 +105,7 @@ export function DataDashboard() {
             <span className="text-yellow-400">T</span>
             <span>{latest.temperature}°C</span>
             <span className="text-yellow-400">P</span>
-            <span>{fmt(latest.air_pressure / 1000, 2)} kPa</span>
+            <span>{latest.air_pressure === null ? "---" : fmt(latest.air_pressure / 1000, 2)} kPa</span>
             <span className="text-yellow-400">t</span>
             <span>{(latest.system_time / 1000).toFixed(1)}s</span>
           </div>
@@ -1,7 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useMotorStore } from "../../stores/motor-store";
-import type { ParamDef } from "../../types/motor";
+import type { ParamDef, ParamValue, StagedParam } from "../../types/motor";
 
 export function MotorParamsPanel() {
   const connected = useMotorStore((s) => s.connected);
@@ -23,12 +23,27 @@ export function MotorParamsPanel() {
   const [activeTab, setActiveTab] = useState<"params" | "config" | "faults">("params");
   const [editValues, setEditValues] = useState<Record<number, string>>({});
   const [readingAll, setReadingAll] = useState(false);
+  const [dirty, setDirty] = useState<StagedParam[]>([]);
+  const [paramStatus, setParamStatus] = useState("");
 
   // Load param table on mount
   useEffect(() => {
     invoke<ParamDef[]>("get_param_table").then(setParamTable).catch(console.error);
   }, [setParamTable]);
 
+  // Writes staged in RAM but not yet saved to flash
+  const refreshDirty = useCallback(async () => {
+    try {
+      setDirty(await invoke<StagedParam[]>("priv_param_dirty", { motorId }));
+    } catch (e) {
+      console.error("dirty set failed:", e);
+    }
+  }, [motorId]);
+
+  useEffect(() => {
+    refreshDirty();
+  }, [refreshDirty]);
+
   const writableParams = paramTable.filter((p) => p.access === "RW");
   const readonlyParams = paramTable.filter((p) => p.access === "R");
 
@@ -67,23 +82,52 @@ export function MotorParamsPanel() {
     };
     const paramType = typeMap[p.paramType] || "f32";
 
+    const confirm = p.confirm && window.confirm(
+      `${p.name} changes protocol-level behaviour. Write ${val}?`,
+    );
+    if (p.confirm && !confirm) return;
+
     try {
-      await invoke("priv_param_write", { motorId, index: p.index, paramType, valueF64: val });
-      await new Promise((r) => setTimeout(r, 20));
-      await invoke("priv_param_read", { motorId, index: p.index });
+      // Range-checked and read back by the backend
+      await invoke("priv_param_write", { motorId, index: p.index, paramType, valueF64: val, confirm });
+      setParamStatus("");
     } catch (e) {
       console.error("param write failed:", p.name, e);
+      setParamStatus(`Error: ${e}`);
     }
-  }, [editValues, motorId]);
+    refreshDirty();
+  }, [editValues, motorId, refreshDirty]);
 
   const saveToFlash = useCallback(async () => {
     if (!connected) return;
     try {
-      await invoke("priv_save_params", { motorId });
+      const saved = await invoke<StagedParam[]>("priv_save_params", { motorId });
+      setParamStatus(`Saved to flash (${saved.length} staged write${saved.length === 1 ? "" : "s"}).`);
     } catch (e) {
       console.error("save params failed:", e);
+      setParamStatus(`Error: ${e}`);
     }
-  }, [connected, motorId]);
+    refreshDirty();
+  }, [connected, motorId, refreshDirty]);
+
+  // Write staged parameters back to their original values (all when names is omitted)
+  const revertParams = useCallback(async (names?: string[]) => {
+    if (!connected) return;
+    try {
+      const reverted = await invoke<StagedParam[]>("priv_param_revert", { motorId, params: names ?? null });
+      setParamStatus(`Reverted ${reverted.map((s) => s.name).join(", ") || "nothing"}.`);
+    } catch (e) {
+      console.error("revert params failed:", e);
+      setParamStatus(`Error: ${e}`);
+    }
+    refreshDirty();
+  }, [connected, motorId, refreshDirty]);
+
+  const stagedValue = (v: ParamValue | null) => {
+    if (!v) return "?";
+    const n = Object.values(v)[0] as number;
+    return "F32" in v ? n.toFixed(4) : n.toString();
+  };
 
   const formatValue = (p: ParamDef, index: number) => {
     const v = paramValues[index];
@@ -150,6 +194,50 @@ export function MotorParamsPanel() {
               </button>
             </div>
 
+            {paramStatus && (
+              <p className={`text-[10px] mb-2 ${paramStatus.startsWith("Error") ? "text-red-400" : "text-amber-400"}`}>
+                {paramStatus}
+              </p>
+            )}
+
+            {/* Unsaved writes (RAM only until Save to Flash) */}
+            {dirty.length > 0 && (
+              <div className="mb-2 bg-amber-950/30 border border-amber-900/50 rounded p-1.5">
+                <div className="flex items-center text-[10px] text-amber-400 font-medium mb-1">
+                  Unsaved Changes ({dirty.length})
+                  <button
+                    className="ml-auto text-[9px] text-zinc-400 hover:text-zinc-200 disabled:opacity-40"
+                    disabled={!connected}
+                    onClick={() => revertParams()}
+                  >
+                    Revert All
+                  </button>
+                </div>
+                <table className="w-full text-[10px] font-mono">
+                  <tbody>
+                    {dirty.map((s) => (
+                      <tr key={s.index} className="border-b border-zinc-800/50">
+                        <td className="py-0.5 px-1 text-zinc-300">{s.name}</td>
+                        <td className="py-0.5 px-1 text-right text-zinc-500">{stagedValue(s.original)}</td>
+                        <td className="py-0.5 px-1 text-zinc-600 w-4">→</td>
+                        <td className="py-0.5 px-1 text-amber-300">{stagedValue(s.value)}</td>
+                        <td className="py-0.5 px-1 text-center w-12">
+                          <button
+                            className="text-[9px] text-zinc-400 hover:text-zinc-200 disabled:opacity-40"
+                            disabled={!connected || !s.original}
+                            onClick={() => revertParams([s.name])}
+                            title={s.original ? "Write the original value back" : "No original value was read"}
+                          >
+                            Revert
+                          </button>
+                        </td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              </div>
+            )}
+
             {/* Writable params */}
             <div className="text-[10px] text-zinc-500 font-medium mb-1">Writable Parameters</div>
             <table className="w-full text-[10px] font-mono">
@@ -445,7 +533,8 @@ export function MotorParamsPanel() {
                       await invoke("priv_set_can_id", { motorId, newId: id });
                       setCanIdStatus("Set CAN ID sent. Saving to flash...");
                       await new Promise((r) => setTimeout(r, 50));
-                      await invoke("priv_save_params", { motorId });
+                      // The motor now answers on the new ID; staged writes moved with it
+                      await invoke("priv_save_params", { motorId: id });
                       // Sync backend config to the new motor ID
                       await invoke("udp_update_motor_ids", { motorId: id, masterId: config.master_id }).catch(() => {});
                       setCanIdStatus(`CAN ID → ${id} saved. Motor ID updated. Power cycle the motor to apply.`);
@@ -1,7 +1,7 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { PortInfo } from "../../types/imu";
+import { DEFAULT_SESSION, type PortInfo } from "../../types/imu";
 import { useImuStore } from "../../stores/imu-store";
 import { cn } from "../../lib/utils";
 
@@ -34,8 +34,9 @@ export function ConnectionToolbar() {
 
   useEffect(() => {
     refreshPorts();
-    const unlisten = listen<string>("serial-error", (event) => {
-      setError(event.payload);
+    const unlisten = listen<{ session_id: string; message: string }>("serial-error", (event) => {
+      if (event.payload.session_id !== DEFAULT_SESSION) return;
+      setError(event.payload.message);
       setConnected(false);
     });
     return () => { unlisten.then((fn) => fn()); };
@@ -1,6 +1,6 @@
 import { useEffect, useRef } from "react";
 import { listen } from "@tauri-apps/api/event";
-import type { ImuData } from "../types/imu";
+import { DEFAULT_SESSION, type ImuData } from "../types/imu";
 import { useImuStore } from "../stores/imu-store";
 
 export function useImuData() {
@@ -16,6 +16,7 @@ export function useImuData() {
   useEffect(() => {
     // Listen to imu-data events from Rust backend
     const unlisten = listen<ImuData>("imu-data", (event) => {
+      if (event.payload.session_id && event.payload.session_id !== DEFAULT_SESSION) return;
       // Buffer the latest data; only push to store at 60fps
       pendingData.current = event.payload;
       frameCountRef.current++;
@@ -7,11 +7,22 @@ export interface ImuData {
   yaw: number;
   quat: [number, number, number, number];
   temperature: number;
-  air_pressure: number;
+  /** Pa; null when the packet has no pressure (HI92) */
+  air_pressure: number | null;
   system_time: number;
+  /** Gravity-free body acceleration (host-computed, per output profile) */
+  linear_acc?: [number, number, number];
+  /** IMU session that produced the sample */
+  session_id?: string;
 }
 
+/** Session used by the single-IMU dashboard */
+export const DEFAULT_SESSION = "default";
+
 export interface PortInfo {
   name: string;
   port_type: string;
+  vid: number | null;
+  pid: number | null;
+  serial_number: string | null;
 }
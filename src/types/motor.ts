@@ -44,6 +44,29 @@ export interface ParamDef {
   paramType: string; // "U8" | "U16" | "U32" | "I16" | "F32"
   access: string;    // "R" | "W" | "RW"
   defaultStr: string;
+  range: ParamRange;
+  confirm: boolean;  // changes protocol behaviour; write needs confirmation
+}
+
+export type ParamRange =
+  | { kind: "any" }
+  | { kind: "between"; min: number; max: number }
+  | { kind: "at_least"; min: number }
+  | { kind: "one_of"; values: number[] };
+
+/** Typed parameter value as serialized by the backend, e.g. { F32: 30 } */
+export type ParamValue =
+  | { U8: number }
+  | { U16: number }
+  | { U32: number }
+  | { I16: number }
+  | { F32: number };
+
+export interface StagedParam {
+  index: number;
+  name: string;
+  original: ParamValue | null; // last read before the first write
+  value: ParamValue;           // in RAM, not yet saved to flash
 }
 
 export interface PrivateFeedback {